@@ -1,9 +1,9 @@
 use std::sync::Arc;
-use druid::{AppLauncher, Widget, WidgetExt, WindowDesc, Data, Lens};
+use druid::{AppLauncher, Color, Widget, WidgetExt, WindowDesc, Data, Lens};
 use druid::im::Vector;
 use druid::lens::Identity;
 use druid::widget::{Axis, Button, Flex, Label, Slider, TextBox};
-use druid_table::{HeaderData, HeaderTable};
+use druid_table::{AxisPart, FormatRule, FormattedLine, HeaderData, HeaderTable, NumberFormat, WidgetTableLine};
 
 #[derive(Clone, Data, Lens)]
 struct AppData {
@@ -15,12 +15,26 @@ fn root_widget() -> impl Widget<Vector<AppData>> {
 
     let table = HeaderTable::new_static(Axis::Vertical, 20.0)
         .with_line(Identity, AppData::name, ||TextBox::multiline(), Label::new("Name".to_string()))
-        .with_line(Identity, AppData::count, ||Slider::new().with_range(0.0, 10.0), Label::new("Value".to_string()))
+        .with_line_part(Identity, AppData::count, ||Slider::new().with_range(0.0, 10.0), AxisPart::default().with_min(120.0), Label::new("Value".to_string()))
         .with_line(Identity, AppData::name, ||TextBox::multiline(), Label::new("Name".to_string()))
-        .with_line(Identity, AppData::count, ||Slider::new().with_range(0.0, 10.0), Label::new("Value".to_string()))
+        .with_custom_line(
+            WidgetTableLine::new(Identity, AppData::count, ||Label::dynamic(|count: &f64, _|format!("{:.1}", count)))
+                .with_rule(FormatRule::new(|count: &f64|*count > 5.0).with_background(Color::rgb8(0xE0, 0xC0, 0x40)).with_text_color(Color::BLACK)),
+            Label::new("Value".to_string())
+        )
+        .with_custom_line(FormattedLine::editable(Identity, AppData::count, NumberFormat::new(2).with_suffix(" pt"))
+            .with_validator(|count: &f64|if *count < 1.0 {Err("at least one point is required".to_string())} else {Ok(())}), Label::new("Points".to_string()))
         .with_element_header(||{
             Box::new(Label::dynamic(|data: &HeaderData<_>, _|data.index().to_string()))
-        }, 30.0);
+        }, 30.0)
+        .with_element_source(Identity)
+        .with_table(|table|{
+            table.enable_history();
+            table.enable_fill_handle();
+            table.set_change_tracking(Identity, |data: &AppData|data.name.clone());
+        })
+        .with_line_chooser(|line|["Name", "Value", "Name 2", "Value 2", "Points"][line].to_string())
+        .with_search_bar();
     Flex::column()
         .with_flex_child(table, 1.0)
         .with_child(
@@ -53,6 +67,10 @@ fn main() {
 
     AppLauncher::with_window(window)
         .log_to_console()
+        .configure_env(|env, _|{
+            let hover = env.get(druid::theme::TEXT_COLOR).with_alpha(0.08);
+            env.set(druid_table::theme::HOVER_COLOR, hover);
+        })
         .launch(data)
         .expect("could not launch druid")
 }
\ No newline at end of file
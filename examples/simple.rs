@@ -2,8 +2,8 @@ use std::sync::Arc;
 use druid::{AppLauncher, Widget, WidgetExt, WindowDesc, Data, Lens, ArcStr};
 use druid::im::Vector;
 use druid::lens::Identity;
-use druid::widget::{Axis, Button, Flex, Slider, TextBox};
-use druid_table::{Static, Table};
+use druid::widget::{Axis, Button, Flex, Label, Slider, TextBox};
+use druid_table::{BackgroundPainter, Static, Table};
 
 #[derive(Clone, Data, Lens)]
 struct AppData {
@@ -13,8 +13,9 @@ struct AppData {
 
 fn root_widget() -> impl Widget<Vector<AppData>> {
     let table = Table::new_static(Axis::Vertical)
-        .with_line(Identity, AppData::name, ||TextBox::multiline())
-        .with_line(Identity, AppData::count, ||Slider::new().with_range(0.0, 10.0));
+        .with_edit_line(Identity, AppData::name, ||Label::dynamic(|name: &Arc<String>, _|name.to_string()), ||TextBox::new())
+        .with_line(Identity, AppData::count, ||Slider::new().with_range(0.0, 10.0))
+        .with_painter(BackgroundPainter::new().with_stripes(true));
     Flex::column()
         .with_child(table)
         .with_child(
@@ -47,6 +48,10 @@ fn main() {
 
     AppLauncher::with_window(window)
         .use_simple_logger()
+        .configure_env(|env, _|{
+            let hover = env.get(druid::theme::TEXT_COLOR).with_alpha(0.08);
+            env.set(druid_table::theme::HOVER_COLOR, hover);
+        })
         .launch(data)
         .expect("could not launch druid")
 }
\ No newline at end of file
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use druid::{AppLauncher, Widget, WidgetExt, WindowDesc, Data, Lens, LensExt};
+use druid::im::Vector;
+use druid::lens::Identity;
+use druid::widget::{Axis, Label};
+use druid_table::{HeaderTable, TreeLens, TreeNode, TreeRow};
+
+#[derive(Clone, Data, Lens)]
+struct FileNode {
+    name: Arc<String>,
+    size: f64,
+    expanded: bool,
+    children: Vector<FileNode>,
+}
+
+impl TreeNode for FileNode {
+    fn children(&self) -> &Vector<Self> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vector<Self> {
+        &mut self.children
+    }
+
+    fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        self.expanded = expanded;
+    }
+}
+
+fn file(name: &str, size: f64) -> FileNode {
+    FileNode {
+        name: Arc::new(name.to_string()),
+        size,
+        expanded: false,
+        children: Vector::new(),
+    }
+}
+
+fn folder(name: &str, children: Vec<FileNode>) -> FileNode {
+    FileNode {
+        name: Arc::new(name.to_string()),
+        size: children.iter().map(|child|child.size).sum(),
+        expanded: true,
+        children: children.into_iter().collect(),
+    }
+}
+
+fn root_widget() -> impl Widget<Vector<FileNode>> {
+    HeaderTable::new_static(Axis::Vertical, 20.0)
+        .with_tree_line(Identity, FileNode::name, ||Label::dynamic(|name: &Arc<String>, _|name.to_string()), Label::new("Name"))
+        .with_line(TreeLens::new(Identity), TreeRow::node_lens.then(FileNode::size), ||Label::dynamic(|size: &f64, _|format!("{} kB", size)), Label::new("Size"))
+        .center()
+}
+
+fn main() {
+    let mut data = Vector::new();
+    data.push_back(folder("src", vec![
+        file("lib.rs", 1.0),
+        folder("widgets", vec![file("table.rs", 6.0), file("head.rs", 11.0)]),
+    ]));
+    data.push_back(file("Cargo.toml", 0.5));
+
+    let window = WindowDesc::new(root_widget())
+        .title("tree table");
+
+    AppLauncher::with_window(window)
+        .log_to_console()
+        .configure_env(|env, _|{
+            let hover = env.get(druid::theme::TEXT_COLOR).with_alpha(0.08);
+            env.set(druid_table::theme::HOVER_COLOR, hover);
+        })
+        .launch(data)
+        .expect("could not launch druid")
+}
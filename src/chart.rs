@@ -0,0 +1,362 @@
+use std::any::Any;
+use std::marker::PhantomData;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use druid::{Color, Env, Event, EventCtx, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Rect, Size, UpdateCtx, Data, RenderContext};
+use druid::im::Vector;
+use druid::kurbo::BezPath;
+use druid::piet::{Text, TextLayout, TextLayoutBuilder};
+use druid::theme;
+use druid::widget::ListIter;
+use crate::{TableLayout, TableLine};
+use crate::line::{diff_line, line_texts, line_value, line_values, same_line_value, TextFn};
+use crate::theme::{chart_inset, grid_color, grid_width};
+
+/// A small visualization painted into a cell.
+pub trait CellChart<V> {
+    /// The range of the value of a cell, the ranges of all cells of a line form its scale.
+    /// `None` if the value does not contribute to the scale.
+    fn extent(&self, value: &V) -> Option<(f64, f64)>;
+
+    fn paint(&self, ctx: &mut PaintCtx, rect: Rect, value: &V, scale: (f64, f64), env: &Env);
+
+    fn size(&self) -> Size {
+        Size::new(80.0, 20.0)
+    }
+}
+
+/// A line which paints a chart in every cell instead of using widgets.
+pub struct ChartTableLine<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+    V: Data,
+
+    L1: Lens<S, T>,
+    L2: Lens<U, V>,
+    C: CellChart<V>,
+> {
+    outer_lens: L1,
+    inner_lens: L2,
+    chart: C,
+    /// The values the scale was computed from.
+    values: Option<T>,
+    scale: (f64, f64),
+    text: Option<TextFn<V>>,
+    phantom: PhantomData<(S, T, U, V)>,
+}
+
+/// A horizontal bar proportional to the value, the bars of a line start at zero.
+pub struct DataBar {
+    color: Option<Color>,
+}
+
+/// A line chart of a series of values.
+pub struct Sparkline {
+    color: Option<Color>,
+}
+
+/// A bar and percentage for a value between 0 and 1.
+pub struct ProgressBar {
+    color: Option<Color>,
+}
+
+impl<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+    V: Data,
+
+    L1: Lens<S, T>,
+    L2: Lens<U, V>,
+    C: CellChart<V>,
+> ChartTableLine<S, T, U, V, L1, L2, C> {
+    pub fn new(outer_lens: L1, inner_lens: L2, chart: C) -> Self {
+        Self {
+            outer_lens,
+            inner_lens,
+            chart,
+            values: None,
+            scale: (0.0, 1.0),
+            text: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the text of a value, which is searched and copied instead of the chart.
+    pub fn with_text(mut self, text: impl Fn(&V) -> String + 'static) -> Self {
+        self.text = Some(Box::new(text));
+        self
+    }
+
+    fn compute_scale(&self, data: &S) -> (f64, f64) {
+        let Self {outer_lens, inner_lens, chart, ..} = self;
+        let mut scale = (f64::INFINITY, f64::NEG_INFINITY);
+        outer_lens.with(data, |data|data.for_each(|data, _|inner_lens.with(data, |data|{
+            if let Some((min, max)) = chart.extent(data) {
+                scale = (scale.0.min(min), scale.1.max(max));
+            }
+        })));
+        if scale.0 > scale.1 {(0.0, 1.0)} else {scale}
+    }
+}
+
+impl<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+    V: Data,
+
+    L1: Lens<S, T>,
+    L2: Lens<U, V>,
+    C: CellChart<V>,
+> TableLine<S> for ChartTableLine<S, T, U, V, L1, L2, C> {
+    fn event(&mut self, _: &mut EventCtx, _: &Event, _: &mut S, _: &Env, _: &TableLayout, _: usize) {}
+
+    fn lifecycle(&mut self, _: &mut LifeCycleCtx, event: &LifeCycle, data: &S, _: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.values = Some(self.outer_lens.with(data, |values|values.clone()));
+            self.scale = self.compute_scale(data);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, data: &S, _: &Env) {
+        let values = self.outer_lens.with(data, |values|values.clone());
+        if self.values.as_ref().map_or(true, |old|!old.same(&values)) {
+            // The scale depends on all cells, therefore the whole line is painted again
+            self.scale = self.compute_scale(data);
+            self.values = Some(values);
+            ctx.request_paint();
+        }
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        let visible = ctx.region().bounding_box();
+        let inset = chart_inset(env);
+
+        let Self {outer_lens, inner_lens, chart, scale, ..} = self;
+        let scale = *scale;
+        outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
+            if meta.is_covered(line_index, index) {
+                return;
+            }
+            let rect = meta.content_rect(line_index, index);
+            if rect.intersect(visible).area() > 0.0 {
+                ctx.with_save(|ctx|{
+                    ctx.clip(rect);
+                    chart.paint(ctx, rect.inset(-inset), data, scale, env);
+                });
+            }
+        })));
+    }
+
+    fn layout(&mut self, _: &mut LayoutCtx, data: &S, _: &Env, meta: &mut TableLayout, line_index: usize) {
+        let Self {outer_lens, chart, ..} = self;
+        let size = chart.size();
+        outer_lens.with(data, |data|data.for_each(|_, index|{
+            if !meta.is_covered(line_index, index) {
+                meta.layout(line_index, index, |bc|bc.constrain(size));
+            }
+        }));
+    }
+
+    fn arrange(&mut self, _: &mut LayoutCtx, _: &S, _: &Env, _: &TableLayout, _: usize) {}
+
+    fn element_count(&self, data: &S) -> usize {
+        self.outer_lens.with(data, |data|data.data_len())
+    }
+
+    fn changes(&self, baseline: &S, data: &S, elements: &[Option<usize>]) -> Vec<(usize, Rc<dyn Any>, Rc<dyn Any>)> {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with(baseline, |baseline|outer_lens.with(data, |data|diff_line(baseline, data, inner_lens, elements)))
+    }
+
+    fn same_value(&self, a: &dyn Any, b: &dyn Any) -> bool {
+        same_line_value::<V>(a, b)
+    }
+
+    fn value(&self, data: &S, element: usize) -> Option<Rc<dyn Any>> {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with(data, |data|line_value(data, inner_lens, element))
+    }
+
+    fn values(&self, data: &S, elements: RangeInclusive<usize>) -> Vec<(usize, Rc<dyn Any>)> {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with(data, |data|line_values(data, inner_lens, elements))
+    }
+
+    fn texts(&self, data: &S) -> Vec<(usize, String)> {
+        let Self {outer_lens, inner_lens, text, ..} = self;
+        outer_lens.with(data, |data|line_texts(data, inner_lens, text.as_ref()))
+    }
+}
+
+fn scaled(value: f64, scale: (f64, f64)) -> f64 {
+    if scale.1 > scale.0 {(value - scale.0) / (scale.1 - scale.0)} else {0.0}
+}
+
+impl DataBar {
+    pub fn new() -> Self {
+        Self {
+            color: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+impl Default for DataBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CellChart<f64> for DataBar {
+    fn extent(&self, value: &f64) -> Option<(f64, f64)> {
+        Some((value.min(0.0), value.max(0.0))).filter(|_|value.is_finite())
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx, rect: Rect, value: &f64, scale: (f64, f64), env: &Env) {
+        let zero = rect.x0 + scaled(0.0, scale) * rect.width();
+        let end = rect.x0 + scaled(*value, scale) * rect.width();
+        let bar = Rect::new(zero.min(end), rect.y0, zero.max(end), rect.y1);
+        let color = self.color.clone().unwrap_or_else(||env.get(theme::PRIMARY_LIGHT));
+        ctx.fill(bar, &color);
+    }
+}
+
+impl Sparkline {
+    pub fn new() -> Self {
+        Self {
+            color: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+impl Default for Sparkline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CellChart<Vector<f64>> for Sparkline {
+    fn extent(&self, value: &Vector<f64>) -> Option<(f64, f64)> {
+        value.iter()
+            .filter(|value|value.is_finite())
+            .fold(None, |extent, value|Some(match extent {
+                Some((min, max)) => (value.min(min), value.max(max)),
+                None => (*value, *value),
+            }))
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx, rect: Rect, value: &Vector<f64>, scale: (f64, f64), env: &Env) {
+        if value.len() < 2 {
+            return;
+        }
+        let step = rect.width() / (value.len() - 1) as f64;
+        let mut path = BezPath::new();
+        for (index, value) in value.iter().enumerate() {
+            let point = (rect.x0 + index as f64 * step, rect.y1 - scaled(*value, scale) * rect.height());
+            if index == 0 {
+                path.move_to(point);
+            } else {
+                path.line_to(point);
+            }
+        }
+        let color = self.color.clone().unwrap_or_else(||env.get(theme::TEXT_COLOR));
+        ctx.stroke(path, &color, 1.0);
+    }
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        Self {
+            color: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CellChart<f64> for ProgressBar {
+    fn extent(&self, _: &f64) -> Option<(f64, f64)> {
+        // Progress always uses the fixed scale from 0 to 1
+        None
+    }
+
+    fn paint(&self, ctx: &mut PaintCtx, rect: Rect, value: &f64, _: (f64, f64), env: &Env) {
+        let progress = value.max(0.0).min(1.0);
+        let color = self.color.clone().unwrap_or_else(||env.get(theme::PRIMARY_LIGHT));
+        ctx.fill(Rect::new(rect.x0, rect.y0, rect.x0 + progress * rect.width(), rect.y1), &color.with_alpha(0.6));
+        ctx.stroke(rect, &grid_color(env), grid_width(env));
+
+        let font = env.get(theme::UI_FONT);
+        let text = ctx.text()
+            .new_text_layout(format!("{:.0}%", progress * 100.0))
+            .font(font.family, font.size)
+            .text_color(env.get(theme::TEXT_COLOR))
+            .build();
+        if let Ok(text) = text {
+            let size = text.size();
+            ctx.draw_text(&text, (rect.center().x - size.width / 2.0, rect.center().y - size.height / 2.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::lens::Identity;
+    use super::*;
+
+    #[test]
+    fn data_bars_extend_from_zero() {
+        let bar = DataBar::new();
+        assert_eq!(bar.extent(&5.0), Some((0.0, 5.0)));
+        assert_eq!(bar.extent(&-1.5), Some((-1.5, 0.0)));
+        assert_eq!(bar.extent(&f64::NAN), None);
+        assert_eq!(bar.extent(&f64::INFINITY), None);
+    }
+
+    #[test]
+    fn scale_covers_the_extents_of_the_line() {
+        let data: Vector<f64> = vec![3.0, -2.0, f64::NAN, 1.0].into();
+        let line = ChartTableLine::new(Identity, Identity, DataBar::new());
+        assert_eq!(line.compute_scale(&data), (-2.0, 3.0));
+
+        // Without any extent the scale falls back to 0 to 1
+        let line = ChartTableLine::new(Identity, Identity, ProgressBar::new());
+        assert_eq!(line.compute_scale(&data), (0.0, 1.0));
+        let line = ChartTableLine::new(Identity, Identity, DataBar::new());
+        assert_eq!(line.compute_scale(&Vector::new()), (0.0, 1.0));
+
+        let series: Vector<Vector<f64>> = vec![vec![1.0, 4.0].into(), vec![-3.0].into()].into();
+        let line = ChartTableLine::new(Identity, Identity, Sparkline::new());
+        assert_eq!(line.compute_scale(&series), (-3.0, 4.0));
+    }
+
+    #[test]
+    fn values_are_searchable() {
+        let data: Vector<f64> = vec![0.25, 0.5].into();
+        let line = ChartTableLine::new(Identity, Identity, ProgressBar::new())
+            .with_text(|value: &f64|format!("{:.0}%", value * 100.0));
+        assert_eq!(line.texts(&data), vec![(0, "25%".to_string()), (1, "50%".to_string())]);
+        assert_eq!(line.value(&data, 1).and_then(|value|value.downcast_ref::<f64>().copied()), Some(0.5));
+    }
+}
@@ -0,0 +1,139 @@
+use std::cell::Cell;
+use druid::{Env, PaintCtx, Point, Rect, RenderContext, Size};
+use druid::kurbo::BezPath;
+use druid::piet::{Text, TextLayout, TextLayoutBuilder};
+use druid::theme;
+use crate::TableLayout;
+use crate::theme::{cell_padding, grid_color, grid_width, header_background, is_visible};
+
+/// Popup of a `HeaderTable` listing all lines with a checkbox to hide or show them.
+pub(crate) struct LineChooser {
+    names: Box<dyn Fn(usize) -> String>,
+    origin: Option<Point>,
+    /// Width of the popup and height of a row, measured when the popup is painted.
+    row_size: Cell<Size>,
+}
+
+impl LineChooser {
+    /// `names` returns the name of a line by the index in which the line was added.
+    pub fn new(names: impl Fn(usize) -> String + 'static) -> Self {
+        Self {
+            names: Box::new(names),
+            origin: None,
+            row_size: Cell::new(Size::ZERO),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.origin.is_some()
+    }
+
+    pub fn open(&mut self, origin: Point) {
+        self.origin = Some(origin);
+    }
+
+    pub fn close(&mut self) {
+        self.origin = None;
+    }
+
+    pub fn rect(&self, lines: usize) -> Option<Rect> {
+        let row_size = self.row_size.get();
+        self.origin.map(|origin|Rect::from_origin_size(origin, Size::new(row_size.width, row_size.height * lines as f64)))
+    }
+
+    /// Returns the line of the row at the given position.
+    pub fn line_at(&self, lines: usize, pos: Point) -> Option<usize> {
+        let rect = self.rect(lines).filter(|rect|rect.contains(pos))?;
+        Some(((pos.y - rect.y0) / self.row_size.get().height) as usize).filter(|line|*line < lines)
+    }
+
+    /// Paints the popup above the table, so that it is neither clipped nor covered by the cells.
+    pub fn paint(&self, ctx: &mut PaintCtx, env: &Env, layout: &TableLayout, line_order: &[usize]) {
+        let origin = match self.origin {
+            Some(origin) => origin,
+            None => return,
+        };
+        let text_color = env.get(theme::TEXT_COLOR);
+        let padding = cell_padding(env) + env.get(theme::WIDGET_PADDING_HORIZONTAL);
+        let row_height = env.get(theme::BASIC_WIDGET_HEIGHT) + 2.0 * cell_padding(env);
+        let check_size = row_height / 2.0;
+        let font = env.get(theme::UI_FONT);
+
+        let rows: Vec<_> = layout.lines().parts().iter().enumerate()
+            .map(|(index, part)|{
+                let name = line_order.get(index).map(|id|(self.names)(*id)).unwrap_or_default();
+                let text = ctx.text()
+                    .new_text_layout(name)
+                    .font(font.family.clone(), font.size)
+                    .text_color(text_color.clone())
+                    .build()
+                    .ok();
+                (!part.is_hidden(), text)
+            })
+            .collect();
+        let text_width = rows.iter()
+            .filter_map(|(_, text)|text.as_ref())
+            .map(|text|text.size().width)
+            .fold(0.0, f64::max);
+        let row_size = Size::new(check_size + text_width + 3.0 * padding, row_height);
+        self.row_size.set(row_size);
+
+        let rect = Rect::from_origin_size(origin, Size::new(row_size.width, row_height * rows.len() as f64));
+        let background = header_background(env);
+        let background = if is_visible(&background) { background } else { env.get(theme::BACKGROUND_LIGHT) };
+        let (border, border_width) = (grid_color(env), grid_width(env));
+
+        ctx.paint_with_z_index(1, move|ctx|{
+            ctx.fill(rect, &background);
+            if border_width > 0.0 {
+                ctx.stroke(rect.inset(-border_width / 2.0), &border, border_width);
+            }
+
+            for (index, (checked, text)) in rows.iter().enumerate() {
+                let row = Rect::from_origin_size(
+                    Point::new(rect.x0, rect.y0 + index as f64 * row_height),
+                    row_size,
+                );
+
+                let check = Rect::from_center_size(
+                    Point::new(row.x0 + padding + check_size / 2.0, row.center().y),
+                    Size::new(check_size, check_size),
+                );
+                ctx.stroke(check, &text_color, 1.0);
+                if *checked {
+                    let mut path = BezPath::new();
+                    path.move_to((check.x0 + 2.5, check.center().y));
+                    path.line_to((check.center().x - 1.0, check.y1 - 2.5));
+                    path.line_to((check.x1 - 2.0, check.y0 + 2.5));
+                    ctx.stroke(path, &text_color, 1.5);
+                }
+
+                if let Some(text) = text {
+                    let y = row.center().y - text.size().height / 2.0;
+                    ctx.draw_text(text, (check.x1 + padding, y));
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_at_uses_the_painted_row_size() {
+        let mut chooser = LineChooser::new(|line|line.to_string());
+        chooser.open(Point::new(10.0, 20.0));
+        assert_eq!(chooser.line_at(3, Point::new(15.0, 25.0)), None);
+
+        chooser.row_size.set(Size::new(100.0, 20.0));
+        assert_eq!(chooser.rect(3), Some(Rect::new(10.0, 20.0, 110.0, 80.0)));
+        assert_eq!(chooser.line_at(3, Point::new(15.0, 25.0)), Some(0));
+        assert_eq!(chooser.line_at(3, Point::new(15.0, 65.0)), Some(2));
+        assert_eq!(chooser.line_at(3, Point::new(115.0, 25.0)), None);
+
+        chooser.close();
+        assert_eq!(chooser.line_at(3, Point::new(15.0, 25.0)), None);
+    }
+}
@@ -0,0 +1,71 @@
+use druid::Selector;
+use crate::layout::CellRange;
+use crate::reorder::ElementDrag;
+use crate::search::{ReplaceResult, SearchPattern};
+use crate::state::{LineFilter, SortKey};
+
+/// Flips the line axis of a table, lines become elements and vice versa.
+pub const TRANSPOSE: Selector = Selector::new("druid-table.transpose");
+
+/// Moves the line at the first index to the second index, the other lines keep their order.
+///
+/// Submitted as a notification by a line header when it is dropped at a new position.
+pub const MOVE_LINE: Selector<(usize, usize)> = Selector::new("druid-table.move-line");
+
+/// Restores an order of lines previously reported by `LINE_ORDER_CHANGED`.
+pub const SET_LINE_ORDER: Selector<Vec<usize>> = Selector::new("druid-table.set-line-order");
+
+/// Notification with the new order of the lines, given as the indices in which they were added.
+pub const LINE_ORDER_CHANGED: Selector<Vec<usize>> = Selector::new("druid-table.line-order-changed");
+
+/// Moves the element at the first index to the second index inside the data of the table.
+///
+/// Requires an element source, see `Table::with_element_source`.
+pub const MOVE_ELEMENT: Selector<(usize, usize)> = Selector::new("druid-table.move-element");
+
+/// Notifies a `HeaderTable` that its table moved an element after receiving `MOVE_ELEMENT`.
+pub(crate) const ELEMENT_MOVED: Selector<(usize, usize)> = Selector::new("druid-table.element-moved");
+
+/// Selects a range of cells, submitted by a controller which can not change the layout itself.
+pub(crate) const SET_SELECTION: Selector<CellRange> = Selector::new("druid-table.set-selection");
+
+/// Moves the cursor of a table by lines and elements, submitted by a line which handled a navigation key.
+pub(crate) const MOVE_CURSOR: Selector<(isize, isize)> = Selector::new("druid-table.move-cursor");
+
+/// Sent to the window when an element is dragged out of a table.
+/// The table below the drop position inserts the element and answers with `ELEMENT_TAKEN`.
+pub const DROP_ELEMENT: Selector<ElementDrag> = Selector::new("druid-table.drop-element");
+
+/// Sent to the source of a dropped element, which removes the element at the given index.
+pub const ELEMENT_TAKEN: Selector<usize> = Selector::new("druid-table.element-taken");
+
+/// Undoes the last change of a table with an edit history, see `Table::with_history`.
+pub const UNDO: Selector = Selector::new("druid-table.undo");
+
+pub const REDO: Selector = Selector::new("druid-table.redo");
+
+/// Searches the cells of a table, see `Table::search`.
+pub const FIND: Selector<SearchPattern> = Selector::new("druid-table.find");
+
+/// Moves to the next match of the current search, see `Table::search`.
+pub const FIND_NEXT: Selector = Selector::new("druid-table.find-next");
+
+pub const FIND_PREVIOUS: Selector = Selector::new("druid-table.find-previous");
+
+/// Replaces the matches of the current search in the current cell with the text.
+pub const REPLACE: Selector<String> = Selector::new("druid-table.replace");
+
+/// Replaces all matches of the current search with the text, only inside the selection if the flag is set.
+pub const REPLACE_ALL: Selector<(String, bool)> = Selector::new("druid-table.replace-all");
+
+/// Writes tab separated text into the cells from the cursor on, see `Table::paste`.
+pub const PASTE: Selector<String> = Selector::new("druid-table.paste");
+
+/// Notification with the result of `REPLACE` and `REPLACE_ALL`, which lists the cells that failed.
+pub const REPLACED: Selector<ReplaceResult> = Selector::new("druid-table.replaced");
+
+/// Hides or shows the line at the given index.
+pub const SET_LINE_VISIBLE: Selector<(usize, bool)> = Selector::new("druid-table.set-line-visible");
+
+/// Sets the sort keys and filters of a table, which are passed to the hook of `Table::with_sort_filter`.
+pub const SET_SORT_FILTER: Selector<(Vec<SortKey>, Vec<LineFilter>)> = Selector::new("druid-table.set-sort-filter");
@@ -1,4 +1,5 @@
-use druid::{Env, Event, EventCtx, LifeCycle, LifeCycleCtx, UpdateCtx, Data};
+use std::ops::{Deref, DerefMut};
+use druid::{Env, Event, EventCtx, LifeCycle, LifeCycleCtx, PaintCtx, Point, UpdateCtx, Data};
 use crate::table::TableContent;
 use crate::TableLayout;
 
@@ -10,12 +11,44 @@ pub trait TableController<T> {
 
     #[allow(unused_variables)]
     fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {}
+
+    /// Returns true if the controller handles mouse presses at `pos` itself, the table then keeps its cursor.
+    #[allow(unused_variables)]
+    fn captures_mouse(&self, pos: Point, layout: &TableLayout) -> bool {
+        false
+    }
+
+    /// Paints the state of the controller over the table, e.g. a fill handle.
+    #[allow(unused_variables)]
+    fn paint(&mut self, ctx: &mut PaintCtx, env: &Env, layout: &TableLayout) {}
+}
+
+impl<T: Data> TableController<T> for Box<dyn TableController<T>> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env, content: &mut TableContent<T>, layout: &TableLayout) {
+        self.deref_mut().event(ctx, event, data, env, content, layout);
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.deref_mut().lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        self.deref_mut().update(ctx, old_data, data, env);
+    }
+
+    fn captures_mouse(&self, pos: Point, layout: &TableLayout) -> bool {
+        self.deref().captures_mouse(pos, layout)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, env: &Env, layout: &TableLayout) {
+        self.deref_mut().paint(ctx, env, layout);
+    }
 }
 
 pub struct DefaultTableController;
 
 impl<T: Data> TableController<T> for DefaultTableController {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env, content: &mut TableContent<T>, _: &TableLayout) {
-        content.event(ctx, event, data, env)
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env, content: &mut TableContent<T>, layout: &TableLayout) {
+        content.event(ctx, event, data, env, layout)
     }
 }
\ No newline at end of file
@@ -0,0 +1,265 @@
+use std::marker::PhantomData;
+use druid::{BoxConstraints, Env, Event, EventCtx, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Size, UpdateCtx, Widget, WidgetPod, Data, RenderContext};
+use druid::theme;
+use druid::widget::ListIter;
+use crate::{HeaderData, TableLayout};
+use crate::util::{disclosure_triangle, move_item};
+
+pub trait TableDetail<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env, meta: &TableLayout);
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env);
+
+    fn update(&mut self, ctx: &mut UpdateCtx, data: &T, env: &Env);
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env, meta: &TableLayout);
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, data: &T, env: &Env, meta: &mut TableLayout);
+
+    fn arrange(&mut self, ctx: &mut LayoutCtx, data: &T, env: &Env, meta: &TableLayout);
+
+    fn move_element(&mut self, _from: usize, _to: usize) {}
+
+    fn insert_element(&mut self, _index: usize) {}
+
+    fn remove_element(&mut self, _index: usize) {}
+}
+
+pub struct WidgetTableDetail<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+
+    L: Lens<S, T>,
+    W: Widget<U>,
+> {
+    lens: L,
+    widgets: Vec<WidgetPod<U, W>>,
+    generate: Box<dyn Fn() -> WidgetPod<U, W>>,
+    phantom: PhantomData<(S, T)>,
+}
+
+/// A toggle for element headers which expands or collapses the detail of its element.
+pub struct ExpandToggle;
+
+fn is_expanded(meta: &TableLayout, element: usize) -> bool {
+    element < meta.elements().length() && meta.elements().get(element).is_expanded()
+}
+
+impl<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+
+    L: Lens<S, T>,
+    W: Widget<U> + 'static,
+> WidgetTableDetail<S, T, U, L, W> {
+    pub fn new(lens: L, generate: impl Fn() -> W + 'static) -> Self {
+        Self {
+            lens,
+            widgets: vec![],
+            generate: Box::new(move||WidgetPod::new(generate())),
+            phantom: Default::default()
+        }
+    }
+
+    fn update_widget_count(&mut self, data: &S) -> bool {
+        let Self {lens, widgets, generate, ..} = self;
+        lens.with(data, |data| {
+            if widgets.len() > data.data_len() {
+                widgets.truncate(data.data_len());
+                true
+            } else if widgets.len() < data.data_len() {
+                widgets.extend(std::iter::repeat_with(generate).take(data.data_len() - widgets.len()));
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+impl<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+
+    L: Lens<S, T>,
+    W: Widget<U> + 'static,
+> TableDetail<S> for WidgetTableDetail<S, T, U, L, W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut S, env: &Env, meta: &TableLayout) {
+        let Self {lens, widgets, ..} = self;
+        lens.with_mut(data, |data|data.for_each_mut(|data, index|{
+            if is_expanded(meta, index) {
+                widgets[index].event(ctx, event, data, env);
+            }
+        }));
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &S, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.update_widget_count(data);
+        }
+
+        let Self {lens, widgets, ..} = self;
+        lens.with(data, |data|data.for_each(|data, index|{
+            widgets[index].lifecycle(ctx, event, data, env);
+        }));
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, data: &S, env: &Env) {
+        let Self {lens, widgets, ..} = self;
+        lens.with(data, |data|data.for_each(|data, index|{
+            if let Some(widget) = widgets.get_mut(index) {
+                widget.update(ctx, data, env);
+            }
+        }));
+
+        if self.update_widget_count(data) {
+            ctx.children_changed();
+        }
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &S, env: &Env, meta: &TableLayout) {
+        let Self {lens, widgets, ..} = self;
+        lens.with(data, |data|data.for_each(|data, index|{
+            if is_expanded(meta, index) {
+                widgets[index].paint(ctx, data, env);
+            }
+        }));
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &mut TableLayout) {
+        let axis = meta.line_axis();
+        let Self {lens, widgets, ..} = self;
+        lens.with(data, |data|data.for_each(|data, index|{
+            if !is_expanded(meta, index) {
+                return;
+            }
+            // The detail covers all lines and takes as much space along the element axis as it needs
+            let extent = axis.minor(meta.detail_rect(index).size());
+            let bc = BoxConstraints::new(
+                Size::from(axis.pack(0.0, extent)),
+                Size::from(axis.pack(f64::INFINITY, extent)),
+            );
+            let size = widgets[index].layout(ctx, &bc, data, env);
+            meta.elements_mut().set_detail_size(index, axis.major(size));
+        }));
+    }
+
+    fn arrange(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &TableLayout) {
+        let Self {lens, widgets, ..} = self;
+        lens.with(data, |data|data.for_each(|data, index|{
+            if is_expanded(meta, index) {
+                widgets[index].set_origin(ctx, data, env, meta.detail_rect(index).origin());
+            }
+        }));
+    }
+
+    fn move_element(&mut self, from: usize, to: usize) {
+        move_item(&mut self.widgets, from, to);
+    }
+
+    fn insert_element(&mut self, index: usize) {
+        if index <= self.widgets.len() {
+            self.widgets.insert(index, (self.generate)());
+        }
+    }
+
+    fn remove_element(&mut self, index: usize) {
+        if index < self.widgets.len() {
+            self.widgets.remove(index);
+        }
+    }
+}
+
+impl<T: Data> Widget<HeaderData<T>> for ExpandToggle {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut HeaderData<T>, _: &Env) {
+        match event {
+            Event::MouseDown(mouse) if mouse.button.is_left() => {
+                ctx.set_active(true);
+                ctx.set_handled();
+            }
+            Event::MouseUp(mouse) if mouse.button.is_left() && ctx.is_active() => {
+                ctx.set_active(false);
+                if ctx.is_hot() {
+                    let expanded = data.part.is_expanded();
+                    data.part.set_expanded(!expanded);
+                    ctx.request_paint();
+                }
+                ctx.set_handled();
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _: &HeaderData<T>, _: &Env) {
+        if let LifeCycle::HotChanged(_) = event {
+            ctx.request_paint();
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &HeaderData<T>, data: &HeaderData<T>, _: &Env) {
+        if old_data.part.is_expanded() != data.part.is_expanded() {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(&mut self, _: &mut LayoutCtx, bc: &BoxConstraints, _: &HeaderData<T>, env: &Env) -> Size {
+        let size = env.get(theme::BASIC_WIDGET_HEIGHT);
+        bc.constrain(Size::new(size, size))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &HeaderData<T>, env: &Env) {
+        let size = ctx.size();
+        let center = size.to_rect().center();
+        let radius = size.width.min(size.height) / 4.0;
+
+        let path = disclosure_triangle(center, radius, data.part.is_expanded());
+        let color = if ctx.is_hot() {env.get(theme::FOREGROUND_LIGHT)} else {env.get(theme::TEXT_COLOR)};
+        ctx.fill(path, &color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::widget::Axis;
+    use crate::AxisPart;
+    use super::*;
+
+    fn table_layout() -> TableLayout {
+        let mut layout = TableLayout::new(Axis::Vertical);
+        layout.lines_mut().set_length(2, AxisPart::new(None));
+        layout.elements_mut().set_length(3, AxisPart::new(None));
+        layout.prepare_layout(Size::new(1000.0, 1000.0));
+        for element in 0..3 {
+            layout.elements_mut().set_size(element, 10.0);
+        }
+        layout
+    }
+
+    fn set_expanded(layout: &mut TableLayout, element: usize, expanded: bool) {
+        let mut part = layout.elements().get(element);
+        part.set_expanded(expanded);
+        layout.elements_mut().set(element, part);
+    }
+
+    #[test]
+    fn detail_takes_space_only_when_expanded() {
+        let mut layout = table_layout();
+        layout.elements_mut().set_detail_size(1, 25.0);
+        assert_eq!(layout.elements().detail_layout(1), (22.0, 22.0));
+        assert!(!is_expanded(&layout, 1));
+        assert!(!is_expanded(&layout, 7));
+
+        set_expanded(&mut layout, 1, true);
+        layout.elements_mut().set_detail_size(1, 25.0);
+        assert!(is_expanded(&layout, 1));
+        assert_eq!(layout.elements().detail_layout(1), (22.0, 47.0));
+        assert_eq!(layout.elements().current_layout(2), (48.0, 58.0));
+        assert_eq!(layout.line_axis().major(layout.element_rect(1).size()), 35.0);
+
+        set_expanded(&mut layout, 1, false);
+        assert_eq!(layout.elements().current_layout(2), (23.0, 33.0));
+    }
+}
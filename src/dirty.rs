@@ -0,0 +1,287 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+use druid::{Env, Lens, PaintCtx, Rect, RenderContext, Data};
+use druid::widget::{Axis, ListIter};
+use crate::{TableAxis, TableLayout};
+use crate::theme;
+use crate::util::moved_index;
+
+/// A value which differs from the baseline.
+#[derive(Clone)]
+pub struct CellChange {
+    key: Rc<dyn Any>,
+    line: usize,
+    old: Rc<dyn Any>,
+    new: Rc<dyn Any>,
+}
+
+/// The differences between the data of a table and its baseline, e.g. to build a save request.
+#[derive(Clone, Default)]
+pub struct ChangeSet {
+    cells: Vec<CellChange>,
+    added: Vec<Rc<dyn Any>>,
+    removed: Vec<Rc<dyn Any>>,
+}
+
+/// How the elements of the data correspond to the elements of the baseline, by their keys.
+pub(crate) struct ElementMatch {
+    /// For every element its index in the baseline, `None` for added elements.
+    pub baseline_index: Vec<Option<usize>>,
+    pub keys: Vec<Rc<dyn Any>>,
+    /// The baseline index and key of the removed elements, ordered by index.
+    pub removed: Vec<(usize, Rc<dyn Any>)>,
+}
+
+/// The baseline of a table and how to identify its elements.
+pub(crate) struct ChangeTracker<T> {
+    pub baseline: Option<T>,
+    match_elements: Box<dyn Fn(&T, &T) -> ElementMatch>,
+}
+
+/// The cells to mark, computed when the data or the baseline changes.
+#[derive(Default)]
+pub(crate) struct DirtyCells {
+    pub changed: Vec<(usize, usize)>,
+    pub added: Vec<usize>,
+    /// The positions between elements where elements were removed.
+    pub removed_at: Vec<usize>,
+}
+
+impl CellChange {
+    pub(crate) fn new(key: Rc<dyn Any>, line: usize, old: Rc<dyn Any>, new: Rc<dyn Any>) -> Self {
+        Self {
+            key,
+            line,
+            old,
+            new,
+        }
+    }
+
+    /// The key of the element, `None` if `K` is not the type of the keys.
+    pub fn key<K: 'static>(&self) -> Option<&K> {
+        self.key.downcast_ref()
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn old_value<V: 'static>(&self) -> Option<&V> {
+        self.old.downcast_ref()
+    }
+
+    pub fn new_value<V: 'static>(&self) -> Option<&V> {
+        self.new.downcast_ref()
+    }
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// The changed values of elements which exist in the baseline.
+    pub fn cells(&self) -> &[CellChange] {
+        &self.cells
+    }
+
+    /// The keys of the elements which are not in the baseline.
+    pub fn added<K: Clone + 'static>(&self) -> Vec<K> {
+        self.added.iter().filter_map(|key|key.downcast_ref().cloned()).collect()
+    }
+
+    /// The keys of the elements of the baseline which were removed.
+    pub fn removed<K: Clone + 'static>(&self) -> Vec<K> {
+        self.removed.iter().filter_map(|key|key.downcast_ref().cloned()).collect()
+    }
+}
+
+impl<T: Data> ChangeTracker<T> {
+    /// The keys have to be unique. Of elements with the same key only the first one is matched
+    /// with the baseline, the others count as added or removed.
+    pub fn new<
+        T2: ListIter<U>,
+        U: Data,
+        K: Hash + Eq + 'static,
+
+        L: Lens<T, T2> + 'static,
+    >(lens: L, key: impl Fn(&U) -> K + 'static) -> Self {
+        Self {
+            baseline: None,
+            match_elements: Box::new(move|baseline, data|{
+                let mut baseline_keys = HashMap::new();
+                lens.with(baseline, |baseline|baseline.for_each(|element, index|{
+                    baseline_keys.entry(key(element)).or_insert(index);
+                }));
+
+                let mut baseline_index = vec![];
+                let mut keys = vec![];
+                lens.with(data, |data|data.for_each(|element, _|{
+                    let element_key = key(element);
+                    baseline_index.push(baseline_keys.remove(&element_key));
+                    keys.push(Rc::new(element_key) as Rc<dyn Any>);
+                }));
+
+                let mut removed: Vec<_> = baseline_keys.into_iter()
+                    .map(|(key, index)|(index, Rc::new(key) as Rc<dyn Any>))
+                    .collect();
+                removed.sort_by_key(|(index, _)|*index);
+
+                ElementMatch {
+                    baseline_index,
+                    keys,
+                    removed,
+                }
+            }),
+        }
+    }
+
+    /// Returns `None` without a baseline.
+    pub fn match_elements(&self, data: &T) -> Option<ElementMatch> {
+        self.baseline.as_ref().map(|baseline|(self.match_elements)(baseline, data))
+    }
+}
+
+impl ChangeSet {
+    pub(crate) fn new(elements: ElementMatch, cells: Vec<CellChange>) -> Self {
+        Self {
+            cells,
+            added: elements.baseline_index.iter()
+                .zip(elements.keys.iter())
+                .filter(|(index, _)|index.is_none())
+                .map(|(_, key)|key.clone())
+                .collect(),
+            removed: elements.removed.into_iter().map(|(_, key)|key).collect(),
+        }
+    }
+}
+
+impl DirtyCells {
+    pub fn new(elements: &ElementMatch, changed: Vec<(usize, usize)>) -> Self {
+        let added = elements.baseline_index.iter()
+            .enumerate()
+            .filter(|(_, index)|index.is_none())
+            .map(|(element, _)|element)
+            .collect();
+        let removed_at = elements.removed.iter()
+            .map(|(removed, _)|elements.baseline_index.iter()
+                .position(|index|matches!(index, Some(index) if index > removed))
+                .unwrap_or(elements.baseline_index.len()))
+            .collect();
+        Self {
+            changed,
+            added,
+            removed_at,
+        }
+    }
+
+    /// Keeps the marks on their line after the line at `from` was moved to `to`.
+    pub fn move_line(&mut self, from: usize, to: usize) {
+        for (line, _) in &mut self.changed {
+            *line = moved_index(*line, from, to);
+        }
+    }
+
+    /// Keeps the marks on their element after the element at `from` was moved to `to`,
+    /// until they are computed again for the new data.
+    pub fn move_element(&mut self, from: usize, to: usize) {
+        for (_, element) in &mut self.changed {
+            *element = moved_index(*element, from, to);
+        }
+        for element in &mut self.added {
+            *element = moved_index(*element, from, to);
+        }
+    }
+
+    pub fn paint(&self, ctx: &mut PaintCtx, env: &Env, layout: &TableLayout) {
+        let elements = layout.elements().length();
+
+        let added_color = theme::added_color(env);
+        for element in self.added.iter().filter(|element|**element < elements) {
+            ctx.fill(layout.element_rect(*element), &added_color);
+        }
+
+        let changed_color = theme::changed_color(env);
+        for (line, element) in &self.changed {
+            if *element < elements && !layout.is_line_hidden(*line) && !layout.is_covered(*line, *element) {
+                ctx.fill(layout.layout_rect(*line, *element), &changed_color);
+            }
+        }
+
+        let removed_color = theme::removed_color(env);
+        for position in &self.removed_at {
+            // The marker is painted at the start of the following element or the end of the last one
+            let (rect, end) = match *position {
+                position if position < elements => (layout.element_rect(position), false),
+                _ if elements > 0 => (layout.element_rect(elements - 1), true),
+                _ => continue,
+            };
+            let marker = match layout.axis_direction(TableAxis::LineAxis) {
+                Axis::Vertical => {
+                    let y = if end {rect.y1} else {rect.y0};
+                    Rect::new(rect.x0, y - 1.0, rect.x1, y + 1.0)
+                }
+                Axis::Horizontal => {
+                    let x = if end {rect.x1} else {rect.x0};
+                    Rect::new(x - 1.0, rect.y0, x + 1.0, rect.y1)
+                }
+            };
+            ctx.fill(marker, &removed_color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use druid::im::Vector;
+    use druid::lens::Identity;
+
+    fn tracker(baseline: &[(u32, i32)]) -> ChangeTracker<Vector<(u32, i32)>> {
+        let mut tracker = ChangeTracker::new(Identity, |element: &(u32, i32)|element.0);
+        tracker.baseline = Some(Vector::from(baseline.to_vec()));
+        tracker
+    }
+
+    fn keys(elements: &ElementMatch) -> Vec<u32> {
+        elements.keys.iter().filter_map(|key|key.downcast_ref().copied()).collect()
+    }
+
+    #[test]
+    fn elements_match_by_key() {
+        let tracker = tracker(&[(1, 10), (2, 20), (3, 30)]);
+        let elements = tracker.match_elements(&Vector::from(vec![(2, 20), (4, 40), (1, 11)])).unwrap();
+        assert_eq!(elements.baseline_index, vec![Some(1), None, Some(0)]);
+        assert_eq!(keys(&elements), vec![2, 4, 1]);
+        assert_eq!(elements.removed.len(), 1);
+        assert_eq!(elements.removed[0].0, 2);
+        assert_eq!(elements.removed[0].1.downcast_ref::<u32>(), Some(&3));
+    }
+
+    #[test]
+    fn duplicate_keys_match_once() {
+        let tracker = tracker(&[(1, 10), (1, 20)]);
+        let elements = tracker.match_elements(&Vector::from(vec![(1, 10), (1, 20)])).unwrap();
+        assert_eq!(elements.baseline_index, vec![Some(0), None]);
+    }
+
+    #[test]
+    fn dirty_cells_mark_added_and_removed_positions() {
+        let tracker = tracker(&[(1, 10), (2, 20), (3, 30)]);
+        let elements = tracker.match_elements(&Vector::from(vec![(2, 20), (4, 40), (1, 11)])).unwrap();
+        let dirty = DirtyCells::new(&elements, vec![(0, 2)]);
+        assert_eq!(dirty.added, vec![1]);
+        // The element after the removed one is not in the data anymore, the mark goes to the end
+        assert_eq!(dirty.removed_at, vec![3]);
+
+        let elements = tracker.match_elements(&Vector::from(vec![(2, 20), (3, 30)])).unwrap();
+        let mut dirty = DirtyCells::new(&elements, vec![(0, 1)]);
+        assert!(dirty.added.is_empty());
+        assert_eq!(dirty.removed_at, vec![0]);
+
+        dirty.move_line(0, 2);
+        assert_eq!(dirty.changed, vec![(2, 1)]);
+    }
+}
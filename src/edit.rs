@@ -0,0 +1,610 @@
+use std::any::Any;
+use std::marker::PhantomData;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use druid::{Env, Event, EventCtx, InternalLifeCycle, KbKey, KeyEvent, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Selector, Size, UpdateCtx, Widget, WidgetId, WidgetPod, Data};
+use druid::widget::ListIter;
+use crate::{FormatRule, TableLayout, TableLine};
+use crate::commands::MOVE_CURSOR;
+use crate::util::move_item;
+use crate::line::{cell_env, diff_line, line_texts, line_value, line_values, paint_rule_backgrounds, rejected_texts, rule_envs, same_line_value, set_line_texts, set_line_value, set_line_values, validate_line, ParseFn, TextFn, Validator};
+
+const BEGIN_EDITING: Selector = Selector::new("druid-table.edit.begin-editing");
+const EDITOR_FOCUS_LOST: Selector<WidgetId> = Selector::new("druid-table.edit.editor-focus-lost");
+
+/// A line which shows lightweight display widgets and swaps in an editor for the active cell.
+///
+/// Editing starts with a double click, Enter, F2 or by typing into the cell under the cursor,
+/// the typed character replaces the value if it can be parsed, see `with_parser`.
+/// Enter and Tab commit the value, Escape reverts it. Only one editor exists at a time.
+pub struct EditTableLine<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+    V: Data,
+
+    L1: Lens<S, T>,
+    L2: Lens<U, V>,
+    D: Widget<V>,
+    E: Widget<V>,
+> {
+    outer_lens: L1,
+    inner_lens: L2,
+    widgets: Vec<WidgetPod<V, D>>,
+    generate: Box<dyn Fn() -> WidgetPod<V, D>>,
+    generate_editor: Box<dyn Fn() -> E>,
+    editor: Option<CellEditor<V, E>>,
+    editable: bool,
+    rules: Vec<FormatRule<V>>,
+    /// The environments of the cells changed by rules, computed when the data changes.
+    envs: Vec<Option<Env>>,
+    validators: Vec<Validator<V>>,
+    text: Option<TextFn<V>>,
+    parse: Option<ParseFn<V>>,
+    phantom: PhantomData<(S, T, U)>,
+}
+
+struct CellEditor<V, E> {
+    element: usize,
+    widget: WidgetPod<V, E>,
+    original: V,
+    focused: bool,
+    /// The focus was moved back to the table, the editor is removed once it committed its value.
+    closing: bool,
+}
+
+/// What a key does while a cell is edited.
+#[derive(Debug, PartialEq)]
+enum EditorKey {
+    Revert,
+    Commit,
+    /// Commits the value and moves the cursor by the given number of lines.
+    Move(isize),
+    Forward,
+}
+
+fn editor_key(key: &KeyEvent) -> EditorKey {
+    match key.key {
+        KbKey::Escape => EditorKey::Revert,
+        KbKey::Enter if !key.mods.shift() => EditorKey::Commit,
+        KbKey::Tab => EditorKey::Move(if key.mods.shift() {-1} else {1}),
+        _ => EditorKey::Forward,
+    }
+}
+
+/// The text typed into a cell which is not edited yet.
+fn typed_text(key: &KeyEvent) -> Option<&str> {
+    match &key.key {
+        KbKey::Character(text) if !key.mods.ctrl() && !key.mods.meta() && !key.mods.alt() => Some(text.as_str()),
+        _ => None,
+    }
+}
+
+impl<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+    V: Data,
+
+    L1: Lens<S, T>,
+    L2: Lens<U, V>,
+    D: Widget<V> + 'static,
+    E: Widget<V> + 'static,
+> EditTableLine<S, T, U, V, L1, L2, D, E> {
+    pub fn new(outer_lens: L1, inner_lens: L2, display: impl Fn() -> D + 'static, editor: impl Fn() -> E + 'static) -> Self {
+        Self {
+            outer_lens,
+            inner_lens,
+            widgets: vec![],
+            generate: Box::new(move||WidgetPod::new(display())),
+            generate_editor: Box::new(editor),
+            editor: None,
+            editable: true,
+            rules: vec![],
+            envs: vec![],
+            validators: vec![],
+            text: None,
+            parse: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// A line which is not editable only shows its display widgets.
+    pub fn with_editable(mut self, editable: bool) -> Self {
+        self.editable = editable;
+        self
+    }
+
+    /// Adds a conditional formatting rule for the cells of this line.
+    pub fn with_rule(mut self, rule: FormatRule<V>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Adds a check of the values, invalid cells are marked by the table.
+    pub fn with_validator(mut self, validator: impl Fn(&V) -> Result<(), String> + 'static) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    /// Sets the text of the values which is matched by a search.
+    pub fn with_text(mut self, text: impl Fn(&V) -> String + 'static) -> Self {
+        self.text = Some(Box::new(text));
+        self
+    }
+
+    /// Sets how text is converted to values, e.g. when replacing text in the cells.
+    pub fn with_parser(mut self, parse: impl Fn(&str) -> Result<V, String> + 'static) -> Self {
+        self.parse = Some(Box::new(parse));
+        self
+    }
+
+    /// Returns the element which is currently edited.
+    pub fn editing(&self) -> Option<usize> {
+        self.editor.as_ref().map(|editor|editor.element)
+    }
+
+    fn update_widget_count(&mut self, data: &S) -> bool {
+        let Self {outer_lens, widgets, generate, ..} = self;
+        outer_lens.with(data, |data| {
+            if widgets.len() > data.data_len() {
+                widgets.truncate(data.data_len());
+                true
+            } else if widgets.len() < data.data_len() {
+                widgets.extend(std::iter::repeat_with(generate).take(data.data_len() - widgets.len()));
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    fn update_envs(&mut self, data: &S, env: &Env) {
+        let Self {outer_lens, inner_lens, rules, envs, ..} = self;
+        *envs = outer_lens.with(data, |data|rule_envs(data, inner_lens, rules, env));
+    }
+
+    /// Returns the element to edit and the text typed into it.
+    fn edit_trigger(&self, ctx: &EventCtx, event: &Event, meta: &TableLayout, line_index: usize) -> Option<(usize, Option<String>)> {
+        if !self.editable {
+            return None;
+        }
+        match event {
+            Event::MouseDown(mouse) if mouse.count == 2 => {
+                meta.cell_at(mouse.pos)
+                    .filter(|(line, _)|*line == line_index)
+                    .map(|(_, element)|(element, None))
+            }
+            Event::KeyDown(key) if ctx.is_focused() => {
+                let element = meta.cursor().filter(|(line, _)|*line == line_index)?.1;
+                match &key.key {
+                    KbKey::Enter | KbKey::F2 => Some((element, None)),
+                    _ => typed_text(key).map(|text|(element, Some(text.to_string()))),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn start_editing(&mut self, ctx: &mut EventCtx, data: &mut S, element: usize, input: Option<String>) {
+        if self.open_editor(data, element, input) {
+            ctx.children_changed();
+            // The editor can only take the focus after it was added
+            ctx.submit_command(BEGIN_EDITING.to(ctx.widget_id()));
+            ctx.set_handled();
+        }
+    }
+
+    /// Creates the editor, the typed text replaces the value if it can be parsed.
+    ///
+    /// The text is written into the data instead of being passed to the editor as a key,
+    /// since text fields take their text from the input method and not from key events.
+    fn open_editor(&mut self, data: &mut S, element: usize, input: Option<String>) -> bool {
+        let Self {outer_lens, inner_lens, parse, ..} = self;
+        let original = outer_lens.with(data, |data|line_value(data, inner_lens, element))
+            .and_then(|value|value.downcast_ref::<V>().cloned());
+        let original = match original {
+            Some(original) => original,
+            None => return false,
+        };
+        if let Some(text) = input {
+            outer_lens.with_mut(data, |data|set_line_texts(data, inner_lens, &[(element, text)], parse.as_ref()));
+        }
+
+        self.editor = Some(CellEditor {
+            element,
+            widget: WidgetPod::new((self.generate_editor)()),
+            original,
+            focused: false,
+            closing: false,
+        });
+        true
+    }
+
+    /// Writes the value from before editing back and removes the editor.
+    fn revert_editor(&mut self, data: &mut S) {
+        let Self {outer_lens, inner_lens, editor, ..} = self;
+        if let Some(editor) = editor.take() {
+            outer_lens.with_mut(data, |data|set_line_value(data, inner_lens, editor.element, &editor.original));
+        }
+    }
+
+    /// The editor commits its value when it loses the focus, it is removed after `EDITOR_FOCUS_LOST`.
+    fn stop_editing(&mut self, ctx: &mut EventCtx) {
+        match &mut self.editor {
+            Some(editor) if editor.focused => {
+                editor.closing = true;
+                if editor.widget.has_focus() {
+                    ctx.request_focus();
+                }
+            }
+            Some(_) => {
+                self.editor = None;
+                ctx.request_layout();
+            }
+            None => {}
+        }
+    }
+
+    fn editor_event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut S, env: &Env) {
+        let (mut close, mut revert) = (false, false);
+        let Self {outer_lens, inner_lens, editor: slot, ..} = self;
+        let editor = match slot.as_mut() {
+            Some(editor) => editor,
+            None => return,
+        };
+        let forward = |ctx: &mut EventCtx, editor: &mut CellEditor<V, E>, event: &Event, data: &mut S|{
+            outer_lens.with_mut(data, |data|data.for_each_mut(|data, index|{
+                if index == editor.element {
+                    inner_lens.with_mut(data, |data|editor.widget.event(ctx, event, data, env));
+                }
+            }));
+        };
+
+        match event {
+            Event::Command(command) if command.is(BEGIN_EDITING) => {
+                ctx.set_focus(editor.widget.id());
+                ctx.set_handled();
+            }
+            Event::Command(command) if command.get(EDITOR_FOCUS_LOST) == Some(&editor.widget.id()) => {
+                // Submitted after the editor committed its value on focus loss
+                *slot = None;
+                ctx.request_layout();
+                ctx.set_handled();
+            }
+            Event::KeyDown(key) => match editor_key(key) {
+                EditorKey::Revert => {
+                    revert = true;
+                    ctx.request_layout();
+                    ctx.request_focus();
+                    ctx.set_handled();
+                }
+                EditorKey::Commit => {
+                    // The editor gets the key first, in case it commits its value on Enter
+                    forward(ctx, editor, event, data);
+                    *slot = None;
+                    ctx.request_layout();
+                    ctx.request_focus();
+                    ctx.set_handled();
+                }
+                EditorKey::Move(lines) => {
+                    // The editor may handle Tab itself, so the table is asked to move the cursor afterwards
+                    forward(ctx, editor, event, data);
+                    ctx.submit_command(MOVE_CURSOR.with((lines, 0)).to(ctx.widget_id()));
+                    ctx.set_handled();
+                    close = true;
+                }
+                EditorKey::Forward => forward(ctx, editor, event, data),
+            },
+            _ => forward(ctx, editor, event, data),
+        }
+
+        if revert {
+            self.revert_editor(data);
+        }
+        if close {
+            self.stop_editing(ctx);
+        }
+    }
+}
+
+impl<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+    V: Data,
+
+    L1: Lens<S, T>,
+    L2: Lens<U, V>,
+    D: Widget<V> + 'static,
+    E: Widget<V> + 'static,
+> TableLine<S> for EditTableLine<S, T, U, V, L1, L2, D, E> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut S, env: &Env, meta: &TableLayout, line_index: usize) {
+        // The value is committed when the cursor leaves the cell, focus loss is handled by `EDITOR_FOCUS_LOST`
+        if let Some(editor) = &self.editor {
+            if !editor.closing && meta.cursor() != Some((line_index, editor.element)) {
+                self.stop_editing(ctx);
+            }
+        }
+
+        if self.editor.is_some() {
+            self.editor_event(ctx, event, data, env);
+        } else if let Some((element, input)) = self.edit_trigger(ctx, event, meta, line_index) {
+            self.start_editing(ctx, data, element, input);
+        }
+
+        let Self {outer_lens, inner_lens, widgets, editor, envs, ..} = self;
+        let editing = editor.as_ref().map(|editor|editor.element);
+        outer_lens.with_mut(data, |data|data.for_each_mut(|data, index|inner_lens.with_mut(data, |data|{
+            if Some(index) == editing || meta.is_covered(line_index, index) {
+                return;
+            }
+            if let Some(widget) = widgets.get_mut(index) {
+                widget.event(ctx, event, data, cell_env(envs, index, env));
+            }
+        })));
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &S, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.update_widget_count(data);
+            self.update_envs(data, env);
+        }
+
+        let Self {outer_lens, inner_lens, widgets, editor, envs, ..} = self;
+        outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
+            widgets[index].lifecycle(ctx, event, data, cell_env(envs, index, env));
+            if let Some(editor) = editor.as_mut().filter(|editor|editor.element == index) {
+                editor.widget.lifecycle(ctx, event, data, env);
+            }
+        })));
+
+        if let Some(editor) = editor {
+            if editor.widget.has_focus() {
+                editor.focused = true;
+            }
+            if let LifeCycle::Internal(InternalLifeCycle::RouteFocusChanged {old: Some(old), ..}) = event {
+                if *old == editor.widget.id() {
+                    ctx.submit_command(EDITOR_FOCUS_LOST.with(*old).to(ctx.widget_id()));
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, data: &S, env: &Env) {
+        self.update_envs(data, env);
+
+        let Self {outer_lens, inner_lens, widgets, editor, envs, ..} = self;
+        let mut edited = false;
+        outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
+            if let Some(widget) = widgets.get_mut(index) {
+                widget.update(ctx, data, cell_env(envs, index, env));
+            }
+            if let Some(editor) = editor.as_mut().filter(|editor|editor.element == index) {
+                editor.widget.update(ctx, data, env);
+                edited = true;
+            }
+        })));
+        if !edited && editor.is_some() {
+            // The edited element was removed
+            *editor = None;
+            ctx.request_layout();
+        }
+
+        if self.update_widget_count(data) {
+            ctx.children_changed();
+        }
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        let Self {outer_lens, inner_lens, widgets, editor, envs, ..} = self;
+        outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
+            if meta.is_covered(line_index, index) {
+                return;
+            }
+            match editor.as_mut().filter(|editor|editor.element == index) {
+                Some(editor) => editor.widget.paint(ctx, data, env),
+                None => widgets[index].paint(ctx, data, cell_env(envs, index, env)),
+            }
+        })));
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &mut TableLayout, line_index: usize) {
+        let Self {outer_lens, inner_lens, widgets, editor, envs, ..} = self;
+        outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
+            if meta.is_covered(line_index, index) {
+                return;
+            }
+            meta.layout(line_index, index, |bc|{
+                let size = widgets[index].layout(ctx, bc, data, cell_env(envs, index, env));
+                match editor.as_mut().filter(|editor|editor.element == index) {
+                    Some(editor) => {
+                        let editor_size = editor.widget.layout(ctx, bc, data, env);
+                        Size::new(size.width.max(editor_size.width), size.height.max(editor_size.height))
+                    }
+                    None => size,
+                }
+            });
+        })));
+    }
+
+    fn arrange(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        let Self {outer_lens, inner_lens, widgets, editor, envs, ..} = self;
+        outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
+            if meta.is_covered(line_index, index) {
+                return;
+            }
+            let origin = meta.content_rect(line_index, index).origin();
+            widgets[index].set_origin(ctx, data, cell_env(envs, index, env), origin);
+            if let Some(editor) = editor.as_mut().filter(|editor|editor.element == index) {
+                editor.widget.set_origin(ctx, data, env, origin);
+            }
+        })));
+    }
+
+    fn paint_background(&mut self, ctx: &mut PaintCtx, data: &S, _: &Env, meta: &TableLayout, line_index: usize) {
+        let Self {outer_lens, inner_lens, rules, ..} = self;
+        outer_lens.with(data, |data|paint_rule_backgrounds(ctx, data, inner_lens, rules, meta, line_index));
+    }
+
+    fn element_count(&self, data: &S) -> usize {
+        self.outer_lens.with(data, |data|data.data_len())
+    }
+
+    fn validate(&self, data: &S) -> Vec<(usize, String)> {
+        let Self {outer_lens, inner_lens, validators, ..} = self;
+        outer_lens.with(data, |data|validate_line(data, inner_lens, validators))
+    }
+
+    fn changes(&self, baseline: &S, data: &S, elements: &[Option<usize>]) -> Vec<(usize, Rc<dyn Any>, Rc<dyn Any>)> {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with(baseline, |baseline|outer_lens.with(data, |data|diff_line(baseline, data, inner_lens, elements)))
+    }
+
+    fn same_value(&self, a: &dyn Any, b: &dyn Any) -> bool {
+        same_line_value::<V>(a, b)
+    }
+
+    fn value(&self, data: &S, element: usize) -> Option<Rc<dyn Any>> {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with(data, |data|line_value(data, inner_lens, element))
+    }
+
+    fn set_value(&mut self, data: &mut S, element: usize, value: &dyn Any) -> bool {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with_mut(data, |data|set_line_value(data, inner_lens, element, value))
+    }
+
+    fn values(&self, data: &S, elements: RangeInclusive<usize>) -> Vec<(usize, Rc<dyn Any>)> {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with(data, |data|line_values(data, inner_lens, elements))
+    }
+
+    fn set_values(&mut self, data: &mut S, values: &[(usize, Rc<dyn Any>)]) -> usize {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with_mut(data, |data|set_line_values(data, inner_lens, values))
+    }
+
+    fn texts(&self, data: &S) -> Vec<(usize, String)> {
+        let Self {outer_lens, inner_lens, text, ..} = self;
+        outer_lens.with(data, |data|line_texts(data, inner_lens, text.as_ref()))
+    }
+
+    fn set_texts(&mut self, data: &mut S, texts: &[(usize, String)]) -> Vec<(usize, Result<(), String>)> {
+        if !self.editable {
+            return rejected_texts(texts);
+        }
+        let Self {outer_lens, inner_lens, parse, ..} = self;
+        outer_lens.with_mut(data, |data|set_line_texts(data, inner_lens, texts, parse.as_ref()))
+    }
+
+    fn accepts_text(&self) -> bool {
+        self.editable
+    }
+
+    fn needs_cursor(&self) -> bool {
+        self.editable
+    }
+
+    fn move_element(&mut self, from: usize, to: usize) {
+        move_item(&mut self.widgets, from, to);
+        move_item(&mut self.envs, from, to);
+        if let Some(editor) = &mut self.editor {
+            if editor.element == from {
+                editor.element = to;
+            } else if from < editor.element && editor.element <= to {
+                editor.element -= 1;
+            } else if to <= editor.element && editor.element < from {
+                editor.element += 1;
+            }
+        }
+    }
+
+    fn insert_element(&mut self, index: usize) {
+        if index <= self.widgets.len() {
+            self.widgets.insert(index, (self.generate)());
+        }
+        if index <= self.envs.len() && !self.rules.is_empty() {
+            self.envs.insert(index, None);
+        }
+        if let Some(editor) = self.editor.as_mut().filter(|editor|editor.element >= index) {
+            editor.element += 1;
+        }
+    }
+
+    fn remove_element(&mut self, index: usize) {
+        if index < self.widgets.len() {
+            self.widgets.remove(index);
+        }
+        if index < self.envs.len() {
+            self.envs.remove(index);
+        }
+        if self.editor.as_ref().map_or(false, |editor|editor.element == index) {
+            self.editor = None;
+        }
+        if let Some(editor) = self.editor.as_mut().filter(|editor|editor.element > index) {
+            editor.element -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::Modifiers;
+    use druid::im::Vector;
+    use druid::lens::Identity;
+    use druid::widget::{Label, TextBox};
+    use crate::WidgetTableLine;
+    use super::*;
+
+    #[test]
+    fn keys_commit_revert_or_move() {
+        let key = |mods: Modifiers, key: KbKey|KeyEvent::for_test(mods, key);
+        assert_eq!(editor_key(&key(Modifiers::empty(), KbKey::Escape)), EditorKey::Revert);
+        assert_eq!(editor_key(&key(Modifiers::empty(), KbKey::Enter)), EditorKey::Commit);
+        // Shift+Enter is left to the editor, e.g. for a line break
+        assert_eq!(editor_key(&key(Modifiers::SHIFT, KbKey::Enter)), EditorKey::Forward);
+        assert_eq!(editor_key(&key(Modifiers::empty(), KbKey::Tab)), EditorKey::Move(1));
+        assert_eq!(editor_key(&key(Modifiers::SHIFT, KbKey::Tab)), EditorKey::Move(-1));
+        assert_eq!(editor_key(&key(Modifiers::empty(), KbKey::Character("a".into()))), EditorKey::Forward);
+
+        assert_eq!(typed_text(&key(Modifiers::SHIFT, KbKey::Character("A".into()))), Some("A"));
+        assert_eq!(typed_text(&key(Modifiers::CONTROL, KbKey::Character("c".into()))), None);
+    }
+
+    #[test]
+    fn typed_text_replaces_the_value_until_reverted() {
+        let mut data: Vector<String> = vec!["a".to_string(), "b".to_string()].into();
+        let mut line = EditTableLine::new(Identity, Identity, ||Label::dynamic(|value: &String, _|value.clone()), TextBox::new);
+
+        assert!(line.open_editor(&mut data, 1, Some("x".to_string())));
+        assert_eq!(line.editing(), Some(1));
+        assert_eq!(data[1], "x");
+
+        line.revert_editor(&mut data);
+        assert_eq!(line.editing(), None);
+        assert_eq!(data[1], "b");
+
+        // Editing without typing keeps the value, elements outside of the data are not edited
+        assert!(line.open_editor(&mut data, 0, None));
+        assert_eq!(data[0], "a");
+        assert!(!line.open_editor(&mut data, 2, Some("y".to_string())));
+    }
+
+    #[test]
+    fn only_edited_lines_need_the_cursor() {
+        let label = ||Label::dynamic(|value: &String, _|value.clone());
+        let line: EditTableLine<Vector<String>, _, _, _, _, _, _, _> = EditTableLine::new(Identity, Identity, label, TextBox::new);
+        assert!(line.accepts_text() && line.needs_cursor());
+
+        // Widgets which edit their values themselves get text without a cursor
+        let line: WidgetTableLine<Vector<String>, _, _, _, _, _, _> = WidgetTableLine::new(Identity, Identity, TextBox::new).with_editable(true);
+        assert!(line.accepts_text() && !line.needs_cursor());
+
+        let mut data: Vector<String> = vec!["a".to_string()].into();
+        let mut line = EditTableLine::new(Identity, Identity, label, TextBox::new).with_editable(false);
+        assert!(!line.accepts_text() && !line.needs_cursor());
+        assert_eq!(line.set_texts(&mut data, &[(0, "b".to_string())]), vec![(0, Err("the line does not accept text".to_string()))]);
+        assert_eq!(data[0], "a");
+    }
+}
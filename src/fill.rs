@@ -0,0 +1,311 @@
+use std::any::Any;
+use std::rc::Rc;
+use druid::{Env, Event, EventCtx, KbKey, LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect, RenderContext, Size, UpdateCtx, Data};
+use druid::widget::Axis;
+use crate::{TableAxis, TableController, TableLayout};
+use crate::commands::SET_SELECTION;
+use crate::layout::CellRange;
+use crate::table::TableContent;
+use crate::theme;
+
+/// Fills cells by dragging the handle at the corner of the selection, with Ctrl+D (fill down)
+/// and Ctrl+R (fill right).
+///
+/// Values are copied through the lenses of the lines. Two or more numbers with a constant step,
+/// e.g. `1, 2` or dates given as timestamps, are continued as a series. Hidden lines are skipped.
+pub struct FillController<T> {
+    inner: Box<dyn TableController<T>>,
+    /// The selection when the handle was grabbed.
+    dragging: Option<CellRange>,
+    /// The cells which will be filled while the handle is dragged.
+    preview: Option<CellRange>,
+    /// The size of the handle when it was painted, see `theme::FILL_HANDLE_SIZE`.
+    handle_size: f64,
+}
+
+impl<T: Data> FillController<T> {
+    pub fn new(inner: impl TableController<T> + 'static) -> Self {
+        Self {
+            inner: Box::new(inner),
+            dragging: None,
+            preview: None,
+            handle_size: 0.0,
+        }
+    }
+}
+
+/// The handle at the end corner of the selection, which is dragged to fill cells.
+fn fill_handle_rect(layout: &TableLayout, size: f64) -> Option<Rect> {
+    let selection = layout.selection()?;
+    if selection.lines.1 >= layout.lines().length() || selection.elements.1 >= layout.elements().length() {
+        return None;
+    }
+    let corner = layout.layout_rect(selection.lines.1, selection.elements.1);
+    Some(Rect::from_center_size((corner.x1, corner.y1), Size::new(size, size)))
+}
+
+impl<T: Data> TableController<T> for FillController<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env, content: &mut TableContent<T>, layout: &TableLayout) {
+        match event {
+            Event::MouseDown(mouse) if fill_handle_rect(layout, self.handle_size).map_or(false, |rect|rect.contains(mouse.pos)) => {
+                self.dragging = layout.selection();
+                ctx.set_active(true);
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseMove(mouse) if self.dragging.is_some() => {
+                let preview = match (self.dragging, layout.cell_at(mouse.pos)) {
+                    (Some(source), Some(cell)) => extend_range(source, cell),
+                    _ => None,
+                };
+                if preview != self.preview {
+                    self.preview = preview;
+                    ctx.request_paint();
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::MouseUp(_) if self.dragging.is_some() => {
+                if let (Some(source), Some(target)) = (self.dragging.take(), self.preview.take()) {
+                    let axis = if target.elements != source.elements {TableAxis::ElementAxis} else {TableAxis::LineAxis};
+                    fill(content, data, layout, source, target, axis);
+                    ctx.submit_command(SET_SELECTION.with(target).to(ctx.widget_id()));
+                }
+                ctx.set_active(false);
+                ctx.request_paint();
+                ctx.set_handled();
+                return;
+            }
+            _ => {}
+        }
+
+        self.inner.event(ctx, event, data, env, content, layout);
+        if ctx.is_handled() {
+            return;
+        }
+
+        if let Event::KeyDown(key) = event {
+            if !(key.mods.ctrl() || key.mods.meta()) {
+                return;
+            }
+            // Down and right on the screen, along whichever axis of the table runs that way
+            let direction = match &key.key {
+                KbKey::Character(c) if c.eq_ignore_ascii_case("d") => Axis::Vertical,
+                KbKey::Character(c) if c.eq_ignore_ascii_case("r") => Axis::Horizontal,
+                _ => return,
+            };
+            let axis = if layout.axis_direction(TableAxis::LineAxis) == direction {TableAxis::ElementAxis} else {TableAxis::LineAxis};
+            let target = match layout.selection() {
+                Some(target) => target,
+                None => return,
+            };
+            // The first element (or line) of the selection is copied into the others
+            let source = match axis {
+                TableAxis::ElementAxis => CellRange {elements: (target.elements.0, target.elements.0), ..target},
+                TableAxis::LineAxis => CellRange {lines: (target.lines.0, target.lines.0), ..target},
+            };
+            // Keys which fill nothing are left to the application
+            if fill(content, data, layout, source, target, axis) > 0 {
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.inner.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        self.inner.update(ctx, old_data, data, env);
+    }
+
+    fn captures_mouse(&self, pos: Point, layout: &TableLayout) -> bool {
+        fill_handle_rect(layout, self.handle_size).map_or(false, |rect|rect.contains(pos)) || self.inner.captures_mouse(pos, layout)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, env: &Env, layout: &TableLayout) {
+        self.inner.paint(ctx, env, layout);
+
+        let in_table = |range: &CellRange|range.lines.1 < layout.lines().length() && range.elements.1 < layout.elements().length();
+        if let Some(preview) = self.preview.filter(in_table) {
+            ctx.stroke(layout.range_rect(preview).inset(-0.5), &theme::focus_ring_color(env), 1.0);
+        }
+        self.handle_size = theme::fill_handle_size(env);
+        if let Some(handle) = fill_handle_rect(layout, self.handle_size) {
+            ctx.fill(handle, &theme::focus_ring_color(env));
+        }
+    }
+}
+
+/// Extends the source range towards the cell along the axis with the larger distance.
+fn extend_range(source: CellRange, (line, element): (usize, usize)) -> Option<CellRange> {
+    let elements = element.saturating_sub(source.elements.1);
+    let lines = line.saturating_sub(source.lines.1);
+    if elements == 0 && lines == 0 {
+        None
+    } else if elements >= lines {
+        Some(CellRange {elements: (source.elements.0, element), ..source})
+    } else {
+        Some(CellRange {lines: (source.lines.0, line), ..source})
+    }
+}
+
+/// Fills the cells of `target` outside of `source` with the values of `source` along `axis`.
+/// Every line is written at once, hidden lines are neither read nor written.
+/// Returns the count of written cells.
+fn fill<T: Data>(content: &mut TableContent<T>, data: &mut T, layout: &TableLayout, source: CellRange, target: CellRange, axis: TableAxis) -> usize {
+    let visible = |line: &usize|!layout.is_line_hidden(*line);
+    let mut written = 0;
+    content.begin_group();
+    match axis {
+        TableAxis::ElementAxis => {
+            for line in (target.lines.0..=target.lines.1).filter(visible) {
+                let values: Vec<_> = content.values(data, line, source.elements.0..=source.elements.1)
+                    .into_iter()
+                    .map(|(_, value)|value)
+                    .collect();
+                let template = match values.first() {
+                    Some(template) => template.clone(),
+                    None => continue,
+                };
+                let writes: Vec<_> = (source.elements.1 + 1..=target.elements.1)
+                    .filter_map(|element|Some((element, series_value(&values, element - source.elements.0, &*template)?)))
+                    .collect();
+                written += content.set_values(data, line, &writes);
+            }
+        }
+        TableAxis::LineAxis => {
+            // The values of every element in the source lines, in the order of the lines
+            let first = target.elements.0;
+            let mut columns = vec![vec![]; target.elements.1 + 1 - first];
+            let source_lines: Vec<usize> = (source.lines.0..=source.lines.1).filter(visible).collect();
+            for line in &source_lines {
+                for (element, value) in content.values(data, *line, first..=target.elements.1) {
+                    columns[element - first].push(value);
+                }
+            }
+            let target_lines = (source.lines.1 + 1..=target.lines.1).filter(visible);
+            for (offset, line) in target_lines.enumerate() {
+                // The source lines may have other types, every cell converts the values to its own
+                let writes: Vec<_> = content.values(data, line, first..=target.elements.1)
+                    .into_iter()
+                    .filter_map(|(element, template)|{
+                        let value = series_value(&columns[element - first], source_lines.len() + offset, &*template)?;
+                        Some((element, value))
+                    })
+                    .collect();
+                written += content.set_values(data, line, &writes);
+            }
+        }
+    }
+    content.end_group();
+    written
+}
+
+/// The value at `index` of the series started by `values`, which repeat if they are no numeric series.
+/// Numbers are converted to the type of `template`, the value of the written cell.
+fn series_value(values: &[Rc<dyn Any>], index: usize, template: &dyn Any) -> Option<Rc<dyn Any>> {
+    if values.is_empty() {
+        return None;
+    }
+    let numbers: Option<Vec<f64>> = values.iter().map(|value|as_number(&**value)).collect();
+    if let Some(numbers) = numbers.filter(|numbers|numbers.len() >= 2) {
+        let step = numbers[1] - numbers[0];
+        let is_series = numbers.windows(2).all(|pair|((pair[1] - pair[0]) - step).abs() < 1e-9);
+        if is_series {
+            return from_number(template, numbers[0] + step * index as f64);
+        }
+    }
+    let value = values[index % values.len()].clone();
+    if (*value).type_id() == (*template).type_id() {
+        return Some(value);
+    }
+    as_number(&*value).and_then(|number|from_number(template, number)).or(Some(value))
+}
+
+fn as_number(value: &dyn Any) -> Option<f64> {
+    value.downcast_ref::<f64>().copied()
+        .or_else(||value.downcast_ref::<f32>().map(|value|*value as f64))
+        .or_else(||value.downcast_ref::<i64>().map(|value|*value as f64))
+        .or_else(||value.downcast_ref::<i32>().map(|value|*value as f64))
+        .or_else(||value.downcast_ref::<u64>().map(|value|*value as f64))
+        .or_else(||value.downcast_ref::<u32>().map(|value|*value as f64))
+        .or_else(||value.downcast_ref::<usize>().map(|value|*value as f64))
+}
+
+/// Converts the number to the type of `template`.
+fn from_number(template: &dyn Any, number: f64) -> Option<Rc<dyn Any>> {
+    let number = if template.is::<f64>() || template.is::<f32>() {number} else {number.round()};
+    let value: Rc<dyn Any> = if template.is::<f64>() {
+        Rc::new(number)
+    } else if template.is::<f32>() {
+        Rc::new(number as f32)
+    } else if template.is::<i64>() {
+        Rc::new(number as i64)
+    } else if template.is::<i32>() {
+        Rc::new(number as i32)
+    } else if template.is::<u64>() {
+        Rc::new(number.max(0.0) as u64)
+    } else if template.is::<u32>() {
+        Rc::new(number.max(0.0) as u32)
+    } else if template.is::<usize>() {
+        Rc::new(number.max(0.0) as usize)
+    } else {
+        return None;
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series<V: Clone + 'static>(values: &[V], index: usize) -> Option<V> {
+        let values: Vec<Rc<dyn Any>> = values.iter().map(|value|Rc::new(value.clone()) as Rc<dyn Any>).collect();
+        let template = values.first()?.clone();
+        series_value(&values, index, &*template).and_then(|value|value.downcast_ref::<V>().cloned())
+    }
+
+    #[test]
+    fn numbers_with_a_constant_step_continue() {
+        assert_eq!(series(&[1.0, 2.0], 2), Some(3.0));
+        assert_eq!(series(&[1.0, 2.0], 5), Some(6.0));
+        assert_eq!(series(&[10i64, 20], 3), Some(40));
+        assert_eq!(series(&[1.5f32, 1.0], 3), Some(0.0));
+        // Unsigned series stop at zero
+        assert_eq!(series(&[3u64, 1], 3), Some(0));
+    }
+
+    #[test]
+    fn other_values_repeat() {
+        assert_eq!(series(&[1.0, 5.0, 6.0], 3), Some(1.0));
+        assert_eq!(series(&[1.0, 5.0, 6.0], 4), Some(5.0));
+        assert_eq!(series(&[7i32], 3), Some(7));
+        assert_eq!(series(&["a".to_string(), "b".to_string()], 2), Some("a".to_string()));
+        assert_eq!(series::<f64>(&[], 1), None);
+    }
+
+    #[test]
+    fn values_take_the_type_of_the_written_cell() {
+        // e.g. a line of i32 and a line of f64 filled into a line of u32
+        let values: Vec<Rc<dyn Any>> = vec![Rc::new(1i32), Rc::new(2.0f64)];
+        let value = series_value(&values, 2, &0u32).unwrap();
+        assert_eq!(value.downcast_ref::<u32>(), Some(&3));
+
+        let values: Vec<Rc<dyn Any>> = vec![Rc::new(4i32), Rc::new("a".to_string())];
+        let value = series_value(&values, 2, &0.0f64).unwrap();
+        assert_eq!(value.downcast_ref::<f64>(), Some(&4.0));
+        // Values which can not be converted are written as they are and rejected by the line
+        let value = series_value(&values, 3, &0.0f64).unwrap();
+        assert_eq!(value.downcast_ref::<String>(), Some(&"a".to_string()));
+    }
+
+    #[test]
+    fn range_extends_along_the_larger_distance() {
+        let source = CellRange {lines: (0, 0), elements: (0, 1)};
+        assert_eq!(extend_range(source, (0, 5)), Some(CellRange {lines: (0, 0), elements: (0, 5)}));
+        assert_eq!(extend_range(source, (3, 2)), Some(CellRange {lines: (0, 3), elements: (0, 1)}));
+        assert_eq!(extend_range(source, (0, 1)), None);
+    }
+}
@@ -0,0 +1,556 @@
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use std::sync::Arc;
+use druid::{Env, Event, EventCtx, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, UpdateCtx, Widget, Data};
+use druid::text::{Formatter, Selection, Validation, ValidationError};
+use druid::widget::{Align, Label, ListIter, TextBox, ValueTextBox};
+use crate::{EditTableLine, TableLayout, TableLine};
+use crate::rule::FormatRule;
+
+/// Converts the values of a line to text and back.
+pub trait CellFormat<V> {
+    fn format(&self, value: &V) -> String;
+
+    /// Parses edited text, the error is shown to the user.
+    fn parse(&self, text: &str) -> Result<V, String>;
+
+    /// Numbers are aligned to the right, text to the left.
+    fn is_numeric(&self) -> bool {
+        false
+    }
+}
+
+/// Plain text, left aligned.
+#[derive(Clone, Default)]
+pub struct TextFormat;
+
+/// A number with a fixed count of decimals, optional thousands separator, prefix and suffix,
+/// for example currencies or units.
+#[derive(Clone)]
+pub struct NumberFormat {
+    decimals: usize,
+    separator: Option<char>,
+    prefix: String,
+    suffix: String,
+}
+
+/// A date or date and time of a unix timestamp in seconds, shown in UTC as `2020-12-31 23:59`.
+#[derive(Clone)]
+pub struct DateTimeFormat {
+    time: bool,
+}
+
+/// A count of bytes shown with a binary unit, for example `1.5 MB`.
+#[derive(Clone, Default)]
+pub struct ByteSizeFormat;
+
+impl CellFormat<String> for TextFormat {
+    fn format(&self, value: &String) -> String {
+        value.clone()
+    }
+
+    fn parse(&self, text: &str) -> Result<String, String> {
+        Ok(text.to_string())
+    }
+}
+
+impl CellFormat<Arc<String>> for TextFormat {
+    fn format(&self, value: &Arc<String>) -> String {
+        value.to_string()
+    }
+
+    fn parse(&self, text: &str) -> Result<Arc<String>, String> {
+        Ok(Arc::new(text.to_string()))
+    }
+}
+
+impl NumberFormat {
+    pub fn new(decimals: usize) -> Self {
+        Self {
+            decimals,
+            separator: None,
+            prefix: String::new(),
+            suffix: String::new(),
+        }
+    }
+
+    pub fn with_thousands_separator(mut self, separator: char) -> Self {
+        self.separator = Some(separator);
+        self
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = suffix.into();
+        self
+    }
+}
+
+impl CellFormat<f64> for NumberFormat {
+    fn format(&self, value: &f64) -> String {
+        let text = format!("{:.*}", self.decimals, value.abs());
+        let (integer, fraction) = match text.find('.') {
+            Some(index) => text.split_at(index),
+            None => (text.as_str(), ""),
+        };
+
+        let mut result = String::new();
+        if value.is_sign_negative() && text.chars().any(|c|c.is_ascii_digit() && c != '0') {
+            result.push('-');
+        }
+        result.push_str(&self.prefix);
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                if let Some(separator) = self.separator {
+                    result.push(separator);
+                }
+            }
+            result.push(digit);
+        }
+        result.push_str(fraction);
+        result.push_str(&self.suffix);
+        result
+    }
+
+    fn parse(&self, text: &str) -> Result<f64, String> {
+        let text = text.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text.trim_start()),
+            None => (false, text),
+        };
+        let text = text.strip_prefix(self.prefix.as_str()).unwrap_or(text);
+        let text = text.strip_suffix(self.suffix.as_str()).unwrap_or(text);
+        let text: String = text.trim().chars().filter(|c|Some(*c) != self.separator).collect();
+        let value = text.parse::<f64>().map_err(|_|format!("\"{}\" is not a number", text))?;
+        Ok(if negative {-value} else {value})
+    }
+
+    fn is_numeric(&self) -> bool {
+        true
+    }
+}
+
+impl DateTimeFormat {
+    pub fn date() -> Self {
+        Self {
+            time: false,
+        }
+    }
+
+    pub fn date_time() -> Self {
+        Self {
+            time: true,
+        }
+    }
+}
+
+// Conversion between days since 1970-01-01 and the proleptic gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 {year - 1} else {year};
+    let era = (if year >= 0 {year} else {year - 399}) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 {month - 3} else {month + 9}) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = (if days >= 0 {days} else {days - 146096}) / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 {month + 3} else {month - 9};
+    let year = year_of_era + era * 400;
+    (if month <= 2 {year + 1} else {year}, month, day)
+}
+
+impl CellFormat<i64> for DateTimeFormat {
+    fn format(&self, value: &i64) -> String {
+        let (year, month, day) = civil_from_days(value.div_euclid(86400));
+        let seconds = value.rem_euclid(86400);
+        if self.time {
+            format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, seconds / 3600, seconds / 60 % 60)
+        } else {
+            format!("{:04}-{:02}-{:02}", year, month, day)
+        }
+    }
+
+    fn parse(&self, text: &str) -> Result<i64, String> {
+        let error = ||format!("\"{}\" is not a date, expected {}", text.trim(), if self.time {"YYYY-MM-DD HH:MM"} else {"YYYY-MM-DD"});
+        let mut parts = text.split_whitespace();
+
+        let date: Vec<i64> = parts.next().ok_or_else(error)?
+            .split('-')
+            .map(|part|part.parse().map_err(|_|error()))
+            .collect::<Result<_, _>>()?;
+        let (year, month, day) = match date.as_slice() {
+            [year, month, day] if (1..=12).contains(month) && (1..=31).contains(day) => (*year, *month, *day),
+            _ => return Err(error()),
+        };
+
+        let time: Vec<i64> = match parts.next() {
+            Some(time) if self.time => time.split(':').map(|part|part.parse().map_err(|_|error())).collect::<Result<_, _>>()?,
+            Some(_) => return Err(error()),
+            None => vec![],
+        };
+        let seconds = match time.as_slice() {
+            [] => 0,
+            [hour, minute] if *hour < 24 && *minute < 60 => hour * 3600 + minute * 60,
+            [hour, minute, second] if *hour < 24 && *minute < 60 && *second < 60 => hour * 3600 + minute * 60 + second,
+            _ => return Err(error()),
+        };
+        if parts.next().is_some() {
+            return Err(error());
+        }
+
+        // Days past the end of the month, like 2021-02-30, don't survive the round trip
+        let days = days_from_civil(year, month, day);
+        if civil_from_days(days) != (year, month, day) {
+            return Err(error());
+        }
+        Ok(days * 86400 + seconds)
+    }
+
+    fn is_numeric(&self) -> bool {
+        true
+    }
+}
+
+const BYTE_UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+impl CellFormat<u64> for ByteSizeFormat {
+    fn format(&self, value: &u64) -> String {
+        let mut size = *value as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit + 1 < BYTE_UNITS.len() {
+            size /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} B", value)
+        } else {
+            format!("{:.1} {}", size, BYTE_UNITS[unit])
+        }
+    }
+
+    fn parse(&self, text: &str) -> Result<u64, String> {
+        let text = text.trim();
+        let split = text.find(|c: char|!c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let number = number.parse::<f64>().map_err(|_|format!("\"{}\" is not a size", text))?;
+        let unit = unit.trim().to_uppercase();
+        let exponent = match unit.as_str() {
+            "" => 0,
+            unit => BYTE_UNITS.iter()
+                .position(|name|*name == unit || name.trim_end_matches('B') == unit)
+                .ok_or_else(||format!("unknown unit \"{}\"", unit))?,
+        };
+        Ok((number * 1024f64.powi(exponent as i32)).round() as u64)
+    }
+
+    fn is_numeric(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+struct FormatError(String);
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Makes a `CellFormat` usable by `TextBox::with_formatter`.
+struct CellFormatter<V>(Rc<dyn CellFormat<V>>);
+
+impl<V> Formatter<V> for CellFormatter<V> {
+    fn format(&self, value: &V) -> String {
+        self.0.format(value)
+    }
+
+    fn validate_partial_input(&self, _: &str, _: &Selection) -> Validation {
+        // Partial input like a single minus sign is allowed, the value is validated on commit
+        Validation::success()
+    }
+
+    fn value(&self, input: &str) -> Result<V, ValidationError> {
+        self.0.parse(input).map_err(|message|ValidationError::new(FormatError(message)))
+    }
+}
+
+/// Shows a formatted value, aligned by the kind of format.
+fn formatted_label<V: Data>(format: Rc<dyn CellFormat<V>>) -> Box<dyn Widget<V>> {
+    let numeric = format.is_numeric();
+    let label = Label::dynamic(move |value: &V, _|format.format(value));
+    if numeric {
+        Box::new(Align::right(label))
+    } else {
+        Box::new(Align::left(label))
+    }
+}
+
+/// A line which shows its values through a `CellFormat`, numbers are aligned to the right.
+pub struct FormattedLine<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+    V: Data,
+
+    L1: Lens<S, T>,
+    L2: Lens<U, V>,
+> {
+    line: EditTableLine<S, T, U, V, L1, L2, Box<dyn Widget<V>>, ValueTextBox<V>>,
+    phantom: PhantomData<(S, T, U, V)>,
+}
+
+impl<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+    V: Data,
+
+    L1: Lens<S, T>,
+    L2: Lens<U, V>,
+> FormattedLine<S, T, U, V, L1, L2> {
+    pub fn new(outer_lens: L1, inner_lens: L2, format: impl CellFormat<V> + 'static) -> Self {
+        Self::with_editing(outer_lens, inner_lens, format, false)
+    }
+
+    /// Cells are edited with a `TextBox` like an `EditTableLine`, invalid input is rejected.
+    pub fn editable(outer_lens: L1, inner_lens: L2, format: impl CellFormat<V> + 'static) -> Self {
+        Self::with_editing(outer_lens, inner_lens, format, true)
+    }
+
+    fn with_editing(outer_lens: L1, inner_lens: L2, format: impl CellFormat<V> + 'static, editable: bool) -> Self {
+        let format: Rc<dyn CellFormat<V>> = Rc::new(format);
+        let label_format = format.clone();
+        let text_format = format.clone();
+        let parse_format = format.clone();
+        Self {
+            line: EditTableLine::new(
+                outer_lens,
+                inner_lens,
+                move||formatted_label(label_format.clone()),
+                move||TextBox::new().with_formatter(CellFormatter(format.clone())),
+            )
+                .with_editable(editable)
+                .with_text(move|value|text_format.format(value))
+                .with_parser(move|text|parse_format.parse(text)),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn with_rule(mut self, rule: FormatRule<V>) -> Self {
+        self.line = self.line.with_rule(rule);
+        self
+    }
+
+    pub fn with_validator(mut self, validator: impl Fn(&V) -> Result<(), String> + 'static) -> Self {
+        self.line = self.line.with_validator(validator);
+        self
+    }
+}
+
+impl<
+    S: Data,
+    T: ListIter<U> + Data,
+    U: Data,
+    V: Data,
+
+    L1: Lens<S, T>,
+    L2: Lens<U, V>,
+> TableLine<S> for FormattedLine<S, T, U, V, L1, L2> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut S, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.line.event(ctx, event, data, env, meta, line_index);
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &S, env: &Env) {
+        self.line.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, data: &S, env: &Env) {
+        self.line.update(ctx, data, env);
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.line.paint(ctx, data, env, meta, line_index);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &mut TableLayout, line_index: usize) {
+        self.line.layout(ctx, data, env, meta, line_index);
+    }
+
+    fn arrange(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.line.arrange(ctx, data, env, meta, line_index);
+    }
+
+    fn element_count(&self, data: &S) -> usize {
+        self.line.element_count(data)
+    }
+
+    fn paint_background(&mut self, ctx: &mut PaintCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.line.paint_background(ctx, data, env, meta, line_index);
+    }
+
+    fn accepts_text(&self) -> bool {
+        self.line.accepts_text()
+    }
+
+    fn needs_cursor(&self) -> bool {
+        self.line.needs_cursor()
+    }
+
+    fn move_element(&mut self, from: usize, to: usize) {
+        self.line.move_element(from, to);
+    }
+
+    fn insert_element(&mut self, index: usize) {
+        self.line.insert_element(index);
+    }
+
+    fn remove_element(&mut self, index: usize) {
+        self.line.remove_element(index);
+    }
+
+    fn validate(&self, data: &S) -> Vec<(usize, String)> {
+        self.line.validate(data)
+    }
+
+    fn changes(&self, baseline: &S, data: &S, elements: &[Option<usize>]) -> Vec<(usize, Rc<dyn Any>, Rc<dyn Any>)> {
+        self.line.changes(baseline, data, elements)
+    }
+
+    fn same_value(&self, a: &dyn Any, b: &dyn Any) -> bool {
+        self.line.same_value(a, b)
+    }
+
+    fn value(&self, data: &S, element: usize) -> Option<Rc<dyn Any>> {
+        self.line.value(data, element)
+    }
+
+    fn set_value(&mut self, data: &mut S, element: usize, value: &dyn Any) -> bool {
+        self.line.set_value(data, element, value)
+    }
+
+    fn values(&self, data: &S, elements: RangeInclusive<usize>) -> Vec<(usize, Rc<dyn Any>)> {
+        self.line.values(data, elements)
+    }
+
+    fn set_values(&mut self, data: &mut S, values: &[(usize, Rc<dyn Any>)]) -> usize {
+        self.line.set_values(data, values)
+    }
+
+    fn texts(&self, data: &S) -> Vec<(usize, String)> {
+        self.line.texts(data)
+    }
+
+    fn set_texts(&mut self, data: &mut S, texts: &[(usize, String)]) -> Vec<(usize, Result<(), String>)> {
+        self.line.set_texts(data, texts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number_round_trip() {
+        let format = NumberFormat::new(2).with_thousands_separator(',').with_prefix("$");
+        assert_eq!(format.format(&1234567.891), "$1,234,567.89");
+        assert_eq!(format.parse("$1,234,567.89"), Ok(1234567.89));
+        assert_eq!(format.format(&-12.5), "-$12.50");
+        assert_eq!(format.parse("-$12.50"), Ok(-12.5));
+        assert_eq!(format.format(&-0.001), "$0.00");
+
+        let format = NumberFormat::new(0).with_suffix(" kg");
+        assert_eq!(format.format(&999.0), "999 kg");
+        assert_eq!(format.parse(" 999 kg "), Ok(999.0));
+        assert!(format.parse("kg").is_err());
+    }
+
+    #[test]
+    fn text_round_trip() {
+        assert_eq!(CellFormat::<String>::parse(&TextFormat, "a b"), Ok("a b".to_string()));
+        assert_eq!(CellFormat::<Arc<String>>::format(&TextFormat, &Arc::new("a".to_string())), "a");
+    }
+
+    #[test]
+    fn byte_size_round_trip() {
+        let format = ByteSizeFormat;
+        assert_eq!(format.format(&512), "512 B");
+        assert_eq!(format.format(&1536), "1.5 KB");
+        assert_eq!(format.parse("1.5 KB"), Ok(1536));
+        assert_eq!(format.parse("2 mb"), Ok(2 * 1024 * 1024));
+        assert_eq!(format.parse("512"), Ok(512));
+        assert!(format.parse("1 PB").is_err());
+    }
+
+    #[test]
+    fn date_round_trip() {
+        let date = DateTimeFormat::date();
+        assert_eq!(date.format(&0), "1970-01-01");
+        assert_eq!(date.parse("1970-01-01"), Ok(0));
+        assert_eq!(date.parse("2020-02-29").map(|value|date.format(&value)), Ok("2020-02-29".to_string()));
+        assert!(date.parse("2021-02-29").is_err());
+        assert!(date.parse("2021-02-31").is_err());
+        assert!(date.parse("2021-04-31").is_err());
+        assert!(date.parse("2021-13-01").is_err());
+        assert!(date.parse("2021-01-01 12:00").is_err());
+
+        let date_time = DateTimeFormat::date_time();
+        assert_eq!(date_time.format(&-1), "1969-12-31 23:59");
+        assert_eq!(date_time.parse("1970-01-02 01:30"), Ok(86400 + 5400));
+        assert!(date_time.parse("1970-01-02 24:00").is_err());
+    }
+
+    #[test]
+    fn civil_days_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in (-800_000..800_000).step_by(97) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn only_editable_lines_accept_text() {
+        use druid::im::Vector;
+        use druid::lens::Identity;
+
+        let mut data: Vector<f64> = vec![1.0, 2.0, 3.0].into();
+        let texts = vec![(0, "5".to_string()), (2, "x".to_string()), (7, "1".to_string())];
+
+        type Line = FormattedLine<Vector<f64>, Vector<f64>, f64, f64, Identity, Identity>;
+
+        let mut line = Line::new(Identity, Identity, NumberFormat::new(0));
+        let results = line.set_texts(&mut data, &texts);
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|(_, result)|result.is_err()));
+        assert_eq!(data, Vector::from(vec![1.0, 2.0, 3.0]));
+
+        let mut line = Line::editable(Identity, Identity, NumberFormat::new(0));
+        let mut results = line.set_texts(&mut data, &texts);
+        results.sort_by_key(|(element, _)|*element);
+        assert_eq!(results[0], (0, Ok(())));
+        assert!(results[1].1.is_err());
+        assert_eq!(results[1].0, 2);
+        assert!(results[2].1.is_err());
+        assert_eq!(data, Vector::from(vec![5.0, 2.0, 3.0]));
+    }
+}
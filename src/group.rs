@@ -0,0 +1,643 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+use std::sync::Arc;
+use druid::{BoxConstraints, Env, Event, EventCtx, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Point, Size, UpdateCtx, Widget, WidgetPod, Data, RenderContext};
+use druid::im::Vector;
+use druid::theme;
+use druid::widget::{Label, ListIter};
+use crate::{CellSpan, TableLayout, TableLine, TablePolicy};
+use crate::util::disclosure_triangle;
+
+type GroupKey<U> = Box<dyn Fn(&U) -> String>;
+type GroupAggregate<U> = (String, Box<dyn Fn(&[U]) -> String>);
+
+/// Groups the elements of a `Vector` by one or more keys.
+///
+/// Every group is preceded by a header row showing the key and the count of the group, followed by
+/// the aggregates of the lines added with `with_aggregated_line`. The title starts at the first
+/// visible line without an aggregate and spans the lines up to the next aggregated line. Groups are ordered by their key, numbers inside a key are compared by
+/// value. The rows of the table are provided by `GroupPolicy::lens`, which has to be used as the
+/// outer lens of all lines.
+///
+/// The collapsed groups are kept by the policy and not in the data, they are neither part of the
+/// edit history nor of a `TableViewState`.
+pub struct GroupPolicy<U, L> {
+    lens: L,
+    state: Rc<GroupState<U>>,
+    spans: Vec<usize>,
+    grouped: bool,
+}
+
+pub struct GroupState<U> {
+    keys: RefCell<Vec<GroupKey<U>>>,
+    aggregates: RefCell<Vec<GroupAggregate<U>>>,
+    rows: RefCell<Vec<GroupEntry>>,
+    collapsed: RefCell<HashSet<Vec<String>>>,
+}
+
+#[derive(Clone)]
+enum GroupEntry {
+    Header(GroupHeader),
+    Item(usize),
+}
+
+#[derive(Clone, Data)]
+pub struct GroupHeader {
+    key: Arc<String>,
+    path: Arc<Vec<String>>,
+    depth: usize,
+    count: usize,
+    aggregates: Arc<Vec<(String, String)>>,
+    collapsed: bool,
+}
+
+#[derive(Clone, Data)]
+pub enum GroupRow<U> {
+    Header(GroupHeader),
+    Item(U),
+}
+
+/// The rows of a grouped `Vector`, consisting of group headers and the elements of expanded groups.
+#[derive(Clone, Data)]
+pub struct GroupedList<U: 'static> {
+    source: Vector<U>,
+    state: Rc<GroupState<U>>,
+}
+
+pub struct GroupLens<L, U> {
+    lens: L,
+    state: Rc<GroupState<U>>,
+}
+
+enum GroupCellContent<V, W> {
+    Header(WidgetPod<GroupHeader, GroupHeaderCell>),
+    Item(WidgetPod<V, W>),
+}
+
+pub struct GroupCell<U, V, L, W> {
+    lens: L,
+    aggregate: Option<Arc<String>>,
+    content: GroupCellContent<V, W>,
+    generate: Rc<dyn Fn() -> W>,
+    phantom: std::marker::PhantomData<U>,
+}
+
+pub struct GroupHeaderCell {
+    label: WidgetPod<GroupHeader, Label<GroupHeader>>,
+    aggregate: Option<Arc<String>>,
+    indent: f64,
+}
+
+impl<U: Data, L> GroupPolicy<U, L> {
+    pub fn new(lens: L) -> Self {
+        Self {
+            lens,
+            state: Rc::new(GroupState {
+                keys: RefCell::new(vec![]),
+                aggregates: RefCell::new(vec![]),
+                rows: RefCell::new(vec![]),
+                collapsed: RefCell::new(HashSet::new()),
+            }),
+            spans: vec![],
+            grouped: false,
+        }
+    }
+
+    pub fn with_key(mut self, key: impl Fn(&U) -> String + 'static) -> Self {
+        self.state.keys.borrow_mut().push(Box::new(key));
+        self.grouped = false;
+        self
+    }
+
+    /// Adds an aggregate of the elements of a group, it is shown in the group header by the line
+    /// with the same key, see `Table::with_aggregated_line`.
+    pub fn with_aggregate(mut self, line_key: impl Into<String>, aggregate: impl Fn(&[U]) -> String + 'static) -> Self {
+        self.state.aggregates.borrow_mut().push((line_key.into(), Box::new(aggregate)));
+        self.grouped = false;
+        self
+    }
+
+    pub fn lens(&self) -> GroupLens<L, U> where L: Clone {
+        GroupLens {
+            lens: self.lens.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S: Data, U: Data, L: Lens<S, Vector<U>>> TablePolicy<S> for GroupPolicy<U, L> {
+    fn update(&mut self, old_data: &S, data: &S, widgets: &mut Vec<Box<dyn TableLine<S>>>, meta: &mut TableLayout) {
+        if !self.grouped || !old_data.same(data) {
+            let state = &self.state;
+            self.lens.with(data, |source|state.regroup(source));
+            self.grouped = true;
+        }
+
+        self.place_titles(widgets.len(), meta);
+    }
+
+    fn lines_changed(&mut self, widgets: &[Box<dyn TableLine<S>>], meta: &mut TableLayout) {
+        self.place_titles(widgets.len(), meta);
+    }
+}
+
+impl<U: Data, L> GroupPolicy<U, L> {
+    /// Spans the titles of the group headers over the first visible line without an aggregate and
+    /// the lines up to the next aggregated line. The spans are placed again when groups collapse
+    /// or lines move, which changes the rows or lines without changing the data.
+    fn place_titles(&mut self, lines: usize, meta: &mut TableLayout) {
+        let title = {
+            let aggregates = self.state.aggregates.borrow();
+            let is_aggregate = |line: usize|meta.lines().key(line).map_or(false, |key|aggregates.iter().any(|(name, _)|name == key));
+            let lines = lines.min(meta.lines().length());
+            (0..lines)
+                .find(|line|!meta.is_line_hidden(*line) && !is_aggregate(*line))
+                .map(|first|(first, (first..lines).take_while(|line|!is_aggregate(*line)).count()))
+        };
+
+        // Moving a line moves the spans of the table with it, the old titles are found by their element
+        let elements: HashSet<usize> = self.spans.drain(..).collect();
+        let old_spans: Vec<(usize, usize)> = meta.spans().iter()
+            .filter(|span|elements.contains(&span.element))
+            .map(|span|(span.line, span.element))
+            .collect();
+        for (line, element) in old_spans {
+            meta.remove_span(line, element);
+        }
+
+        let (first, length) = match title {
+            Some(title) => title,
+            None => return,
+        };
+        for (element, row) in self.state.rows.borrow().iter().enumerate() {
+            if let GroupEntry::Header(_) = row {
+                meta.set_span(CellSpan::new(first, element, length, 1));
+                self.spans.push(element);
+            }
+        }
+    }
+}
+
+impl<U: Data> GroupState<U> {
+    fn regroup(&self, source: &Vector<U>) {
+        let mut rows = vec![];
+        let collapsed = self.collapsed.borrow();
+        self.group(source, (0..source.len()).collect(), vec![], &collapsed, &mut rows);
+        *self.rows.borrow_mut() = rows;
+    }
+
+    fn group(&self, source: &Vector<U>, items: Vec<usize>, path: Vec<String>, collapsed: &HashSet<Vec<String>>, rows: &mut Vec<GroupEntry>) {
+        let depth = path.len();
+        let keys = self.keys.borrow();
+        let key = match keys.get(depth) {
+            Some(key) => key,
+            None => {
+                rows.extend(items.into_iter().map(GroupEntry::Item));
+                return;
+            }
+        };
+
+        let mut groups: Vec<(String, Vec<usize>)> = vec![];
+        let mut indices = HashMap::new();
+        for item in items {
+            let key = key(&source[item]);
+            let index = *indices.entry(key.clone()).or_insert_with(||{
+                groups.push((key, vec![]));
+                groups.len() - 1
+            });
+            groups[index].1.push(item);
+        }
+        groups.sort_by(|(a, _), (b, _)|natural_cmp(a, b));
+
+        for (key, items) in groups {
+            let mut path = path.clone();
+            path.push(key.clone());
+
+            let values = items.iter().map(|item|source[*item].clone()).collect::<Vec<_>>();
+            let aggregates = self.aggregates.borrow().iter()
+                .map(|(name, aggregate)|(name.clone(), aggregate(&values)))
+                .collect();
+            let is_collapsed = collapsed.contains(&path);
+
+            rows.push(GroupEntry::Header(GroupHeader {
+                key: Arc::new(key),
+                path: Arc::new(path.clone()),
+                depth,
+                count: items.len(),
+                aggregates: Arc::new(aggregates),
+                collapsed: is_collapsed,
+            }));
+            if !is_collapsed {
+                self.group(source, items, path, collapsed, rows);
+            }
+        }
+    }
+}
+
+impl GroupHeader {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The aggregates of the group by the key of their line.
+    pub fn aggregates(&self) -> &[(String, String)] {
+        &self.aggregates
+    }
+
+    pub fn aggregate(&self, line_key: &str) -> Option<&str> {
+        self.aggregates.iter().find(|(name, _)|name == line_key).map(|(_, value)|value.as_str())
+    }
+
+    pub fn is_collapsed(&self) -> bool {
+        self.collapsed
+    }
+
+    pub fn set_collapsed(&mut self, collapsed: bool) {
+        self.collapsed = collapsed;
+    }
+
+    pub fn title(&self) -> String {
+        format!("{} ({})", self.key, self.count)
+    }
+}
+
+/// Compares keys by text, with runs of digits compared by their value so that "9" is before "10".
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    fn digits(chars: &mut Peekable<Chars>) -> String {
+        let mut digits = String::new();
+        while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+            digits.push(c);
+            chars.next();
+        }
+        digits
+    }
+
+    let (mut left, mut right) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ordering = match (left.peek().copied(), right.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(l), Some(r)) if l.is_ascii_digit() && r.is_ascii_digit() => {
+                let (l, r) = (digits(&mut left), digits(&mut right));
+                let (l, r) = (l.trim_start_matches('0'), r.trim_start_matches('0'));
+                l.len().cmp(&r.len()).then_with(||l.cmp(r))
+            }
+            (Some(l), Some(r)) => {
+                left.next();
+                right.next();
+                l.cmp(&r)
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl<U: Data> ListIter<GroupRow<U>> for GroupedList<U> {
+    fn for_each(&self, mut cb: impl FnMut(&GroupRow<U>, usize)) {
+        for (index, row) in self.state.rows.borrow().iter().enumerate() {
+            match row {
+                GroupEntry::Header(header) => cb(&GroupRow::Header(header.clone()), index),
+                GroupEntry::Item(item) => if let Some(value) = self.source.get(*item) {
+                    cb(&GroupRow::Item(value.clone()), index)
+                },
+            }
+        }
+    }
+
+    fn for_each_mut(&mut self, mut cb: impl FnMut(&mut GroupRow<U>, usize)) {
+        let mut changed = false;
+        let rows = self.state.rows.borrow().clone();
+
+        for (index, row) in rows.iter().enumerate() {
+            match row {
+                GroupEntry::Header(header) => {
+                    let mut row = GroupRow::Header(header.clone());
+                    cb(&mut row, index);
+                    if let GroupRow::Header(new_header) = row {
+                        if new_header.collapsed != header.collapsed {
+                            let mut collapsed = self.state.collapsed.borrow_mut();
+                            if new_header.collapsed {
+                                collapsed.insert(header.path.to_vec());
+                            } else {
+                                collapsed.remove(header.path.as_slice());
+                            }
+                            changed = true;
+                        }
+                    }
+                }
+                GroupEntry::Item(item) => if let Some(value) = self.source.get(*item) {
+                    let mut row = GroupRow::Item(value.clone());
+                    cb(&mut row, index);
+                    if let GroupRow::Item(new_value) = row {
+                        if !new_value.same(value) {
+                            self.source.set(*item, new_value);
+                        }
+                    }
+                },
+            }
+        }
+
+        if changed {
+            self.state.regroup(&self.source);
+        }
+    }
+
+    fn data_len(&self) -> usize {
+        self.state.rows.borrow().len()
+    }
+}
+
+impl<L: Clone, U> Clone for GroupLens<L, U> {
+    fn clone(&self) -> Self {
+        Self {
+            lens: self.lens.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S, U: Data, L: Lens<S, Vector<U>>> Lens<S, GroupedList<U>> for GroupLens<L, U> {
+    fn with<V, F: FnOnce(&GroupedList<U>) -> V>(&self, data: &S, f: F) -> V {
+        self.lens.with(data, |source|f(&GroupedList {
+            source: source.clone(),
+            state: self.state.clone(),
+        }))
+    }
+
+    fn with_mut<V, F: FnOnce(&mut GroupedList<U>) -> V>(&self, data: &mut S, f: F) -> V {
+        self.lens.with_mut(data, |source|{
+            let mut list = GroupedList {
+                source: source.clone(),
+                state: self.state.clone(),
+            };
+            let value = f(&mut list);
+            if !list.source.same(source) {
+                *source = list.source;
+            }
+            value
+        })
+    }
+}
+
+impl<U: Data, V: Data, L: Lens<U, V>, W: Widget<V> + 'static> GroupCell<U, V, L, W> {
+    pub fn new(lens: L, generate: impl Fn() -> W + 'static) -> Self {
+        Self {
+            lens,
+            aggregate: None,
+            content: GroupCellContent::Header(WidgetPod::new(GroupHeaderCell::new())),
+            generate: Rc::new(generate),
+            phantom: Default::default(),
+        }
+    }
+
+    /// Shows the aggregate of the line with the given key in group headers instead of the title.
+    pub fn with_aggregate(mut self, line_key: impl Into<String>) -> Self {
+        let line_key = Arc::new(line_key.into());
+        self.content = GroupCellContent::Header(WidgetPod::new(GroupHeaderCell::aggregate(line_key.clone())));
+        self.aggregate = Some(line_key);
+        self
+    }
+
+    fn matches(&self, data: &GroupRow<U>) -> bool {
+        match (&self.content, data) {
+            (GroupCellContent::Header(_), GroupRow::Header(_)) => true,
+            (GroupCellContent::Item(_), GroupRow::Item(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn generate_content(&mut self, data: &GroupRow<U>) {
+        self.content = match data {
+            GroupRow::Header(_) => GroupCellContent::Header(WidgetPod::new(match &self.aggregate {
+                Some(line_key) => GroupHeaderCell::aggregate(line_key.clone()),
+                None => GroupHeaderCell::new(),
+            })),
+            GroupRow::Item(_) => GroupCellContent::Item(WidgetPod::new((self.generate)())),
+        };
+    }
+}
+
+impl<U: Data, V: Data, L: Lens<U, V>, W: Widget<V> + 'static> Widget<GroupRow<U>> for GroupCell<U, V, L, W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut GroupRow<U>, env: &Env) {
+        // The rows change during the event when a group is collapsed, the content is replaced in update
+        match (&mut self.content, data) {
+            (GroupCellContent::Header(header), GroupRow::Header(data)) => header.event(ctx, event, data, env),
+            (GroupCellContent::Item(item), GroupRow::Item(data)) => {
+                self.lens.with_mut(data, |data|item.event(ctx, event, data, env))
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &GroupRow<U>, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            if !self.matches(data) {
+                self.generate_content(data);
+            }
+        }
+
+        match (&mut self.content, data) {
+            (GroupCellContent::Header(header), GroupRow::Header(data)) => header.lifecycle(ctx, event, data, env),
+            (GroupCellContent::Item(item), GroupRow::Item(data)) => {
+                self.lens.with(data, |data|item.lifecycle(ctx, event, data, env))
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _: &GroupRow<U>, data: &GroupRow<U>, env: &Env) {
+        if !self.matches(data) {
+            self.generate_content(data);
+            ctx.children_changed();
+            return;
+        }
+
+        match (&mut self.content, data) {
+            (GroupCellContent::Header(header), GroupRow::Header(data)) => header.update(ctx, data, env),
+            (GroupCellContent::Item(item), GroupRow::Item(data)) => {
+                self.lens.with(data, |data|item.update(ctx, data, env))
+            }
+            _ => {}
+        }
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &GroupRow<U>, env: &Env) -> Size {
+        match (&mut self.content, data) {
+            (GroupCellContent::Header(header), GroupRow::Header(data)) => {
+                let size = header.layout(ctx, bc, data, env);
+                header.set_origin(ctx, data, env, Point::ORIGIN);
+                size
+            }
+            (GroupCellContent::Item(item), GroupRow::Item(data)) => self.lens.with(data, |data|{
+                let size = item.layout(ctx, bc, data, env);
+                item.set_origin(ctx, data, env, Point::ORIGIN);
+                size
+            }),
+            _ => bc.min(),
+        }
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &GroupRow<U>, env: &Env) {
+        match (&mut self.content, data) {
+            (GroupCellContent::Header(header), GroupRow::Header(data)) => header.paint(ctx, data, env),
+            (GroupCellContent::Item(item), GroupRow::Item(data)) => {
+                self.lens.with(data, |data|item.paint(ctx, data, env))
+            }
+            _ => {}
+        }
+    }
+}
+
+impl GroupHeaderCell {
+    pub fn new() -> Self {
+        Self {
+            label: WidgetPod::new(Label::dynamic(|header: &GroupHeader, _|header.title())),
+            aggregate: None,
+            indent: 16.0,
+        }
+    }
+
+    /// Shows the aggregate of the line with the given key, without indentation or toggle.
+    pub fn aggregate(line_key: Arc<String>) -> Self {
+        let key = line_key.clone();
+        Self {
+            label: WidgetPod::new(Label::dynamic(move |header: &GroupHeader, _|{
+                header.aggregate(&key).unwrap_or_default().to_string()
+            })),
+            aggregate: Some(line_key),
+            indent: 0.0,
+        }
+    }
+}
+
+impl Widget<GroupHeader> for GroupHeaderCell {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut GroupHeader, env: &Env) {
+        match event {
+            Event::MouseDown(mouse) if mouse.button.is_left() && self.aggregate.is_none() => {
+                ctx.set_active(true);
+                ctx.set_handled();
+            }
+            Event::MouseUp(mouse) if mouse.button.is_left() && ctx.is_active() => {
+                ctx.set_active(false);
+                if ctx.is_hot() {
+                    data.collapsed = !data.collapsed;
+                }
+                ctx.set_handled();
+            }
+            _ => self.label.event(ctx, event, data, env),
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &GroupHeader, env: &Env) {
+        self.label.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &GroupHeader, data: &GroupHeader, env: &Env) {
+        if old_data.collapsed != data.collapsed {
+            ctx.request_paint();
+        }
+        if old_data.depth != data.depth {
+            ctx.request_layout();
+        }
+        self.label.update(ctx, data, env);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &GroupHeader, env: &Env) -> Size {
+        let offset = if self.aggregate.is_some() {0.0} else {(data.depth + 1) as f64 * self.indent};
+        let size = self.label.layout(ctx, &bc.shrink(Size::new(offset, 0.0)), data, env);
+        self.label.set_origin(ctx, data, env, Point::new(offset, 0.0));
+
+        bc.constrain(Size::new(size.width + offset, size.height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &GroupHeader, env: &Env) {
+        if self.aggregate.is_some() {
+            self.label.paint(ctx, data, env);
+            return;
+        }
+        let center = Point::new((data.depth as f64 + 0.5) * self.indent, ctx.size().height / 2.0);
+        let path = disclosure_triangle(center, self.indent / 4.0, !data.collapsed);
+        ctx.fill(path, &env.get(theme::TEXT_COLOR));
+
+        self.label.paint(ctx, data, env);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_order_compares_numbers_by_value() {
+        let mut keys = vec!["10", "9", "a10", "a2", "b", "", "09"];
+        keys.sort_by(|a, b|natural_cmp(a, b));
+        assert_eq!(keys, vec!["", "09", "9", "10", "a2", "a10", "b"]);
+    }
+
+    #[test]
+    fn keys_and_aggregates_can_be_added_after_the_lens() {
+        let policy = GroupPolicy::<i32, druid::lens::Identity>::new(druid::lens::Identity);
+        let lens = policy.lens();
+        let policy = policy
+            .with_key(|value|(value % 2).to_string())
+            .with_aggregate("sum", |values|values.iter().sum::<i32>().to_string());
+
+        policy.state.regroup(&Vector::from(vec![1, 2, 3]));
+        let rows = policy.state.rows.borrow();
+        match &rows[0] {
+            GroupEntry::Header(header) => {
+                assert_eq!(header.title(), "0 (1)");
+                assert_eq!(header.aggregate("sum"), Some("2"));
+            }
+            GroupEntry::Item(_) => panic!("expected a group header"),
+        }
+        assert!(Rc::ptr_eq(&lens.state, &policy.state));
+    }
+
+    #[test]
+    fn titles_start_at_the_first_visible_line_without_aggregate() {
+        let mut policy = GroupPolicy::<i32, druid::lens::Identity>::new(druid::lens::Identity)
+            .with_key(|value|(value % 2).to_string())
+            .with_aggregate("sum", |values|values.iter().sum::<i32>().to_string());
+        policy.state.regroup(&Vector::from(vec![1, 2, 3]));
+
+        let mut layout = TableLayout::new(druid::widget::Axis::Vertical);
+        layout.lines_mut().set_length(4, crate::AxisPart::new(None));
+        layout.lines_mut().set_key(2, "sum");
+        layout.elements_mut().set_length(5, crate::AxisPart::new(None));
+
+        policy.place_titles(4, &mut layout);
+        // Rows: header 0, item 2, header 1, item 1, item 3
+        assert_eq!(layout.spans(), &[CellSpan::new(0, 0, 2, 1), CellSpan::new(0, 2, 2, 1)]);
+
+        // The spans moved along with the line are replaced
+        layout.move_line(2, 0);
+        policy.place_titles(4, &mut layout);
+        assert_eq!(layout.spans(), &[CellSpan::new(1, 0, 3, 1), CellSpan::new(1, 2, 3, 1)]);
+
+        let mut hidden = layout.lines().get(1);
+        hidden.set_hidden(true);
+        layout.lines_mut().set(1, hidden);
+        policy.place_titles(4, &mut layout);
+        assert_eq!(layout.spans(), &[CellSpan::new(2, 0, 2, 1), CellSpan::new(2, 2, 2, 1)]);
+    }
+}
@@ -1,10 +1,26 @@
-use std::cell::{RefCell, RefMut};
+use std::any::Any;
+use std::cell::{Ref, RefCell, RefMut};
 use std::ops::Deref;
 use std::rc::Rc;
-use druid::{BoxConstraints, Env, Event, EventCtx, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect, Size, UpdateCtx, Widget, WidgetPod, Data};
+use druid::{BoxConstraints, Env, Event, EventCtx, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect, Size, UpdateCtx, Vec2, Widget, WidgetPod, Data, RenderContext, Selector, Target};
+use druid::kurbo::{Circle, Line};
+use druid::theme;
+use druid::im::Vector;
+use druid::lens::Identity;
 use druid::widget::{Axis, ClipBox, ListIter, Scroll};
 use crate::{AxisPart, Static, Table, TableAxis, TableLayout, TableLine, TablePolicy, WidgetTableLine};
-use crate::util::set_len;
+use crate::util::{move_item, order_moves, set_len};
+use crate::commands::{DROP_ELEMENT, ELEMENT_MOVED, ELEMENT_TAKEN, LINE_ORDER_CHANGED, MOVE_ELEMENT, MOVE_LINE, SET_LINE_ORDER, SET_LINE_VISIBLE, TRANSPOSE};
+use crate::reorder::{ElementDrag, ListEdit};
+use crate::chooser::LineChooser;
+use crate::search::{ReplaceResult, SearchAction, SearchBar, SearchPattern};
+use crate::chart::{CellChart, ChartTableLine};
+use crate::format::{CellFormat, FormattedLine};
+use crate::edit::EditTableLine;
+use crate::state::{ScrollAnchor, TableViewState};
+use crate::theme::{header_background, is_visible};
+use crate::tree::{TreeNode, TreeTableLine};
+use crate::group::{GroupCell, GroupLens};
 
 pub struct HeaderTable<T: Data, P: TablePolicy<T>> {
     table: WidgetPod<T, Scroll<T, Table<T, P>>>,
@@ -15,19 +31,37 @@ pub struct HeaderTable<T: Data, P: TablePolicy<T>> {
     element_header_width: f64,
 
     last_view_origin: Point,
+    pending_cell_offset: Option<(Vec2, u64, u64)>,
+    chooser: Option<LineChooser>,
+    search_bar: Option<SearchBar>,
 }
 
-type HeaderBuilder<T> = Box<dyn FnMut(&T, &T, usize, &mut Vec<HeaderWidget<T>>)>;
+pub type HeaderBuilder<T> = Box<dyn FnMut(&T, &T, usize, &mut Vec<HeaderWidget<T>>)>;
 
 pub type HeaderWidget<T> = WidgetPod<HeaderData<T>, Box<dyn Widget<HeaderData<T>>>>;
 
 struct Header<T> {
     widgets: Vec<HeaderWidget<T>>,
     builder: HeaderBuilder<T>,
+    /// Creates the widget of an inserted part, only element headers have one.
+    generate: Option<Box<dyn Fn() -> HeaderWidget<T>>>,
+    children_changed: bool,
     layout: Rc<RefCell<TableLayout>>,
     table_axis: TableAxis,
+    reorderable: bool,
+    drag: Option<HeaderDrag>,
 }
 
+struct HeaderDrag {
+    index: usize,
+    start: f64,
+    dragging: bool,
+    gap: Option<usize>,
+}
+
+/// An element header was released outside of its header, with the position in window coordinates.
+const RELEASE_ELEMENT: Selector<(usize, Point)> = Selector::new("druid-table.release-element");
+
 #[derive(Copy, Clone, Data)]
 pub struct HeaderData<T> {
     pub(crate) data: T,
@@ -42,7 +76,7 @@ impl<T: Data, P: TablePolicy<T>> HeaderTable<T, P> {
         Self {
             table: WidgetPod::new(Scroll::new(Table::new(policy, layout.clone()))),
             line_header: WidgetPod::new(
-                ClipBox::new(Header::new(line_headers, layout, TableAxis::LineAxis))
+                ClipBox::new(Header::new(line_headers, layout, TableAxis::LineAxis).with_reordering(true))
                     .constrain_vertical(true)
                     .constrain_horizontal(true)
             ),
@@ -50,17 +84,23 @@ impl<T: Data, P: TablePolicy<T>> HeaderTable<T, P> {
             element_header: None,
             element_header_width: 0.0,
             last_view_origin: Point::ORIGIN,
+            pending_cell_offset: None,
+            chooser: None,
+            search_bar: None,
         }
     }
 
     pub fn with_element_header(mut self, builder: impl Fn() -> Box<dyn Widget<HeaderData<T>>> + 'static, element_header_width: f64) -> Self {
+        let builder = Rc::new(builder);
+        let generate = builder.clone();
         self.element_header = Some(WidgetPod::new(ClipBox::new(Header::new(
             Box::new(move|_, _, length, list| {
                 set_len(list, length, ||WidgetPod::new(builder()));
             }),
             self.table.widget().child().layout.clone(),
             TableAxis::ElementAxis
-        ))
+        ).with_reordering(self.table.widget().child().has_element_source())
+            .with_generator(move||WidgetPod::new(generate())))
             .constrain_horizontal(true)
             .constrain_vertical(true)
         ));
@@ -80,16 +120,6 @@ impl<T: Data, P: TablePolicy<T>> HeaderTable<T, P> {
         }
     }
 
-    fn table_layout(&self) -> RefMut<TableLayout> {
-        self.table.widget().child().layout_mut()
-    }
-}
-
-impl<T: Data> HeaderTable<T, Static> {
-    pub fn new_static(axis: Axis, line_header_width: f64) -> Self {
-        Self::new_dynamic(axis, Static, Box::new(|_, _, _, _|()), line_header_width)
-    }
-
     pub fn with_custom_line<L: TableLine<T> + 'static>(mut self, line: L, header: impl Widget<HeaderData<T>> + 'static) -> Self {
         self.table.widget_mut().child_mut().add_line(line);
         self.line_header.widget_mut().child_mut().widgets.push(WidgetPod::new(Box::new(header)));
@@ -109,18 +139,541 @@ impl<T: Data> HeaderTable<T, Static> {
     >(self, outer_lens: L1, inner_lens: L2, widget: F, header: impl Widget<HeaderData<T>> + 'static) -> Self {
         self.with_custom_line(WidgetTableLine::new(outer_lens, inner_lens, widget), header)
     }
+
+    /// Adds a line with the given size constraints and padding.
+    pub fn with_line_part<
+        T2: ListIter<U> + Data,
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, T2> + 'static,
+        L2: Lens<U, V> + 'static,
+        W: Widget<V> + 'static,
+        F: Fn() -> W + 'static,
+    >(mut self, outer_lens: L1, inner_lens: L2, widget: F, part: AxisPart, header: impl Widget<HeaderData<T>> + 'static) -> Self {
+        self.table.widget_mut().child_mut().add_line_part(WidgetTableLine::new(outer_lens, inner_lens, widget), part);
+        self.line_header.widget_mut().child_mut().widgets.push(WidgetPod::new(Box::new(header)));
+        self
+    }
+
+    pub fn with_chart_line<
+        T2: ListIter<U> + Data,
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, T2> + 'static,
+        L2: Lens<U, V> + 'static,
+        C: CellChart<V> + 'static,
+    >(self, outer_lens: L1, inner_lens: L2, chart: C, header: impl Widget<HeaderData<T>> + 'static) -> Self {
+        self.with_custom_line(ChartTableLine::new(outer_lens, inner_lens, chart), header)
+    }
+
+    pub fn with_formatted_line<
+        T2: ListIter<U> + Data,
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, T2> + 'static,
+        L2: Lens<U, V> + 'static,
+    >(self, outer_lens: L1, inner_lens: L2, format: impl CellFormat<V> + 'static, header: impl Widget<HeaderData<T>> + 'static) -> Self {
+        self.with_custom_line(FormattedLine::new(outer_lens, inner_lens, format), header)
+    }
+
+    pub fn with_edit_line<
+        T2: ListIter<U> + Data,
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, T2> + 'static,
+        L2: Lens<U, V> + 'static,
+        D: Widget<V> + 'static,
+        E: Widget<V> + 'static,
+    >(self, outer_lens: L1, inner_lens: L2, display: impl Fn() -> D + 'static, editor: impl Fn() -> E + 'static, header: impl Widget<HeaderData<T>> + 'static) -> Self {
+        self.with_custom_line(EditTableLine::new(outer_lens, inner_lens, display, editor), header)
+    }
+
+    pub fn with_tree_line<
+        N: TreeNode + 'static,
+        V: Data,
+
+        L1: Lens<T, Vector<N>> + 'static,
+        L2: Lens<N, V> + Clone + 'static,
+        W: Widget<V> + 'static,
+        F: Fn() -> W + 'static,
+    >(self, lens: L1, inner_lens: L2, widget: F, header: impl Widget<HeaderData<T>> + 'static) -> Self {
+        self.with_custom_line(TreeTableLine::new(lens, inner_lens, widget), header)
+    }
+
+    pub fn with_grouped_line<
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, Vector<U>> + 'static,
+        L2: Lens<U, V> + Clone + 'static,
+        W: Widget<V> + 'static,
+        F: Fn() -> W + 'static,
+    >(self, lens: GroupLens<L1, U>, inner_lens: L2, widget: F, header: impl Widget<HeaderData<T>> + 'static) -> Self {
+        let widget = Rc::new(widget);
+        self.with_custom_line(WidgetTableLine::new(lens, Identity, move||{
+            let widget = widget.clone();
+            GroupCell::new(inner_lens.clone(), move||widget())
+        }), header)
+    }
+
+    /// Adds a grouped line which shows the aggregate of the `GroupPolicy` with the same key in the
+    /// group headers, the key also becomes the key of the line.
+    pub fn with_aggregated_line<
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, Vector<U>> + 'static,
+        L2: Lens<U, V> + Clone + 'static,
+        W: Widget<V> + 'static,
+        F: Fn() -> W + 'static,
+    >(self, lens: GroupLens<L1, U>, inner_lens: L2, widget: F, key: impl Into<String>, header: impl Widget<HeaderData<T>> + 'static) -> Self {
+        let key = key.into();
+        let line_key = key.clone();
+        let widget = Rc::new(widget);
+        self.with_custom_line(WidgetTableLine::new(lens, Identity, move||{
+            let widget = widget.clone();
+            GroupCell::new(inner_lens.clone(), move||widget()).with_aggregate(line_key.clone())
+        }), header).with_line_key(key)
+    }
+
+    /// Enables or disables reordering of the lines by dragging their headers.
+    pub fn with_line_reordering(mut self, reorderable: bool) -> Self {
+        self.line_header.widget_mut().child_mut().reorderable = reorderable;
+        self
+    }
+
+    /// Restores an order of the lines reported by `LINE_ORDER_CHANGED`.
+    pub fn with_line_order(mut self, order: &[usize]) -> Self {
+        self.set_line_order(order);
+        self
+    }
+
+    pub fn line_order(&self) -> &[usize] {
+        self.table.widget().child().line_order()
+    }
+
+    pub fn set_line_order(&mut self, order: &[usize]) {
+        for (from, to) in order_moves(self.line_order(), order) {
+            self.move_line(from, to);
+        }
+    }
+
+    pub fn move_line(&mut self, from: usize, to: usize) {
+        self.table.widget_mut().child_mut().move_line(from, to);
+        move_item(&mut self.line_header.widget_mut().child_mut().widgets, from, to);
+    }
+
+    /// Sets the list of elements, this enables dragging elements by their header to move them
+    /// inside the data or to another table with the same element type.
+    pub fn with_element_source<
+        T2: ListEdit<U> + 'static,
+        U: Data,
+
+        L: Lens<T, T2> + 'static,
+    >(mut self, lens: L) -> Self {
+        self.table.widget_mut().child_mut().set_element_source(lens);
+        if let Some(element_header) = &mut self.element_header {
+            element_header.widget_mut().child_mut().reorderable = true;
+        }
+        self
+    }
+
+    pub fn move_element(&mut self, data: &mut T, from: usize, to: usize) -> bool {
+        let moved = self.table.widget_mut().child_mut().move_element(data, from, to);
+        if moved {
+            self.move_element_header(from, to);
+        }
+        moved
+    }
+
+    fn move_element_header(&mut self, from: usize, to: usize) {
+        if let Some(element_header) = &mut self.element_header {
+            move_item(&mut element_header.widget_mut().child_mut().widgets, from, to);
+        }
+    }
+
+    fn insert_element(&mut self, data: &mut T, index: usize, value: &dyn Any) -> bool {
+        let index = index.min(self.table_layout().elements().length());
+        let inserted = self.table.widget_mut().child_mut().insert_element(data, index, value);
+        if let (true, Some(element_header)) = (inserted, &mut self.element_header) {
+            element_header.widget_mut().child_mut().insert_widget(index);
+        }
+        inserted
+    }
+
+    fn remove_element(&mut self, data: &mut T, index: usize) {
+        if index >= self.table_layout().elements().length() {
+            return;
+        }
+        self.table.widget_mut().child_mut().remove_element(data, index);
+        if let Some(element_header) = &mut self.element_header {
+            element_header.widget_mut().child_mut().remove_widget(index);
+        }
+    }
+
+    /// Returns the gap between two elements at the given window position,
+    /// `None` if the position is outside of this table.
+    fn element_gap(&self, ctx: &EventCtx, window_pos: Point) -> Option<usize> {
+        let pos = window_pos - ctx.window_origin().to_vec2();
+        if !ctx.size().to_rect().contains(pos) {
+            return None;
+        }
+        let content_pos = pos - self.table.layout_rect().origin().to_vec2() + self.table.widget().offset();
+
+        let layout = self.table_layout();
+        let axis = layout.header_direction(TableAxis::ElementAxis);
+        Some(layout.elements().gap_at(axis.major_pos(content_pos)))
+    }
+
+    fn release_element(&mut self, ctx: &mut EventCtx, data: &mut T, index: usize, window_pos: Point) {
+        match self.element_gap(ctx, window_pos) {
+            Some(gap) => {
+                let target = if gap > index {gap - 1} else {gap};
+                if target != index && self.move_element(data, index, target) {
+                    ctx.request_layout();
+                }
+            }
+            None => {
+                if let Some(value) = self.table.widget().child().element(data, index) {
+                    ctx.submit_command(DROP_ELEMENT.with(ElementDrag {
+                        source: ctx.widget_id(),
+                        index,
+                        value,
+                        window_pos,
+                    }));
+                }
+            }
+        }
+    }
+
+    fn drop_element(&mut self, ctx: &mut EventCtx, data: &mut T, drag: &ElementDrag) -> bool {
+        if drag.source == ctx.widget_id() {
+            return false;
+        }
+        match self.element_gap(ctx, drag.window_pos) {
+            Some(gap) if self.insert_element(data, gap, drag.value.as_ref()) => {
+                ctx.submit_command(ELEMENT_TAKEN.with(drag.index).to(Target::Widget(drag.source)));
+                ctx.request_layout();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds a popup to hide and show lines, opened by clicking the corner between the headers
+    /// or by a right click on a header.
+    /// `names` returns the name of a line by the index in which it was added.
+    pub fn with_line_chooser(mut self, names: impl Fn(usize) -> String + 'static) -> Self {
+        self.chooser = Some(LineChooser::new(names));
+        self
+    }
+
+    /// Adds a text input below the table which searches the cells while typing.
+    /// Ctrl+F focuses it, Enter and F3 move to the next match, Escape ends the search.
+    pub fn with_search_bar(mut self) -> Self {
+        self.search_bar = Some(SearchBar::new());
+        self
+    }
+
+    /// See `Table::search`, the table scrolls to the first match.
+    pub fn search(&mut self, data: &T, pattern: SearchPattern) -> usize {
+        let count = self.table.widget_mut().child_mut().search(data, pattern);
+        self.scroll_to_match();
+        count
+    }
+
+    pub fn next_match(&mut self) -> Option<(usize, usize)> {
+        let found = self.table.widget_mut().child_mut().next_match();
+        self.scroll_to_match();
+        found
+    }
+
+    pub fn previous_match(&mut self) -> Option<(usize, usize)> {
+        let found = self.table.widget_mut().child_mut().previous_match();
+        self.scroll_to_match();
+        found
+    }
+
+    /// See `Table::replace_current`, the table scrolls to the next match.
+    pub fn replace_current(&mut self, data: &mut T, replacement: &str) -> ReplaceResult {
+        let result = self.table.widget_mut().child_mut().replace_current(data, replacement);
+        self.scroll_to_match();
+        result
+    }
+
+    fn scroll_to_match(&mut self) {
+        if let Some((line, element)) = self.table().current_match() {
+            let rect = self.table_layout().layout_rect(line, element);
+            self.table.widget_mut().scroll_to(rect);
+        }
+    }
+
+    fn search_bar_event(&mut self, ctx: &mut EventCtx, event: &Event, data: &T, env: &Env) {
+        let action = match &mut self.search_bar {
+            Some(search_bar) => search_bar.event(ctx, event, env),
+            None => return,
+        };
+        let table = self.table.widget_mut().child_mut();
+        match action {
+            Some(SearchAction::Search(pattern)) => {
+                table.search(data, pattern);
+            }
+            Some(SearchAction::Next) => {
+                table.next_match();
+            }
+            Some(SearchAction::Previous) => {
+                table.previous_match();
+            }
+            Some(SearchAction::Clear) => table.clear_search(),
+            None => return,
+        }
+        ctx.request_paint();
+    }
+
+    /// Sets the key of the last added line, see `apply_layout`.
+    pub fn with_line_key(mut self, key: impl Into<String>) -> Self {
+        let table = self.table.widget_mut().child_mut();
+        let index = table.lines.len().saturating_sub(1);
+        table.layout_mut().lines_mut().set_key(index, key);
+        self
+    }
+
+    /// Applies a stored layout, lines are matched by their key and reordered like in `layout`.
+    pub fn apply_layout(&mut self, layout: &TableLayout) {
+        // Keeps the scroll position like `TRANSPOSE`
+        let transposed = self.table_layout().line_axis() != layout.line_axis();
+        if transposed {
+            self.transpose();
+        }
+        let order = self.table.widget().child().keyed_line_order(layout);
+        self.set_line_order(&order);
+        self.table_layout().apply(layout);
+    }
+
+    /// Returns the view state of the table including the scroll position.
+    pub fn view_state(&self) -> TableViewState {
+        let (offset, line, element) = match self.pending_cell_offset {
+            Some(cell_offset) => cell_offset,
+            None => self.table_layout().as_cell_offset(self.table.widget().offset()),
+        };
+
+        let mut state = self.table.widget().child().view_state();
+        state.scroll = Some(ScrollAnchor {
+            offset: (offset.x, offset.y),
+            line,
+            element,
+        });
+        state
+    }
+
+    pub fn apply_view_state(&mut self, state: &TableViewState) {
+        self.set_line_order(&state.line_order);
+        self.table.widget_mut().child_mut().apply_view_state(state);
+        if let Some(scroll) = &state.scroll {
+            self.pending_cell_offset = Some((Vec2::new(scroll.offset.0, scroll.offset.1), scroll.line, scroll.element));
+        }
+    }
+
+    pub fn with_view_state(mut self, state: &TableViewState) -> Self {
+        self.apply_view_state(state);
+        self
+    }
+
+    fn header_space(&self) -> Size {
+        let table_axis = self.table_layout().line_axis();
+        Size::from(table_axis.pack(self.line_header_width, self.element_header_width))
+    }
+
+    fn chooser_event(&mut self, ctx: &mut EventCtx, event: &Event) {
+        let mouse = match event {
+            Event::MouseDown(mouse) => mouse,
+            _ => return,
+        };
+        let corner = self.header_space().to_rect();
+        let on_header = !self.table.layout_rect().contains(mouse.pos);
+        let lines = self.table_layout().lines().length();
+        let chooser = match &mut self.chooser {
+            Some(chooser) => chooser,
+            None => return,
+        };
+
+        let toggled = if chooser.is_open() {
+            let line = chooser.line_at(lines, mouse.pos);
+            if line.is_none() {
+                chooser.close();
+            }
+            line
+        } else if corner.contains(mouse.pos) && mouse.button.is_left() {
+            chooser.open(corner.origin() + Vec2::new(0.0, corner.height()));
+            None
+        } else if on_header && mouse.button.is_right() {
+            chooser.open(mouse.pos);
+            None
+        } else {
+            return;
+        };
+
+        if let Some(line) = toggled {
+            let table = self.table_mut();
+            let visible = table.is_line_visible(line);
+            table.set_line_visible(line, !visible);
+            ctx.request_layout();
+        }
+        ctx.request_paint();
+        ctx.set_handled();
+    }
+
+    fn line_order_changed(&self, ctx: &mut EventCtx) {
+        // Notifications submitted while handling a notification are dropped,
+        // therefore the change is reported by a command to ourself.
+        let order = self.line_order().to_vec();
+        ctx.submit_command(LINE_ORDER_CHANGED.with(order).to(Target::Widget(ctx.widget_id())));
+        ctx.request_layout();
+    }
+
+    fn transpose(&mut self) {
+        let offset = self.table.widget().offset();
+        let (cell_offset, line, element) = {
+            let mut layout = self.table_layout();
+            let cell_offset = layout.as_cell_offset(offset);
+            layout.transpose();
+            cell_offset
+        };
+
+        // The offset inside the cell is packed by the line axis, which just changed
+        self.pending_cell_offset = Some((Vec2::new(cell_offset.y, cell_offset.x), line, element));
+    }
+
+    /// The table inside the headers, e.g. for its edit history, search matches or invalid cells.
+    pub fn table(&self) -> &Table<T, P> {
+        self.table.widget().child()
+    }
+
+    /// Changes which would move lines or elements have to go through the `HeaderTable`,
+    /// otherwise the headers do not follow.
+    pub fn table_mut(&mut self) -> &mut Table<T, P> {
+        self.table.widget_mut().child_mut()
+    }
+
+    /// Configures the table inside the headers, e.g. `.with_table(|table|table.enable_history())`.
+    pub fn with_table(mut self, configure: impl FnOnce(&mut Table<T, P>)) -> Self {
+        configure(self.table_mut());
+        self
+    }
+
+    pub fn layout(&self) -> Ref<TableLayout> {
+        self.table.widget().child().layout()
+    }
+
+    fn table_layout(&self) -> RefMut<TableLayout> {
+        self.table.widget().child().layout_mut()
+    }
+}
+
+impl<T: Data> HeaderTable<T, Static> {
+    pub fn new_static(axis: Axis, line_header_width: f64) -> Self {
+        Self::new_dynamic(axis, Static, Box::new(|_, _, _, _|()), line_header_width)
+    }
 }
 
 impl<T: Data, P: TablePolicy<T>> Widget<T> for HeaderTable<T, P> {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
-        self.table.event(ctx, event, data, env);
-        self.line_header.event(ctx, event, data, env);
-        if let Some(element_header) = &mut self.element_header {
-            element_header.event(ctx, event, data, env);
+        if let Event::Command(command) = event {
+            if command.is(TRANSPOSE) {
+                self.transpose();
+                ctx.request_layout();
+                ctx.set_handled();
+                return;
+            }
+            if let Some((from, to)) = command.get(MOVE_LINE) {
+                self.move_line(*from, *to);
+                self.line_order_changed(ctx);
+                ctx.set_handled();
+                return;
+            }
+            if let Some(order) = command.get(SET_LINE_ORDER) {
+                self.set_line_order(order);
+                self.line_order_changed(ctx);
+                ctx.set_handled();
+                return;
+            }
+            if let Some((from, to)) = command.get(MOVE_ELEMENT) {
+                if self.move_element(data, *from, *to) {
+                    ctx.request_layout();
+                }
+                ctx.set_handled();
+                return;
+            }
+            if let Some(drag) = command.get(DROP_ELEMENT) {
+                if self.drop_element(ctx, data, drag) {
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            if let Some(index) = command.get(ELEMENT_TAKEN) {
+                self.remove_element(data, *index);
+                ctx.request_layout();
+                ctx.set_handled();
+                return;
+            }
+            if let Some((index, visible)) = command.get(SET_LINE_VISIBLE) {
+                self.table_mut().set_line_visible(*index, *visible);
+                ctx.request_layout();
+                ctx.set_handled();
+                return;
+            }
+            if let Some(order) = command.get(LINE_ORDER_CHANGED) {
+                ctx.submit_notification(LINE_ORDER_CHANGED.with(order.clone()));
+                ctx.set_handled();
+                return;
+            }
+        }
+        if let Event::Notification(notification) = event {
+            if let Some((from, to)) = notification.get(MOVE_LINE) {
+                self.move_line(*from, *to);
+                self.line_order_changed(ctx);
+                ctx.set_handled();
+            } else if let Some((from, to)) = notification.get(MOVE_ELEMENT) {
+                if self.move_element(data, *from, *to) {
+                    ctx.request_layout();
+                }
+                ctx.set_handled();
+            } else if let Some((from, to)) = notification.get(ELEMENT_MOVED) {
+                self.move_element_header(*from, *to);
+                ctx.set_handled();
+            } else if let Some((index, window_pos)) = notification.get(RELEASE_ELEMENT) {
+                self.release_element(ctx, data, *index, *window_pos);
+                ctx.set_handled();
+            }
+            return;
+        }
+
+        let current_match = self.table().current_match();
+        self.search_bar_event(ctx, event, data, env);
+        if !ctx.is_handled() {
+            self.chooser_event(ctx, event);
+            if ctx.is_handled() {
+                return;
+            }
+
+            self.table.event(ctx, event, data, env);
+            self.line_header.event(ctx, event, data, env);
+            if let Some(element_header) = &mut self.element_header {
+                element_header.event(ctx, event, data, env);
+            }
         }
 
         //TODO: handle SCROLL_TO_VIEW from headers
 
+        // F3 and FIND_NEXT are handled by the table, which does not know the scroll position
+        if self.table().current_match() != current_match {
+            self.scroll_to_match();
+        }
+
         //Sync headers with content
         let new_view_origin = self.table.widget().offset().to_point();
         if new_view_origin != self.last_view_origin {
@@ -136,6 +689,9 @@ impl<T: Data, P: TablePolicy<T>> Widget<T> for HeaderTable<T, P> {
         if let Some(element_header) = &mut self.element_header {
             element_header.lifecycle(ctx, event, data, env);
         }
+        if let Some(search_bar) = &mut self.search_bar {
+            search_bar.lifecycle(ctx, event, env);
+        }
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, _: &T, data: &T, env: &Env) {
@@ -144,6 +700,9 @@ impl<T: Data, P: TablePolicy<T>> Widget<T> for HeaderTable<T, P> {
         if let Some(element_header) = &mut self.element_header {
             element_header.update(ctx, data, env);
         }
+        if let Some(search_bar) = &mut self.search_bar {
+            search_bar.update(ctx, env);
+        }
     }
 
     fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
@@ -153,12 +712,20 @@ impl<T: Data, P: TablePolicy<T>> Widget<T> for HeaderTable<T, P> {
         // for now we use a fixed size for the headers
 
         let table_axis = self.table_layout().line_axis();
-        let header_space = Size::from(table_axis.pack(self.line_header_width, self.element_header_width));
-        let table_bc = bc.shrink(header_space);
+        let header_space = self.header_space();
+        let search_bar_height = match &mut self.search_bar {
+            Some(search_bar) => search_bar.layout(ctx, bc.max().width, env),
+            None => 0.0,
+        };
+        let table_bc = bc.shrink(header_space + Size::new(0.0, search_bar_height));
 
         //Layout Table
         let offset = self.table.widget().offset();
-        let cell_offset = self.table_layout().as_cell_offset(offset);
+        let transposed = self.pending_cell_offset.is_some();
+        let cell_offset = match self.pending_cell_offset.take() {
+            Some(cell_offset) => cell_offset,
+            None => self.table_layout().as_cell_offset(offset),
+        };
 
         let table_size = self.table.layout(ctx, &table_bc, data, env);
         self.table.set_origin(ctx, data, env, header_space.to_vec2().to_point());
@@ -167,7 +734,7 @@ impl<T: Data, P: TablePolicy<T>> Widget<T> for HeaderTable<T, P> {
         // This is important for virtual scrolling.
         //TODO: allow sticky borders
         let view_origin = self.table_layout().from_cell_offset(cell_offset).to_point();
-        if view_origin != self.last_view_origin {
+        if view_origin != self.last_view_origin || transposed {
             self.last_view_origin = view_origin;
 
             // This is a hack until we can use ScrollComponent directly
@@ -184,7 +751,12 @@ impl<T: Data, P: TablePolicy<T>> Widget<T> for HeaderTable<T, P> {
             element_header.set_origin(ctx, data, env, Point::from(table_axis.pack(self.line_header_width, 0.0)));
         }
 
-        table_size + header_space
+        let size = table_size + header_space;
+        if let Some(search_bar) = &mut self.search_bar {
+            search_bar.set_rect(ctx, Rect::new(0.0, size.height, size.width, size.height + search_bar_height), env);
+        }
+
+        size + Size::new(0.0, search_bar_height)
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
@@ -193,6 +765,28 @@ impl<T: Data, P: TablePolicy<T>> Widget<T> for HeaderTable<T, P> {
         if let Some(element_header) = &mut self.element_header {
             element_header.paint(ctx, data, env);
         }
+
+        let corner = self.header_space().to_rect();
+        let background = header_background(env);
+        if is_visible(&background) {
+            ctx.fill(corner, &background);
+        }
+
+        if let Some(chooser) = &self.chooser {
+            if corner.area() > 0.0 {
+                let color = env.get(theme::TEXT_COLOR);
+                for offset in &[-4.0, 0.0, 4.0] {
+                    ctx.fill(Circle::new(corner.center() + Vec2::new(0.0, *offset), 1.5), &color);
+                }
+            }
+
+            let layout = self.table_layout();
+            chooser.paint(ctx, env, &layout, self.table.widget().child().line_order());
+        }
+
+        if let Some(search_bar) = &mut self.search_bar {
+            search_bar.paint(ctx, env, self.table.widget().child().search.as_ref());
+        }
     }
 }
 
@@ -201,8 +795,87 @@ impl<T: Data> Header<T> {
         Self {
             widgets: vec![],
             builder,
+            generate: None,
+            children_changed: false,
             layout,
-            table_axis
+            table_axis,
+            reorderable: false,
+            drag: None,
+        }
+    }
+
+    fn with_reordering(mut self, reorderable: bool) -> Self {
+        self.reorderable = reorderable;
+        self
+    }
+
+    fn with_generator(mut self, generate: impl Fn() -> HeaderWidget<T> + 'static) -> Self {
+        self.generate = Some(Box::new(generate));
+        self
+    }
+
+    fn insert_widget(&mut self, index: usize) {
+        if let Some(generate) = self.generate.as_ref().filter(|_|index <= self.widgets.len()) {
+            self.widgets.insert(index, generate());
+            self.children_changed = true;
+        }
+    }
+
+    fn remove_widget(&mut self, index: usize) {
+        if index < self.widgets.len() {
+            self.widgets.remove(index);
+            self.children_changed = true;
+        }
+    }
+
+    fn drag_event(&mut self, ctx: &mut EventCtx, event: &Event) {
+        let table_layout = self.layout.deref().borrow();
+        let axis = table_layout.header_direction(self.table_axis);
+        let layout = table_layout.table_axis(self.table_axis);
+        let bounds = ctx.size().to_rect();
+
+        match event {
+            Event::MouseDown(mouse) if mouse.button.is_left() && !ctx.is_handled() => {
+                let start = axis.major_pos(mouse.pos);
+                if let Some(index) = layout.index_at(start) {
+                    self.drag = Some(HeaderDrag {index, start, dragging: false, gap: None});
+                    ctx.set_active(true);
+                }
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                if let Some(drag) = &mut self.drag {
+                    // Small movements are still clicks
+                    let pos = axis.major_pos(mouse.pos);
+                    drag.dragging |= (pos - drag.start).abs() > 3.0;
+                    if drag.dragging {
+                        drag.gap = Some(layout.gap_at(pos)).filter(|_|bounds.contains(mouse.pos));
+                        ctx.request_paint();
+                    }
+                }
+            }
+            Event::MouseUp(mouse) if ctx.is_active() => {
+                ctx.set_active(false);
+                if let Some(HeaderDrag {index, dragging: true, gap, ..}) = self.drag.take() {
+                    match (gap, self.table_axis) {
+                        (Some(gap), table_axis) => {
+                            let target = if gap > index {gap - 1} else {gap};
+                            if target != index {
+                                let selector = match table_axis {
+                                    TableAxis::LineAxis => MOVE_LINE,
+                                    TableAxis::ElementAxis => MOVE_ELEMENT,
+                                };
+                                ctx.submit_notification(selector.with((index, target)));
+                            }
+                        }
+                        (None, TableAxis::ElementAxis) => {
+                            ctx.submit_notification(RELEASE_ELEMENT.with((index, mouse.window_pos)));
+                        }
+                        (None, TableAxis::LineAxis) => {}
+                    }
+                    ctx.request_paint();
+                }
+            }
+            _ => {}
         }
     }
 
@@ -223,24 +896,33 @@ impl<T: Data> Header<T> {
 
 impl<T: Data> Widget<T> for Header<T> {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
-        let mut table_layout = self.layout.deref().borrow_mut();
-        let layout = table_layout.table_axis_mut(self.table_axis);
-
-        for (index, widget) in self.widgets.iter_mut().enumerate() {
-            let part = layout.get(index);
-            let mut header_data = HeaderData {
-                data: data.to_owned(),
-                index,
-                part,
-            };
-            widget.event(ctx, event, &mut header_data, env);
-            *data = header_data.data;
-            if !part.same(&header_data.part) {
-                layout.set(index, part);
-                ctx.request_layout();
-                //TODO: notify the table
+        {
+            let mut table_layout = self.layout.deref().borrow_mut();
+            let layout = table_layout.table_axis_mut(self.table_axis);
+
+            for (index, widget) in self.widgets.iter_mut().enumerate() {
+                let part = layout.get(index);
+                if part.is_hidden() {
+                    continue;
+                }
+                let mut header_data = HeaderData {
+                    data: data.to_owned(),
+                    index,
+                    part,
+                };
+                widget.event(ctx, event, &mut header_data, env);
+                *data = header_data.data;
+                if !part.same(&header_data.part) {
+                    layout.set(index, header_data.part);
+                    ctx.request_layout();
+                    //TODO: notify the table
+                }
             }
         }
+
+        if self.reorderable {
+            self.drag_event(ctx, event);
+        }
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
@@ -256,6 +938,9 @@ impl<T: Data> Widget<T> for Header<T> {
         let length = self.layout.deref().borrow().table_axis(self.table_axis).length();
         if length != self.widgets.len() {
             (self.builder)(old_data, data, length, &mut self.widgets);
+            self.children_changed = true;
+        }
+        if std::mem::take(&mut self.children_changed) {
             ctx.children_changed();
         }
     }
@@ -268,6 +953,9 @@ impl<T: Data> Widget<T> for Header<T> {
         let mut advance = self.layout.deref().borrow().table_axis(self.table_axis).start_padding();
 
         self.for_each(data, |data, widget|{
+            if data.part.is_hidden() {
+                return;
+            }
             let inner_bc = BoxConstraints::new(
                 Size::from(axis.pack(data.part.size(), 0.0)),
                 Size::from(axis.pack(data.part.size(), max_cross)),
@@ -283,7 +971,27 @@ impl<T: Data> Widget<T> for Header<T> {
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
-        self.for_each(data, |data, widget|widget.paint(ctx, data, env));
+        let background = header_background(env);
+        if is_visible(&background) {
+            let rect = ctx.size().to_rect();
+            ctx.fill(rect, &background);
+        }
+
+        self.for_each(data, |data, widget|{
+            if !data.part.is_hidden() {
+                widget.paint(ctx, data, env);
+            }
+        });
+
+        if let Some(HeaderDrag {gap: Some(gap), ..}) = &self.drag {
+            let table_layout = self.layout.deref().borrow();
+            let axis = table_layout.header_direction(self.table_axis);
+            let pos = table_layout.table_axis(self.table_axis).gap_offset(*gap);
+            let cross = axis.minor(ctx.size());
+
+            let line = Line::new(Point::from(axis.pack(pos, 0.0)), Point::from(axis.pack(pos, cross)));
+            ctx.stroke(line, &env.get(theme::PRIMARY_LIGHT), 2.0);
+        }
     }
 }
 
@@ -296,6 +1004,14 @@ impl<T: Data> HeaderData<T> {
         self.part.size()
     }
 
+    pub fn part(&self) -> &AxisPart {
+        &self.part
+    }
+
+    pub fn part_mut(&mut self) -> &mut AxisPart {
+        &mut self.part
+    }
+
     pub fn data(&self) -> &T {
         &self.data
     }
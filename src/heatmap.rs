@@ -0,0 +1,247 @@
+use druid::{Color, Env, Lens, PaintCtx, Data, RenderContext};
+use druid::widget::ListIter;
+use crate::painter::paint_grid;
+use crate::table::TableContent;
+use crate::{TableLayout, TablePainter};
+
+/// Maps a value between 0 and 1 to a color.
+#[derive(Clone, Debug)]
+pub enum ColorScale {
+    Sequential {
+        low: Color,
+        high: Color,
+    },
+    /// The middle of the domain gets the `mid` color.
+    Diverging {
+        low: Color,
+        mid: Color,
+        high: Color,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Domain {
+    /// The smallest and largest value in the scope.
+    Auto,
+    Fixed(f64, f64),
+}
+
+/// The cells which share a domain if the domain is computed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DomainScope {
+    Line,
+    Element,
+    Table,
+}
+
+/// Colors the background of every cell by a numeric value of the cell.
+pub struct HeatmapPainter<T> {
+    lines: Vec<Option<Box<dyn Fn(&T, &mut dyn FnMut(usize, f64))>>>,
+    scale: ColorScale,
+    domain: Domain,
+    scope: DomainScope,
+}
+
+fn lerp(a: &Color, b: &Color, t: f64) -> Color {
+    let (r1, g1, b1, a1) = a.as_rgba();
+    let (r2, g2, b2, a2) = b.as_rgba();
+    Color::rgba(r1 + (r2 - r1) * t, g1 + (g2 - g1) * t, b1 + (b2 - b1) * t, a1 + (a2 - a1) * t)
+}
+
+impl ColorScale {
+    pub fn sequential(low: Color, high: Color) -> Self {
+        ColorScale::Sequential {low, high}
+    }
+
+    pub fn diverging(low: Color, mid: Color, high: Color) -> Self {
+        ColorScale::Diverging {low, mid, high}
+    }
+
+    pub fn color(&self, t: f64) -> Color {
+        let t = t.max(0.0).min(1.0);
+        match self {
+            ColorScale::Sequential {low, high} => lerp(low, high, t),
+            ColorScale::Diverging {low, mid, high} => if t < 0.5 {
+                lerp(low, mid, t * 2.0)
+            } else {
+                lerp(mid, high, t * 2.0 - 1.0)
+            },
+        }
+    }
+}
+
+impl<T: Data> HeatmapPainter<T> {
+    pub fn new(scale: ColorScale) -> Self {
+        Self {
+            lines: vec![],
+            scale,
+            domain: Domain::Auto,
+            scope: DomainScope::Table,
+        }
+    }
+
+    pub fn with_domain(mut self, domain: Domain) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    pub fn with_scope(mut self, scope: DomainScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Sets the values of a line by its id, which is the index in which it was added to the table,
+    /// the values follow the line when it is moved. Lines without values keep their background.
+    pub fn with_line_values<
+        T2: ListIter<U>,
+        U: Data,
+
+        L: Lens<T, T2> + 'static,
+    >(mut self, line_id: usize, lens: L, value: impl Fn(&U) -> f64 + 'static) -> Self {
+        if self.lines.len() <= line_id {
+            self.lines.resize_with(line_id + 1, ||None);
+        }
+        self.lines[line_id] = Some(Box::new(move|data, cb|{
+            lens.with(data, |data|data.for_each(|element, index|cb(index, value(element))));
+        }));
+        self
+    }
+
+    fn paint_cells(&self, ctx: &mut PaintCtx, data: &T, content: &TableContent<T>, layout: &TableLayout) {
+        let visible = ctx.region().bounding_box();
+        for (line, element, t) in self.cell_positions(data, |line|content.line_id(line), layout) {
+            let rect = layout.layout_rect(line, element);
+            if rect.intersect(visible).area() > 0.0 {
+                ctx.fill(rect, &self.scale.color(t));
+            }
+        }
+    }
+
+    /// The position of every painted cell on the color scale, hidden and covered cells are
+    /// neither painted nor part of the domain.
+    fn cell_positions(&self, data: &T, line_id: impl Fn(usize) -> usize, layout: &TableLayout) -> Vec<(usize, usize, f64)> {
+        let lines = layout.lines().length();
+        let elements = layout.elements().length();
+
+        let mut cells = vec![];
+        for line in (0..lines).filter(|line|!layout.is_line_hidden(*line)) {
+            if let Some(values) = self.lines.get(line_id(line)).and_then(Option::as_ref) {
+                values(data, &mut |element, value|{
+                    if element < elements && value.is_finite() && !layout.is_covered(line, element) {
+                        cells.push((line, element, value));
+                    }
+                });
+            }
+        }
+
+        let scope_index = |line: usize, element: usize| match self.scope {
+            DomainScope::Line => line,
+            DomainScope::Element => element,
+            DomainScope::Table => 0,
+        };
+        let mut domains = vec![(f64::INFINITY, f64::NEG_INFINITY); lines.max(elements).max(1)];
+        for (line, element, value) in &cells {
+            let domain = &mut domains[scope_index(*line, *element)];
+            *domain = (domain.0.min(*value), domain.1.max(*value));
+        }
+
+        cells.into_iter()
+            .map(|(line, element, value)|{
+                let (min, max) = match self.domain {
+                    Domain::Fixed(min, max) => (min, max),
+                    Domain::Auto => domains[scope_index(line, element)],
+                };
+                let t = if max > min {(value - min) / (max - min)} else {0.5};
+                (line, element, t)
+            })
+            .collect()
+    }
+}
+
+impl<T: Data> TablePainter<T> for HeatmapPainter<T> {
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env, content: &mut TableContent<T>, layout: &TableLayout) {
+        self.paint_cells(ctx, data, content, layout);
+
+        content.paint_background(ctx, data, env, layout);
+        content.paint_foreground(ctx, data, env, layout);
+
+        paint_grid(ctx, env, layout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::im::Vector;
+    use druid::lens::Identity;
+    use druid::widget::Axis;
+    use crate::{AxisPart, CellSpan};
+    use super::*;
+
+    fn layout(lines: usize, elements: usize) -> TableLayout {
+        let mut layout = TableLayout::new(Axis::Vertical);
+        layout.lines_mut().set_length(lines, AxisPart::new(None));
+        layout.elements_mut().set_length(elements, AxisPart::new(None));
+        layout
+    }
+
+    fn painter(scope: DomainScope) -> HeatmapPainter<Vector<f64>> {
+        HeatmapPainter::new(ColorScale::sequential(Color::WHITE, Color::BLACK))
+            .with_scope(scope)
+            .with_line_values(0, Identity, |value: &f64|*value)
+            .with_line_values(1, Identity, |value: &f64|*value * 10.0)
+    }
+
+    #[test]
+    fn scopes_share_a_domain() {
+        let data: Vector<f64> = vec![1.0, 2.0, 3.0].into();
+        let layout = layout(2, 3);
+
+        let table = painter(DomainScope::Table).cell_positions(&data, |line|line, &layout);
+        assert_eq!(table[0], (0, 0, 0.0));
+        assert_eq!(table[5], (1, 2, 1.0));
+
+        let lines = painter(DomainScope::Line).cell_positions(&data, |line|line, &layout);
+        assert_eq!(lines.iter().map(|cell|cell.2).collect::<Vec<_>>(), vec![0.0, 0.5, 1.0, 0.0, 0.5, 1.0]);
+
+        let elements = painter(DomainScope::Element).cell_positions(&data, |line|line, &layout);
+        assert_eq!(elements.iter().map(|cell|cell.2).collect::<Vec<_>>(), vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+
+        // The values follow the line id
+        let swapped = painter(DomainScope::Table).cell_positions(&data, |line|1 - line, &layout);
+        assert_eq!(swapped[0].2, 9.0 / 29.0);
+        assert_eq!(swapped[5].2, 2.0 / 29.0);
+
+        let fixed = painter(DomainScope::Table).with_domain(Domain::Fixed(0.0, 4.0)).cell_positions(&data, |line|line, &layout);
+        assert_eq!(fixed[0], (0, 0, 0.25));
+        // Positions outside of the domain are clamped by the scale
+        assert_eq!(fixed[5], (1, 2, 7.5));
+    }
+
+    #[test]
+    fn hidden_and_covered_cells_are_not_part_of_the_domain() {
+        let data: Vector<f64> = vec![1.0, 2.0, 3.0].into();
+        let mut layout = layout(2, 3);
+        let mut part = layout.lines().get(1);
+        part.set_hidden(true);
+        layout.lines_mut().set(1, part);
+        layout.set_span(CellSpan::new(0, 1, 1, 2));
+
+        let cells = painter(DomainScope::Table).cell_positions(&data, |line|line, &layout);
+        assert_eq!(cells, vec![(0, 0, 0.0), (0, 1, 1.0)]);
+    }
+
+    #[test]
+    fn scales_interpolate_between_their_colors() {
+        let rgba = |color: Color|color.as_rgba8();
+        let sequential = ColorScale::sequential(Color::rgb8(0, 0, 0), Color::rgb8(200, 100, 0));
+        assert_eq!(rgba(sequential.color(0.0)), (0, 0, 0, 255));
+        assert_eq!(rgba(sequential.color(0.5)), (100, 50, 0, 255));
+        assert_eq!(rgba(sequential.color(2.0)), (200, 100, 0, 255));
+
+        let diverging = ColorScale::diverging(Color::rgb8(255, 0, 0), Color::rgb8(255, 255, 255), Color::rgb8(0, 0, 255));
+        assert_eq!(rgba(diverging.color(0.0)), (255, 0, 0, 255));
+        assert_eq!(rgba(diverging.color(0.5)), (255, 255, 255, 255));
+        assert_eq!(rgba(diverging.color(1.0)), (0, 0, 255, 255));
+        assert_eq!(rgba(diverging.color(-1.0)), (255, 0, 0, 255));
+    }
+}
@@ -0,0 +1,423 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+use druid::{Command, Env, Event, EventCtx, KbKey, LifeCycle, LifeCycleCtx, PaintCtx, Point, UpdateCtx, Data};
+use crate::{Table, TableController, TableLayout, TablePolicy};
+use crate::commands::{REDO, UNDO};
+use crate::table::TableContent;
+
+/// A single write of a table. Lines are identified by the index in which they were added.
+#[derive(Clone)]
+pub enum EditOperation {
+    SetValue {
+        line: usize,
+        element: usize,
+        before: Rc<dyn Any>,
+        after: Rc<dyn Any>,
+    },
+    MoveElement {
+        from: usize,
+        to: usize,
+    },
+    InsertElement {
+        index: usize,
+        value: Rc<dyn Any>,
+    },
+    RemoveElement {
+        index: usize,
+        value: Rc<dyn Any>,
+    },
+}
+
+/// A change of the data which can be undone, its operations are undone in reverse order.
+#[derive(Clone, Default)]
+pub struct HistoryStep {
+    operations: Vec<EditOperation>,
+}
+
+/// The undo and redo stacks of a table.
+///
+/// Every write of the table, e.g. an edit, a fill, a replacement or a moved element, is recorded
+/// as operations on cells and elements, which are undone through the table. This way the
+/// widgets, sizes and markers of the elements follow and changes of the data by the application
+/// are kept. Writes made while a cell editor has the focus or inside a group are merged into one step.
+pub struct EditHistory {
+    undo: Vec<HistoryStep>,
+    redo: Vec<HistoryStep>,
+    limit: usize,
+    /// The last step takes further changes.
+    open: bool,
+    editing: bool,
+    groups: usize,
+}
+
+/// Undoes and redoes the steps of an `EditHistory` with Ctrl+Z, Ctrl+Shift+Z and Ctrl+Y,
+/// see `Table::with_history`. The `UNDO` and `REDO` commands are handled by the table.
+pub struct UndoController<T> {
+    inner: Box<dyn TableController<T>>,
+    history: Rc<RefCell<EditHistory>>,
+}
+
+impl HistoryStep {
+    pub fn operations(&self) -> &[EditOperation] {
+        &self.operations
+    }
+
+    /// Adds an operation, a value written again keeps the value from before the first write.
+    fn push(&mut self, operation: EditOperation) {
+        if let EditOperation::SetValue {line, element, after, ..} = &operation {
+            for previous in self.operations.iter_mut().rev() {
+                match previous {
+                    EditOperation::SetValue {line: l, element: e, after: previous_after, ..} if l == line && e == element => {
+                        *previous_after = after.clone();
+                        return;
+                    }
+                    EditOperation::SetValue {..} => {}
+                    // The element may be another one after a structural change
+                    _ => break,
+                }
+            }
+        }
+        self.operations.push(operation);
+    }
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        Self {
+            undo: vec![],
+            redo: vec![],
+            limit: 100,
+            open: false,
+            editing: false,
+            groups: 0,
+        }
+    }
+
+    /// Sets the maximum count of steps which can be undone.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The steps which can be undone, the last one is undone first.
+    pub fn undo_steps(&self) -> &[HistoryStep] {
+        &self.undo
+    }
+
+    pub fn redo_steps(&self) -> &[HistoryStep] {
+        &self.redo
+    }
+
+    /// Records writes of the table, called by the table wherever it changes the data.
+    /// `unchanged` returns true for values which were written back, e.g. by an edit reverted with Escape.
+    pub(crate) fn record(&mut self, operations: Vec<EditOperation>, unchanged: impl Fn(&EditOperation) -> bool) {
+        if operations.is_empty() {
+            return;
+        }
+
+        self.redo.clear();
+        if !self.open || self.undo.is_empty() {
+            self.undo.push(HistoryStep::default());
+            if self.undo.len() > self.limit {
+                self.undo.remove(0);
+            }
+        }
+        if let Some(step) = self.undo.last_mut() {
+            for operation in operations {
+                step.push(operation);
+            }
+            step.operations.retain(|operation|!unchanged(operation));
+            if step.operations.is_empty() {
+                self.undo.pop();
+            }
+        }
+        self.open = self.groups > 0 || self.editing;
+    }
+
+    /// Starts a group, all changes until the matching `end_group` are undone as one step.
+    pub fn begin_group(&mut self) {
+        if self.groups == 0 {
+            self.open = false;
+        }
+        self.groups += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        self.groups = self.groups.saturating_sub(1);
+        if self.groups == 0 {
+            self.open = false;
+        }
+    }
+
+    /// While a cell is edited all changes are merged into one step.
+    pub fn set_editing(&mut self, editing: bool) {
+        if self.editing && !editing && self.groups == 0 {
+            self.open = false;
+        }
+        self.editing = editing;
+    }
+
+    /// Moves the last step to the redo stack and returns it, see `Table::undo`.
+    pub(crate) fn take_undo(&mut self) -> Option<HistoryStep> {
+        let step = self.undo.pop()?;
+        self.redo.push(step.clone());
+        self.open = false;
+        Some(step)
+    }
+
+    /// Moves the last undone step back to the undo stack and returns it, see `Table::redo`.
+    pub(crate) fn take_redo(&mut self) -> Option<HistoryStep> {
+        let step = self.redo.pop()?;
+        self.undo.push(step.clone());
+        self.open = false;
+        Some(step)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = false;
+    }
+}
+
+impl<T: Data, P: TablePolicy<T>> Table<T, P> {
+    /// Undoes the last step of the edit history, returns false if there is nothing to undo.
+    pub fn undo(&mut self, data: &mut T) -> bool {
+        let step = match self.history.as_ref().and_then(|history|history.borrow_mut().take_undo()) {
+            Some(step) => step,
+            None => return false,
+        };
+        for operation in step.operations.iter().rev() {
+            match operation {
+                EditOperation::SetValue {line, element, before, ..} => {
+                    self.set_line_value(data, *line, *element, before.as_ref());
+                }
+                EditOperation::MoveElement {from, to} => {
+                    self.apply_move_element(data, *to, *from);
+                }
+                EditOperation::InsertElement {index, ..} => {
+                    self.apply_remove_element(data, *index);
+                }
+                EditOperation::RemoveElement {index, value} => {
+                    self.apply_insert_element(data, *index, value.as_ref());
+                }
+            }
+        }
+        true
+    }
+
+    /// Repeats the last undone step, returns false if there is nothing to redo.
+    pub fn redo(&mut self, data: &mut T) -> bool {
+        let step = match self.history.as_ref().and_then(|history|history.borrow_mut().take_redo()) {
+            Some(step) => step,
+            None => return false,
+        };
+        for operation in &step.operations {
+            match operation {
+                EditOperation::SetValue {line, element, after, ..} => {
+                    self.set_line_value(data, *line, *element, after.as_ref());
+                }
+                EditOperation::MoveElement {from, to} => {
+                    self.apply_move_element(data, *from, *to);
+                }
+                EditOperation::InsertElement {index, value} => {
+                    self.apply_insert_element(data, *index, value.as_ref());
+                }
+                EditOperation::RemoveElement {index, ..} => {
+                    self.apply_remove_element(data, *index);
+                }
+            }
+        }
+        true
+    }
+
+    /// Handles `UNDO` and `REDO`, returns false for other commands.
+    pub(crate) fn history_command(&mut self, ctx: &mut EventCtx, command: &Command, data: &mut T) -> bool {
+        let applied = if command.is(UNDO) {
+            self.undo(data)
+        } else if command.is(REDO) {
+            self.redo(data)
+        } else {
+            return false;
+        };
+        if applied {
+            ctx.request_layout();
+        }
+        true
+    }
+
+    /// Writes a value into the line with the given id without recording it.
+    fn set_line_value(&mut self, data: &mut T, line_id: usize, element: usize, value: &dyn Any) {
+        if let Some(line) = self.line_order.iter().position(|id|*id == line_id) {
+            self.lines[line].set_value(data, element, value);
+        }
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Data> UndoController<T> {
+    pub(crate) fn new(inner: impl TableController<T> + 'static, history: Rc<RefCell<EditHistory>>) -> Self {
+        Self {
+            inner: Box::new(inner),
+            history,
+        }
+    }
+
+    /// The history is shared, e.g. to undo from a menu or to show the steps.
+    pub fn history(&self) -> Rc<RefCell<EditHistory>> {
+        self.history.clone()
+    }
+}
+
+impl<T: Data> TableController<T> for UndoController<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env, content: &mut TableContent<T>, layout: &TableLayout) {
+        self.inner.event(ctx, event, data, env, content, layout);
+        if ctx.is_handled() {
+            return;
+        }
+
+        let undo = match event {
+            Event::KeyDown(key) if key.mods.ctrl() || key.mods.meta() => match &key.key {
+                KbKey::Character(c) if c.eq_ignore_ascii_case("z") => Some(!key.mods.shift()),
+                KbKey::Character(c) if c.eq_ignore_ascii_case("y") => Some(false),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(undo) = undo {
+            // The steps are undone by the table, which keeps its widgets and layout in sync
+            let command = if undo {UNDO} else {REDO};
+            ctx.submit_command(command.to(ctx.widget_id()));
+            ctx.set_handled();
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.inner.lifecycle(ctx, event, data, env);
+        if let LifeCycle::FocusChanged(_) | LifeCycle::Internal(_) = event {
+            // A descendant has the focus while a cell editor is open
+            self.history.borrow_mut().set_editing(ctx.has_focus() && !ctx.is_focused());
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        self.inner.update(ctx, old_data, data, env);
+    }
+
+    fn captures_mouse(&self, pos: Point, layout: &TableLayout) -> bool {
+        self.inner.captures_mouse(pos, layout)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, env: &Env, layout: &TableLayout) {
+        self.inner.paint(ctx, env, layout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(element: usize, before: i32, after: i32) -> EditOperation {
+        EditOperation::SetValue {
+            line: 0,
+            element,
+            before: Rc::new(before),
+            after: Rc::new(after),
+        }
+    }
+
+    fn values(step: &HistoryStep) -> Vec<(usize, i32, i32)> {
+        step.operations().iter()
+            .filter_map(|operation|match operation {
+                EditOperation::SetValue {element, before, after, ..} => Some((
+                    *element,
+                    *before.downcast_ref::<i32>()?,
+                    *after.downcast_ref::<i32>()?,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn unchanged(operation: &EditOperation) -> bool {
+        match operation {
+            EditOperation::SetValue {before, after, ..} => before.downcast_ref::<i32>() == after.downcast_ref::<i32>(),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut history = EditHistory::new();
+        history.record(vec![set(0, 1, 2)], unchanged);
+        history.record(vec![set(0, 2, 3)], unchanged);
+        history.record(vec![], unchanged);
+        assert_eq!(history.undo_steps().len(), 2);
+
+        assert_eq!(history.take_undo().map(|step|values(&step)), Some(vec![(0, 2, 3)]));
+        assert_eq!(history.take_undo().map(|step|values(&step)), Some(vec![(0, 1, 2)]));
+        assert!(history.take_undo().is_none());
+        assert_eq!(history.take_redo().map(|step|values(&step)), Some(vec![(0, 1, 2)]));
+
+        // A new write drops the steps which could be redone
+        history.record(vec![set(0, 2, 5)], unchanged);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo_steps().last().map(values), Some(vec![(0, 2, 5)]));
+    }
+
+    #[test]
+    fn groups_and_editing_merge_steps() {
+        let mut history = EditHistory::new();
+        history.begin_group();
+        history.record(vec![set(0, 1, 2)], unchanged);
+        history.begin_group();
+        history.record(vec![set(1, 2, 3)], unchanged);
+        history.end_group();
+        history.record(vec![set(0, 2, 4)], unchanged);
+        history.end_group();
+        history.record(vec![set(0, 4, 5)], unchanged);
+        assert_eq!(history.undo_steps().len(), 2);
+        // The first value of a cell is kept when it is written again
+        assert_eq!(values(&history.undo_steps()[0]), vec![(0, 1, 4), (1, 2, 3)]);
+
+        history.set_editing(true);
+        history.record(vec![set(0, 5, 6)], unchanged);
+        history.record(vec![set(0, 6, 5)], unchanged);
+        history.set_editing(false);
+        // The edit was reverted, which removes its step
+        assert_eq!(history.undo_steps().len(), 2);
+    }
+
+    #[test]
+    fn structural_operations_end_merging_of_values() {
+        let mut history = EditHistory::new();
+        history.begin_group();
+        history.record(vec![set(0, 1, 2), EditOperation::MoveElement {from: 0, to: 1}, set(0, 3, 4)], unchanged);
+        history.end_group();
+        assert_eq!(history.undo_steps()[0].operations().len(), 3);
+    }
+
+    #[test]
+    fn limit() {
+        let mut history = EditHistory::new().with_limit(2);
+        for value in 0..5 {
+            history.record(vec![set(0, value, value + 1)], unchanged);
+        }
+        assert_eq!(history.undo_steps().len(), 2);
+        assert_eq!(values(&history.undo_steps()[0]), vec![(0, 3, 4)]);
+    }
+}
@@ -1,30 +1,83 @@
-use druid::{BoxConstraints, Rect, Size, Vec2, Data};
+use std::ops::Range;
+use druid::{BoxConstraints, Point, Rect, Size, Vec2, Data};
 use druid::widget::Axis;
-use crate::util::set_len;
+use crate::util::{move_item, moved_index, set_len};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TableLayout {
+    #[cfg_attr(feature = "serde", serde(rename = "elements"))]
     element_layout: AxisLayout,
+    #[cfg_attr(feature = "serde", serde(rename = "lines"))]
     line_layout: AxisLayout,
+    #[cfg_attr(feature = "serde", serde(with = "serde_axis"))]
     line_axis: Axis,
+    #[cfg_attr(feature = "serde", serde(default))]
+    spans: Vec<CellSpan>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cell_padding: f64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hovered: Option<(usize, usize)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cursor: Option<(usize, usize)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    selection_anchor: Option<(usize, usize)>,
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AxisLayout {
+    #[cfg_attr(feature = "serde", serde(rename = "parts"))]
     layout: Vec<AxisPart>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    keys: Vec<Option<String>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     max_additional_size: f64,
     start_padding: f64,
 }
 
 #[derive(Clone, Copy, Data)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AxisPart {
+    #[cfg_attr(feature = "serde", serde(rename = "padding"))]
     end_padding: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    custom_padding: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     size: f64,
     min: f64,
+    #[cfg_attr(feature = "serde", serde(with = "serde_max"))]
     max: f64,
+    #[cfg_attr(feature = "serde", serde(rename = "fixed"))]
     is_fixed: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    detail: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    expanded: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    hidden: bool,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CellSpan {
+    pub line: usize,
+    pub element: usize,
+    pub lines: usize,
+    pub elements: usize,
+}
+
+/// A rectangular range of cells, the bounds are inclusive.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct CellRange {
+    pub lines: (usize, usize),
+    pub elements: (usize, usize),
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TableAxis {
     LineAxis,
     ElementAxis,
@@ -36,6 +89,11 @@ impl TableLayout {
             element_layout: AxisLayout::new(),
             line_layout: AxisLayout::new(),
             line_axis,
+            spans: vec![],
+            cell_padding: 0.0,
+            hovered: None,
+            cursor: None,
+            selection_anchor: None,
         }
     }
 
@@ -44,26 +102,215 @@ impl TableLayout {
         self.element_layout.prepare_layout(self.line_axis.major(max_size));
     }
 
+    /// Sets the width of the grid lines, parts with a custom padding keep it.
+    pub fn set_grid_width(&mut self, width: f64) {
+        self.line_layout.set_grid_width(width);
+        self.element_layout.set_grid_width(width);
+    }
+
+    pub fn cell_padding(&self) -> f64 {
+        self.cell_padding
+    }
+
+    pub fn set_cell_padding(&mut self, padding: f64) {
+        self.cell_padding = padding;
+    }
+
+    /// The cell at the given position, for cells covered by a span the origin of the span.
+    pub fn cell_at(&self, pos: Point) -> Option<(usize, usize)> {
+        let line = self.line_layout.index_at(self.line_axis.minor_pos(pos))?;
+        let element = self.element_layout.index_at(self.line_axis.major_pos(pos))?;
+        Some(match self.spans.iter().find(|span|span.covers(line, element)) {
+            Some(span) => (span.line, span.element),
+            None => (line, element),
+        })
+    }
+
+    pub fn hovered(&self) -> Option<(usize, usize)> {
+        self.hovered
+    }
+
+    pub fn set_hovered(&mut self, hovered: Option<(usize, usize)>) {
+        self.hovered = hovered;
+    }
+
+    /// The active cell as (line, element), which receives keyboard input.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, cursor: Option<(usize, usize)>) {
+        self.cursor = cursor;
+    }
+
+    /// The cell where the selection started, the selection ranges from it to the cursor.
+    pub fn selection_anchor(&self) -> Option<(usize, usize)> {
+        self.selection_anchor
+    }
+
+    pub fn set_selection_anchor(&mut self, anchor: Option<(usize, usize)>) {
+        self.selection_anchor = anchor;
+    }
+
+    /// The selected cells, without an anchor only the cell under the cursor.
+    pub fn selection(&self) -> Option<CellRange> {
+        let cursor = self.cursor?;
+        Some(CellRange::new(self.selection_anchor.unwrap_or(cursor), cursor))
+    }
+
+    pub fn set_selection(&mut self, selection: CellRange) {
+        self.selection_anchor = Some((selection.lines.0, selection.elements.0));
+        self.cursor = Some((selection.lines.1, selection.elements.1));
+    }
+
+    /// The rect around all cells of a range.
+    pub fn range_rect(&self, range: CellRange) -> Rect {
+        self.layout_rect(range.lines.0, range.elements.0)
+            .union(self.layout_rect(range.lines.1, range.elements.1))
+    }
+
+    /// Moves the cursor by the given count of lines and elements, skipping hidden lines.
+    /// Returns false if the cursor did not change.
+    pub fn move_cursor(&mut self, lines: isize, elements: isize) -> bool {
+        let (line, element) = match self.cursor {
+            Some(cursor) => cursor,
+            None => return false,
+        };
+        let line_count = self.line_layout.length() as isize;
+        let element_count = self.element_layout.length() as isize;
+
+        let mut new_line = line as isize;
+        let mut remaining = lines.abs();
+        while remaining > 0 {
+            let next = new_line + lines.signum();
+            if next < 0 || next >= line_count {
+                break;
+            }
+            new_line = next;
+            if !self.is_line_hidden(next as usize) {
+                remaining -= 1;
+            }
+        }
+        if self.is_line_hidden(new_line as usize) {
+            new_line = line as isize;
+        }
+        let new_element = (element as isize + elements).max(0).min(element_count - 1).max(0);
+
+        let cursor = Some((new_line as usize, new_element as usize));
+        let changed = cursor != self.cursor;
+        self.cursor = cursor;
+        changed
+    }
+
+    /// The rect of the widget of a cell, which is the layout rect without the cell padding.
+    pub fn content_rect(&self, line: usize, element: usize) -> Rect {
+        self.layout_rect(line, element).inset(-self.cell_padding)
+    }
+
     pub fn layout_rect(&self, line: usize, element: usize) -> Rect {
-        let (l1, l2) = self.line_layout.current_layout(line);
-        let (e1, e2) = self.element_layout.current_layout(element);
+        let (lines, elements) = self.cell_extent(line, element);
+        let (l1, l2) = self.line_layout.span_layout(line, lines);
+        let (e1, e2) = self.element_layout.span_layout(element, elements);
 
         Rect::from_points(self.line_axis.pack(e1, l1), self.line_axis.pack(e2, l2))
     }
 
     pub fn layout(&mut self, line: usize, element: usize, layout: impl FnOnce(&BoxConstraints) -> Size) {
-        let line_constrains = self.line_layout.constrains(line);
-        let element_constrains = self.element_layout.constrains(element);
+        let (lines, elements) = self.cell_extent(line, element);
+        let line_constrains = self.line_layout.span_constrains(line, lines);
+        let element_constrains = self.element_layout.span_constrains(element, elements);
 
         let inner_bc = BoxConstraints::new(
             Size::from(self.line_axis.pack(element_constrains.0, line_constrains.0)),
             Size::from(self.line_axis.pack(element_constrains.1, line_constrains.1)),
         );
 
-        let size = layout(&inner_bc);
+        let padding = Size::new(self.cell_padding * 2.0, self.cell_padding * 2.0);
+        let size = layout(&inner_bc.shrink(padding)) + padding;
+
+        self.line_layout.set_span_size(line, lines, self.line_axis.minor(size));
+        self.element_layout.set_span_size(element, elements, self.line_axis.major(size));
+    }
+
+    /// Moves a line together with its spans, the hovered cell, the cursor and the selection.
+    pub fn move_line(&mut self, from: usize, to: usize) {
+        if from >= self.line_layout.length() || to >= self.line_layout.length() {
+            return;
+        }
+        self.line_layout.move_part(from, to);
+
+        let moved = |line|moved_index(line, from, to);
+        for span in self.spans.iter_mut() {
+            span.line = moved_span_start(span.line, span.lines, moved);
+        }
+        let moved_cell = |cell: Option<(usize, usize)>|cell.map(|(line, element)|(moved(line), element));
+        self.hovered = moved_cell(self.hovered);
+        self.cursor = moved_cell(self.cursor);
+        self.selection_anchor = moved_cell(self.selection_anchor);
+    }
+
+    /// Moves an element together with its spans, the hovered cell, the cursor and the selection.
+    pub fn move_element(&mut self, from: usize, to: usize) {
+        if from >= self.element_layout.length() || to >= self.element_layout.length() {
+            return;
+        }
+        self.element_layout.move_part(from, to);
+
+        let moved = |element|moved_index(element, from, to);
+        for span in self.spans.iter_mut() {
+            span.element = moved_span_start(span.element, span.elements, moved);
+        }
+        let moved_cell = |cell: Option<(usize, usize)>|cell.map(|(line, element)|(line, moved(element)));
+        self.hovered = moved_cell(self.hovered);
+        self.cursor = moved_cell(self.cursor);
+        self.selection_anchor = moved_cell(self.selection_anchor);
+    }
+
+    pub fn set_span(&mut self, span: CellSpan) {
+        self.remove_span(span.line, span.element);
+        if span.lines > 1 || span.elements > 1 {
+            self.spans.push(span);
+        }
+    }
+
+    pub fn remove_span(&mut self, line: usize, element: usize) {
+        self.spans.retain(|span|span.line != line || span.element != element);
+    }
+
+    pub fn clear_spans(&mut self) {
+        self.spans.clear();
+    }
+
+    pub fn spans(&self) -> &[CellSpan] {
+        &self.spans
+    }
+
+    pub fn span(&self, line: usize, element: usize) -> Option<CellSpan> {
+        self.spans.iter().find(|span|span.line == line && span.element == element).copied()
+    }
+
+    /// Returns true if the cell is hidden behind a span starting at another cell.
+    pub fn is_covered(&self, line: usize, element: usize) -> bool {
+        self.spans.iter().any(|span|span.covers(line, element) && (span.line != line || span.element != element))
+    }
+
+    /// Applies the line axis and the configuration of the lines of a stored layout,
+    /// lines are matched by their key.
+    pub fn apply(&mut self, other: &TableLayout) {
+        self.line_axis = other.line_axis;
+        self.line_layout.apply(&other.line_layout);
+    }
 
-        self.line_layout.set_size(line, self.line_axis.minor(size));
-        self.element_layout.set_size(element, self.line_axis.major(size));
+    pub fn is_line_hidden(&self, line: usize) -> bool {
+        line < self.line_layout.length() && self.line_layout.get(line).is_hidden()
+    }
+
+    /// The number of lines and elements the cell extends over, clamped to the current table size.
+    pub fn cell_extent(&self, line: usize, element: usize) -> (usize, usize) {
+        self.span(line, element).map_or((1, 1), |span|(
+            span.lines.min(self.line_layout.length().saturating_sub(line)).max(1),
+            span.elements.min(self.element_layout.length().saturating_sub(element)).max(1),
+        ))
     }
 
     pub fn as_cell_offset(&self, offset: Vec2) -> (Vec2, u64, u64) {
@@ -78,6 +325,33 @@ impl TableLayout {
         Vec2::from(self.line_axis.pack(element_offset, line_offset))
     }
 
+    /// The rect of an element across all lines, including its detail.
+    pub fn element_rect(&self, element: usize) -> Rect {
+        let (l1, l2) = (self.line_layout.start_padding(), self.line_layout.size());
+        let (e1, _) = self.element_layout.current_layout(element);
+        let (_, e2) = self.element_layout.detail_layout(element);
+
+        Rect::from_points(self.line_axis.pack(e1, l1), self.line_axis.pack(e2, l2))
+    }
+
+    /// The rect of a line across all elements.
+    pub fn line_rect(&self, line: usize) -> Rect {
+        let (l1, l2) = self.line_layout.current_layout(line);
+        let (e1, e2) = (self.element_layout.start_padding(), self.element_layout.size());
+
+        Rect::from_points(self.line_axis.pack(e1, l1), self.line_axis.pack(e2, l2))
+    }
+
+    pub fn detail_rect(&self, element: usize) -> Rect {
+        let (l1, l2) = match self.line_layout.length() {
+            0 => (self.line_layout.start_padding(), self.line_layout.start_padding()),
+            length => self.line_layout.span_layout(0, length),
+        };
+        let (e1, e2) = self.element_layout.detail_layout(element);
+
+        Rect::from_points(self.line_axis.pack(e1, l1), self.line_axis.pack(e2, l2))
+    }
+
     pub fn table_size(&self) -> Size {
         Size::from(self.line_axis.pack(
             self.element_layout.size(),
@@ -89,6 +363,14 @@ impl TableLayout {
         self.line_axis
     }
 
+    pub fn set_line_axis(&mut self, line_axis: Axis) {
+        self.line_axis = line_axis;
+    }
+
+    pub fn transpose(&mut self) {
+        self.line_axis = self.line_axis.cross();
+    }
+
     pub fn axis_direction(&self, table_axis: TableAxis) -> Axis {
         match table_axis {
             TableAxis::LineAxis => {self.line_axis}
@@ -142,10 +424,20 @@ impl TableLayout {
     }
 }
 
+/// The start of a span after a move of its parts. A span keeps its parts if they are still next to
+/// each other, otherwise it follows the part it starts at.
+fn moved_span_start(start: usize, length: usize, moved: impl Fn(usize) -> usize) -> usize {
+    let (first, last) = (start..start + length)
+        .map(&moved)
+        .fold((usize::MAX, 0), |(first, last), index|(first.min(index), last.max(index)));
+    if last + 1 - first == length {first} else {moved(start)}
+}
+
 impl AxisLayout {
     pub fn new() -> Self {
         Self {
             layout: vec![],
+            keys: vec![],
             max_additional_size: 0.0,
             start_padding: 1.0,
         }
@@ -154,21 +446,58 @@ impl AxisLayout {
     pub fn prepare_layout(&mut self, max_size: f64) {
         self.max_additional_size = max_size;
         for part in self.layout.iter_mut() {
-            if !part.is_fixed {
-                part.size = part.min;
-            }
+            // The minimum of a fixed part is its size, parts created by `AxisPart::new` or
+            // deserialized start with a size of 0 and are never grown by `calc_space`
+            part.size = part.min;
             self.max_additional_size -= part.advance();
         }
     }
 
+    pub fn set_grid_width(&mut self, width: f64) {
+        self.start_padding = width;
+        for part in self.layout.iter_mut().filter(|part|!part.custom_padding) {
+            part.end_padding = width;
+        }
+    }
+
     pub fn constrains(&self, index: usize) -> (f64, f64) {
         self.layout[index].constrains(self.max_additional_size)
     }
 
+    pub fn span_constrains(&self, index: usize, length: usize) -> (f64, f64) {
+        let range = self.span_range(index, length);
+        if range.len() <= 1 {
+            return self.constrains(index);
+        }
+        let parts = &self.layout[range];
+        let padding = parts[..parts.len() - 1].iter().map(|part|part.advance() - part.size).sum::<f64>();
+
+        let min = parts.iter().map(|part|part.min).sum::<f64>() + padding;
+        let max = parts.iter().map(|part|part.max).sum::<f64>()
+            .min(parts.iter().map(|part|part.size).sum::<f64>() + self.max_additional_size) + padding;
+        (min, max.max(min))
+    }
+
     pub fn set_size(&mut self, index: usize, size: f64) {
         self.max_additional_size = self.layout[index].calc_space(size, self.max_additional_size);
     }
 
+    pub fn set_span_size(&mut self, index: usize, length: usize, size: f64) {
+        if length <= 1 {
+            return self.set_size(index, size);
+        }
+        let (start, end) = self.span_layout(index, length);
+        let missing = size - (end - start);
+        let range = self.span_range(index, length);
+
+        // The last flexible part of the span takes up the additional space
+        if missing > 0.0 {
+            if let Some(part) = self.layout[range].iter_mut().rev().find(|part|!part.is_fixed) {
+                self.max_additional_size = part.calc_space(part.size + missing, self.max_additional_size);
+            }
+        }
+    }
+
     pub fn as_cell_offset(&self, mut offset: f64) -> (f64, u64) {
         offset -= self.start_padding;
         for (index, element) in self.layout.iter().enumerate() {
@@ -185,18 +514,148 @@ impl AxisLayout {
     }
 
     pub fn current_layout(&self, index: usize) -> (f64, f64) {
-        let mut iter = self.layout.iter();
+        self.span_layout(index, 1)
+    }
+
+    /// The start and end offset of `length` parts from `index` on. The span is clamped to the
+    /// parts of the axis and ends with its last visible part, a span without visible parts is empty.
+    pub fn span_layout(&self, index: usize, length: usize) -> (f64, f64) {
+        let range = self.span_range(index, length);
+        let start = self.layout[..range.start].iter().map(AxisPart::advance).sum::<f64>() + self.start_padding;
+
+        let mut offset = start;
+        let mut end = start;
+        for part in &self.layout[range] {
+            if !part.hidden {
+                end = offset + part.size;
+            }
+            offset += part.advance();
+        }
+        (start, end)
+    }
+
+    /// The indices of `length` parts from `index` on which exist, at least one if the part exists.
+    fn span_range(&self, index: usize, length: usize) -> Range<usize> {
+        let start = index.min(self.layout.len());
+        start..(index.saturating_add(length.max(1))).min(self.layout.len())
+    }
 
-        let l1 = (&mut iter).take(index).map(AxisPart::advance).sum::<f64>() + self.start_padding;
-        (l1, l1 + iter.next().unwrap().size())
+    pub fn detail_layout(&self, index: usize) -> (f64, f64) {
+        let (_, end) = self.current_layout(index);
+        (end, end + self.layout[index].detail)
+    }
+
+    pub fn set_detail_size(&mut self, index: usize, size: f64) {
+        let part = &mut self.layout[index];
+        part.detail = if part.expanded {size} else {0.0};
     }
 
     pub fn set_length(&mut self, length: usize, new: AxisPart) {
         set_len(&mut self.layout, length, ||new);
+        set_len(&mut self.keys, length, ||None);
     }
 
     pub fn add_part(&mut self, part: AxisPart) {
         self.layout.push(part);
+        self.keys.push(None);
+    }
+
+    pub fn move_part(&mut self, from: usize, to: usize) {
+        move_item(&mut self.layout, from, to);
+        move_item(&mut self.keys, from, to);
+    }
+
+    pub fn insert_part(&mut self, index: usize, part: AxisPart) {
+        let index = index.min(self.layout.len());
+        self.layout.insert(index, part);
+        self.keys.insert(index, None);
+    }
+
+    pub fn remove_part(&mut self, index: usize) {
+        if index < self.layout.len() {
+            self.layout.remove(index);
+            self.keys.remove(index);
+        }
+    }
+
+    /// The key identifies a part independent of its index, e.g. when applying a stored layout.
+    pub fn key(&self, index: usize) -> Option<&str> {
+        self.keys.get(index)?.as_deref()
+    }
+
+    pub fn set_key(&mut self, index: usize, key: impl Into<String>) {
+        if index < self.layout.len() {
+            self.keys.resize(self.layout.len(), None);
+            self.keys[index] = Some(key.into());
+        }
+    }
+
+    pub fn index_of(&self, key: &str) -> Option<usize> {
+        self.keys.iter().position(|other|other.as_deref() == Some(key))
+    }
+
+    /// Copies the size constraints of the parts with the same key from `other`, parts without a
+    /// key or without a counterpart are unchanged.
+    /// Visibility, expansion and padding stay as they are, the visibility is restored by a
+    /// `TableViewState`.
+    pub fn apply(&mut self, other: &AxisLayout) {
+        for (index, key) in self.keys.iter().enumerate() {
+            if let Some(part) = key.as_deref().and_then(|key|other.index_of(key)).map(|index|other.get(index)) {
+                let own = &mut self.layout[index];
+                own.min = part.min;
+                own.max = part.max;
+                own.is_fixed = part.is_fixed;
+                own.size = part.min;
+            }
+        }
+    }
+
+    /// Returns the index of the part at the given offset.
+    pub fn index_at(&self, offset: f64) -> Option<usize> {
+        let mut start = self.start_padding;
+        for (index, part) in self.layout.iter().enumerate() {
+            if offset >= start && offset < start + part.advance() {
+                return Some(index);
+            }
+            start += part.advance();
+        }
+        None
+    }
+
+    /// The visible parts which overlap the range from `start` to `end` as their index, start and
+    /// end offset, the offsets are accumulated in a single pass.
+    pub(crate) fn visible_parts(&self, start: f64, end: f64) -> Vec<(usize, f64, f64)> {
+        let mut parts = vec![];
+        let mut offset = self.start_padding;
+        for (index, part) in self.layout.iter().enumerate() {
+            if offset > end {
+                break;
+            }
+            if !part.hidden && offset + part.advance() >= start {
+                parts.push((index, offset, offset + part.size));
+            }
+            offset += part.advance();
+        }
+        parts
+    }
+
+    /// Returns the index of the gap between two parts which is closest to the given offset.
+    /// Gap `0` is before the first part, gap `length()` after the last one.
+    pub fn gap_at(&self, offset: f64) -> usize {
+        let mut start = self.start_padding;
+        for (index, part) in self.layout.iter().enumerate() {
+            if offset < start + part.advance() / 2.0 {
+                return index;
+            }
+            start += part.advance();
+        }
+        self.layout.len()
+    }
+
+    pub fn gap_offset(&self, gap: usize) -> f64 {
+        let before = &self.layout[..gap];
+        let padding = before.last().map_or(0.0, |part|part.end_padding);
+        before.iter().map(AxisPart::advance).sum::<f64>() + self.start_padding - padding / 2.0
     }
 
     pub fn get(&self, index: usize) -> AxisPart {
@@ -224,6 +683,46 @@ impl AxisLayout {
     }
 }
 
+impl CellSpan {
+    pub fn new(line: usize, element: usize, lines: usize, elements: usize) -> Self {
+        Self {
+            line,
+            element,
+            lines,
+            elements,
+        }
+    }
+
+    pub fn covers(&self, line: usize, element: usize) -> bool {
+        (self.line..self.line + self.lines).contains(&line) &&
+            (self.element..self.element + self.elements).contains(&element)
+    }
+}
+
+impl CellRange {
+    /// The range between two cells given as (line, element).
+    pub fn new(from: (usize, usize), to: (usize, usize)) -> Self {
+        Self {
+            lines: (from.0.min(to.0), from.0.max(to.0)),
+            elements: (from.1.min(to.1), from.1.max(to.1)),
+        }
+    }
+
+    pub fn contains(&self, line: usize, element: usize) -> bool {
+        (self.lines.0..=self.lines.1).contains(&line) && (self.elements.0..=self.elements.1).contains(&element)
+    }
+
+    pub fn is_single_cell(&self) -> bool {
+        self.lines.0 == self.lines.1 && self.elements.0 == self.elements.1
+    }
+}
+
+impl Default for AxisPart {
+    fn default() -> Self {
+        AxisPart::new(None)
+    }
+}
+
 impl AxisPart {
     pub fn new(size: Option<f64>) -> Self {
         AxisPart {
@@ -232,15 +731,114 @@ impl AxisPart {
             max: size.unwrap_or(f64::INFINITY),
             is_fixed: size.is_some(),
             end_padding: 1.0,
+            custom_padding: false,
+            detail: 0.0,
+            expanded: false,
+            hidden: false,
+        }
+    }
+
+    /// A flexible part which takes at least `min`.
+    pub fn with_min(mut self, min: f64) -> Self {
+        self.set_min(min);
+        self
+    }
+
+    pub fn with_max(mut self, max: f64) -> Self {
+        self.set_max(max);
+        self
+    }
+
+    /// A part with a fixed size, which does not depend on its content.
+    pub fn with_fixed(mut self, size: f64) -> Self {
+        self.set_fixed(Some(size));
+        self
+    }
+
+    /// The space after the part, which is used by the grid lines.
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.set_padding(padding);
+        self
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// Has no effect on a fixed part, which keeps its size until `set_fixed(None)`.
+    pub fn set_min(&mut self, min: f64) {
+        if !self.is_fixed {
+            self.min = min;
+            self.max = self.max.max(min);
+        }
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Has no effect on a fixed part, which keeps its size until `set_fixed(None)`.
+    pub fn set_max(&mut self, max: f64) {
+        if !self.is_fixed {
+            self.max = max;
+            self.min = self.min.min(max);
         }
     }
 
+    pub fn padding(&self) -> f64 {
+        self.end_padding
+    }
+
+    pub fn set_padding(&mut self, padding: f64) {
+        self.end_padding = padding;
+        self.custom_padding = true;
+    }
+
     pub fn size(&self) -> f64 {
         self.size
     }
 
     pub fn advance(&self) -> f64 {
-        self.size + self.end_padding
+        if self.hidden {
+            return 0.0;
+        }
+        self.size + self.detail + self.end_padding
+    }
+
+    pub fn detail_size(&self) -> f64 {
+        self.detail
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+
+    pub fn set_expanded(&mut self, expanded: bool) {
+        self.expanded = expanded;
+        if !expanded {
+            self.detail = 0.0;
+        }
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.is_fixed
+    }
+
+    /// Gives the part a fixed size or makes it flexible again.
+    pub fn set_fixed(&mut self, size: Option<f64>) {
+        self.min = size.unwrap_or(0.0);
+        self.max = size.unwrap_or(f64::INFINITY);
+        self.is_fixed = size.is_some();
+        self.size = size.unwrap_or(self.size);
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// A hidden part keeps its configuration but takes no space.
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
     }
 
     pub fn constrains(&self, max_additional_size: f64) -> (f64, f64) {
@@ -257,4 +855,290 @@ impl AxisPart {
             max_additional_space
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(feature = "serde")]
+mod serde_max {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    // Infinity is not supported by most formats, an unbounded maximum is stored as none.
+    pub fn serialize<S: Serializer>(max: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        Some(*max).filter(|max|max.is_finite()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::INFINITY))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_axis {
+    use druid::widget::Axis;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum AxisDef {
+        Horizontal,
+        Vertical,
+    }
+
+    pub fn serialize<S: Serializer>(axis: &Axis, serializer: S) -> Result<S::Ok, S::Error> {
+        match axis {
+            Axis::Horizontal => AxisDef::Horizontal,
+            Axis::Vertical => AxisDef::Vertical,
+        }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Axis, D::Error> {
+        Ok(match AxisDef::deserialize(deserializer)? {
+            AxisDef::Horizontal => Axis::Horizontal,
+            AxisDef::Vertical => Axis::Vertical,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axis_layout(parts: &[AxisPart], max_size: f64) -> AxisLayout {
+        let mut layout = AxisLayout::new();
+        for part in parts {
+            layout.add_part(*part);
+        }
+        layout.prepare_layout(max_size);
+        layout
+    }
+
+    #[test]
+    fn span_constrains_include_inner_padding() {
+        let layout = axis_layout(&[
+            AxisPart::new(None).with_min(10.0),
+            AxisPart::new(None).with_fixed(20.0),
+            AxisPart::new(None).with_max(30.0),
+        ], 100.0);
+
+        assert_eq!(layout.span_constrains(0, 1), layout.constrains(0));
+        assert_eq!(layout.span_constrains(0, 2), (31.0, 98.0));
+        assert_eq!(layout.span_constrains(1, 2), (21.0, 51.0));
+    }
+
+    #[test]
+    fn set_span_size_grows_last_flexible_part() {
+        let mut layout = axis_layout(&[
+            AxisPart::new(None).with_min(10.0),
+            AxisPart::new(None).with_fixed(20.0),
+        ], 100.0);
+
+        layout.set_span_size(0, 2, 50.0);
+        assert_eq!(layout.get(0).size(), 29.0);
+        assert_eq!(layout.get(1).size(), 20.0);
+        assert_eq!(layout.span_layout(0, 2), (1.0, 51.0));
+        assert_eq!(layout.max_additional_size, 48.0);
+
+        // A span which is already large enough changes nothing
+        layout.set_span_size(0, 2, 40.0);
+        assert_eq!(layout.get(0).size(), 29.0);
+    }
+
+    #[test]
+    fn prepare_layout_sizes_fixed_parts() {
+        let layout = axis_layout(&[AxisPart::new(Some(20.0)), AxisPart::new(None)], 100.0);
+        assert_eq!(layout.get(0).size(), 20.0);
+        assert_eq!(layout.span_layout(0, 1), (1.0, 21.0));
+        assert_eq!(layout.max_additional_size, 100.0 - 21.0 - 1.0);
+    }
+
+    #[test]
+    fn visible_parts_skip_hidden_parts() {
+        let mut layout = axis_layout(&[AxisPart::new(None); 3], 100.0);
+        for index in 0..3 {
+            layout.set_size(index, 10.0);
+        }
+
+        assert_eq!(layout.visible_parts(15.0, 20.0), vec![(1, 12.0, 22.0)]);
+
+        let mut hidden = layout.get(1);
+        hidden.set_hidden(true);
+        layout.set(1, hidden);
+        assert_eq!(layout.visible_parts(0.0, 100.0), vec![(0, 1.0, 11.0), (2, 12.0, 22.0)]);
+    }
+
+    #[test]
+    fn span_layout_is_clamped_to_the_axis() {
+        let mut layout = axis_layout(&[AxisPart::new(None); 3], 100.0);
+        for index in 0..3 {
+            layout.set_size(index, 10.0);
+        }
+
+        assert_eq!(layout.span_layout(1, 2), (12.0, 33.0));
+        assert_eq!(layout.span_layout(2, 5), (23.0, 33.0));
+        assert_eq!(layout.span_layout(3, 2), (34.0, 34.0));
+        assert_eq!(layout.span_layout(7, 1), (34.0, 34.0));
+
+        // A hidden last part takes no space
+        let mut hidden = layout.get(2);
+        hidden.set_hidden(true);
+        layout.set(2, hidden);
+        assert_eq!(layout.span_layout(1, 2), (12.0, 22.0));
+        assert_eq!(layout.span_layout(2, 1), (23.0, 23.0));
+        assert_eq!(layout.span_constrains(1, 5), layout.span_constrains(1, 2));
+    }
+
+    #[test]
+    fn transpose_swaps_the_cell_rects() {
+        let mut layout = TableLayout::new(Axis::Vertical);
+        layout.lines_mut().set_length(2, AxisPart::new(None));
+        layout.elements_mut().set_length(3, AxisPart::new(None));
+        layout.prepare_layout(Size::new(1000.0, 1000.0));
+        for line in 0..2 {
+            layout.lines_mut().set_size(line, 20.0);
+        }
+        for element in 0..3 {
+            layout.elements_mut().set_size(element, 10.0);
+        }
+
+        let rect = layout.layout_rect(1, 2);
+        layout.transpose();
+        assert_eq!(layout.line_axis(), Axis::Horizontal);
+        let transposed = layout.layout_rect(1, 2);
+        assert_eq!((transposed.x0, transposed.y0, transposed.x1, transposed.y1), (rect.y0, rect.x0, rect.y1, rect.x1));
+        assert_eq!(layout.cell_at(transposed.center()), Some((1, 2)));
+
+        layout.transpose();
+        assert_eq!(layout.layout_rect(1, 2), rect);
+    }
+
+    #[test]
+    fn span_covers_its_cells() {
+        let span = CellSpan::new(1, 2, 2, 3);
+        assert!(span.covers(1, 2));
+        assert!(span.covers(2, 4));
+        assert!(!span.covers(3, 2));
+        assert!(!span.covers(1, 5));
+
+        let mut layout = TableLayout::new(Axis::Vertical);
+        layout.set_span(span);
+        assert!(!layout.is_covered(1, 2));
+        assert!(layout.is_covered(2, 3));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn apply_restores_serialized_lines_by_key() {
+        let mut stored = TableLayout::new(Axis::Horizontal);
+        let mut hidden = AxisPart::new(None);
+        hidden.set_hidden(true);
+        stored.lines_mut().add_part(AxisPart::new(None).with_fixed(40.0));
+        stored.lines_mut().add_part(AxisPart::new(None).with_min(10.0).with_max(80.0));
+        stored.lines_mut().add_part(hidden);
+        for (index, key) in ["name", "size", "date"].iter().enumerate() {
+            stored.lines_mut().set_key(index, *key);
+        }
+
+        let json = serde_json::to_string(&stored).unwrap();
+        let restored: TableLayout = serde_json::from_str(&json).unwrap();
+
+        let mut layout = TableLayout::new(Axis::Vertical);
+        for key in ["date", "name", "other", "size"].iter() {
+            layout.lines_mut().add_part(AxisPart::new(None));
+            let index = layout.lines().length() - 1;
+            layout.lines_mut().set_key(index, *key);
+        }
+        layout.apply(&restored);
+
+        assert_eq!(layout.line_axis(), Axis::Horizontal);
+        assert!(!layout.lines().get(0).is_hidden());
+        let name = layout.lines().get(1);
+        assert!(name.is_fixed());
+        assert_eq!(name.size(), 40.0);
+        let other = layout.lines().get(2);
+        assert_eq!((other.min(), other.max()), (0.0, f64::INFINITY));
+        let size = layout.lines().get(3);
+        assert!(!size.is_fixed());
+        assert_eq!((size.min(), size.max()), (10.0, 80.0));
+    }
+
+    #[test]
+    fn min_and_max_keep_a_fixed_part() {
+        let part = AxisPart::new(None).with_fixed(80.0).with_min(40.0).with_max(60.0);
+        assert!(part.is_fixed());
+        assert_eq!((part.size(), part.min(), part.max()), (80.0, 80.0, 80.0));
+
+        let mut part = part;
+        part.set_fixed(None);
+        part.set_min(40.0);
+        assert!(!part.is_fixed());
+        assert_eq!((part.min(), part.max()), (40.0, f64::INFINITY));
+    }
+
+    #[test]
+    fn apply_copies_only_the_size_constraints() {
+        let mut stored = AxisLayout::new();
+        let mut part = AxisPart::new(None).with_fixed(40.0).with_padding(5.0);
+        part.set_hidden(true);
+        part.set_expanded(true);
+        stored.add_part(part);
+        stored.set_key(0, "name");
+
+        let mut layout = AxisLayout::new();
+        layout.add_part(AxisPart::new(None));
+        layout.set_key(0, "name");
+        layout.apply(&stored);
+
+        let part = layout.get(0);
+        assert!(part.is_fixed());
+        assert_eq!((part.size(), part.min(), part.max()), (40.0, 40.0, 40.0));
+        assert!(!part.is_hidden());
+        assert!(!part.is_expanded());
+        assert_eq!(part.padding(), 1.0);
+    }
+
+    #[test]
+    fn move_element_remaps_spans_and_cursor() {
+        let mut layout = TableLayout::new(Axis::Vertical);
+        layout.lines_mut().set_length(2, AxisPart::new(None));
+        layout.elements_mut().set_length(4, AxisPart::new(None));
+        layout.elements_mut().set_key(3, "last");
+        layout.set_span(CellSpan::new(0, 0, 1, 2));
+        layout.set_span(CellSpan::new(1, 2, 2, 1));
+        layout.set_cursor(Some((1, 3)));
+        layout.set_selection_anchor(Some((0, 2)));
+
+        layout.move_element(3, 0);
+        assert_eq!(layout.elements().key(0), Some("last"));
+        assert_eq!(layout.spans(), &[CellSpan::new(0, 1, 1, 2), CellSpan::new(1, 3, 2, 1)]);
+        assert_eq!(layout.cursor(), Some((1, 0)));
+        assert_eq!(layout.selection_anchor(), Some((0, 3)));
+
+        // Moving an element out of a span breaks it apart, the span follows its first element
+        layout.move_element(1, 3);
+        assert_eq!(layout.spans()[0], CellSpan::new(0, 3, 1, 2));
+
+        layout.move_element(4, 0);
+        assert_eq!(layout.cursor(), Some((1, 0)));
+    }
+
+    #[test]
+    fn move_line_remaps_spans_and_cursor() {
+        let mut layout = TableLayout::new(Axis::Vertical);
+        layout.lines_mut().set_length(4, AxisPart::new(None));
+        layout.elements_mut().set_length(2, AxisPart::new(None));
+        layout.set_span(CellSpan::new(0, 0, 2, 1));
+        layout.set_span(CellSpan::new(2, 1, 1, 2));
+        layout.set_cursor(Some((3, 1)));
+        layout.set_selection_anchor(Some((2, 0)));
+
+        layout.move_line(3, 0);
+        assert_eq!(layout.spans(), &[CellSpan::new(1, 0, 2, 1), CellSpan::new(3, 1, 1, 2)]);
+        assert_eq!(layout.cursor(), Some((0, 1)));
+        assert_eq!(layout.selection_anchor(), Some((3, 0)));
+
+        // Moving a line out of a span breaks it apart, the span follows its first line
+        layout.move_line(1, 3);
+        assert_eq!(layout.spans()[0], CellSpan::new(3, 0, 2, 1));
+
+        layout.move_line(4, 0);
+        assert_eq!(layout.cursor(), Some((0, 1)));
+    }
+}
@@ -6,11 +6,44 @@ mod head;
 mod util;
 mod controller;
 mod painter;
+mod detail;
+mod tree;
+mod group;
+mod pivot;
+mod reorder;
+mod chooser;
+mod state;
+mod rule;
+mod heatmap;
+mod chart;
+mod format;
+mod edit;
+mod history;
+mod dirty;
+mod fill;
+mod search;
+pub mod commands;
+pub mod theme;
 
 pub use line::{TableLine, WidgetTableLine};
-pub use layout::{TableLayout, AxisLayout, AxisPart, TableAxis};
+pub use layout::{TableLayout, AxisLayout, AxisPart, TableAxis, CellSpan, CellRange};
 pub use policy::{TablePolicy, Static};
 pub use table::{Table};
-pub use head::{HeaderData, HeaderTable, HeaderWidget};
+pub use head::{HeaderBuilder, HeaderData, HeaderTable, HeaderWidget};
 pub use controller::{TableController, DefaultTableController};
-pub use painter::{TablePainter, DefaultTablePainter};
+pub use painter::{TablePainter, DefaultTablePainter, BackgroundPainter, paint_grid};
+pub use detail::{TableDetail, WidgetTableDetail, ExpandToggle};
+pub use tree::{TreeNode, TreeRow, TreeList, TreeLens, TreeCell, TreeTableLine};
+pub use group::{GroupPolicy, GroupLens, GroupedList, GroupRow, GroupHeader, GroupCell, GroupHeaderCell};
+pub use pivot::{PivotPolicy, PivotColumnLens};
+pub use reorder::{ListEdit, ElementDrag};
+pub use rule::FormatRule;
+pub use chart::{CellChart, ChartTableLine, DataBar, Sparkline, ProgressBar};
+pub use format::{CellFormat, FormattedLine, TextFormat, NumberFormat, DateTimeFormat, ByteSizeFormat};
+pub use edit::EditTableLine;
+pub use history::{EditHistory, EditOperation, HistoryStep, UndoController};
+pub use dirty::{ChangeSet, CellChange};
+pub use fill::FillController;
+pub use search::{SearchPattern, ReplaceResult};
+pub use heatmap::{HeatmapPainter, ColorScale, Domain, DomainScope};
+pub use state::{TableViewState, LineState, SortKey, LineFilter, ScrollAnchor};
\ No newline at end of file
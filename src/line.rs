@@ -1,11 +1,17 @@
+use std::any::Any;
+use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
-use druid::{Env, Event, EventCtx, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, UpdateCtx, Widget, WidgetPod, Data};
+use std::ops::{Deref, DerefMut, RangeInclusive};
+use std::rc::Rc;
+use druid::{Env, Event, EventCtx, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, UpdateCtx, Widget, WidgetPod, Data, RenderContext};
 use druid::widget::ListIter;
 use crate::TableLayout;
+use crate::util::move_item;
+use crate::rule::{FormatRule, rule_background, rule_env};
+use crate::search::{parse_value_text, value_text};
 
 pub trait TableLine<T> {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env);
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env, meta: &TableLayout, line_index: usize);
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env);
 
@@ -18,11 +24,84 @@ pub trait TableLine<T> {
     fn arrange(&mut self, ctx: &mut LayoutCtx, data: &T, env: &Env, meta: &TableLayout, line_index: usize);
 
     fn element_count(&self, data: &T) -> usize;
+
+    /// Paints the backgrounds of the cells, which is done for all lines before painting the content.
+    fn paint_background(&mut self, _ctx: &mut PaintCtx, _data: &T, _env: &Env, _meta: &TableLayout, _line_index: usize) {}
+
+    /// Returns true if `set_texts` writes into the cells, e.g. when replacing or pasting text.
+    fn accepts_text(&self) -> bool {
+        false
+    }
+
+    /// Lines which edit their cells at the cursor of the table, which then takes the focus when clicked.
+    fn needs_cursor(&self) -> bool {
+        false
+    }
+
+    /// Called after an element was moved inside the data, widgets with state should follow it.
+    fn move_element(&mut self, _from: usize, _to: usize) {}
+
+    /// Called after an element was inserted into the data, before the next update.
+    fn insert_element(&mut self, _index: usize) {}
+
+    /// Called after an element was removed from the data, before the next update.
+    fn remove_element(&mut self, _index: usize) {}
+
+    /// Returns the elements whose value is invalid together with the message for the user.
+    fn validate(&self, _data: &T) -> Vec<(usize, String)> {
+        vec![]
+    }
+
+    /// Returns the elements whose value differs from the baseline as (element, old value, new value).
+    /// `elements` contains the index in the baseline of every element.
+    fn changes(&self, _baseline: &T, _data: &T, _elements: &[Option<usize>]) -> Vec<(usize, Rc<dyn Any>, Rc<dyn Any>)> {
+        vec![]
+    }
+
+    /// Returns the value of a cell, e.g. to copy it into other cells.
+    fn value(&self, _data: &T, _element: usize) -> Option<Rc<dyn Any>> {
+        None
+    }
+
+    /// Writes the value of a cell, returns false if the line does not support values of this type.
+    fn set_value(&mut self, _data: &mut T, _element: usize, _value: &dyn Any) -> bool {
+        false
+    }
+
+    /// Returns the values of the elements in the range as (element, value).
+    fn values(&self, data: &T, elements: RangeInclusive<usize>) -> Vec<(usize, Rc<dyn Any>)> {
+        elements.filter_map(|element|Some((element, self.value(data, element)?))).collect()
+    }
+
+    /// Writes values given as (element, value), returns the count of written values.
+    /// Lines over lists override it to write all values in one pass.
+    fn set_values(&mut self, data: &mut T, values: &[(usize, Rc<dyn Any>)]) -> usize {
+        values.iter().filter(|(element, value)|self.set_value(data, *element, &**value)).count()
+    }
+
+    /// Compares two values of the line, e.g. to drop writes from the edit history which restored
+    /// the value. Lines without values return false.
+    fn same_value(&self, _a: &dyn Any, _b: &dyn Any) -> bool {
+        false
+    }
+
+    /// Returns the text of the cells as (element, text), which is matched by a search.
+    /// Lines without text are not searched.
+    fn texts(&self, _data: &T) -> Vec<(usize, String)> {
+        vec![]
+    }
+
+    /// Parses texts given as (element, text) and writes the values into the cells, e.g. to replace
+    /// text. Returns the result of every written element, the error is the message for the user.
+    /// Only lines which return true from `accepts_text` write text.
+    fn set_texts(&mut self, _data: &mut T, texts: &[(usize, String)]) -> Vec<(usize, Result<(), String>)> {
+        rejected_texts(texts)
+    }
 }
 
 impl<T: Data> TableLine<T> for Box<dyn TableLine<T>> {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
-        self.deref_mut().event(ctx, event, data, env);
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.deref_mut().event(ctx, event, data, env, meta, line_index);
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
@@ -48,6 +127,66 @@ impl<T: Data> TableLine<T> for Box<dyn TableLine<T>> {
     fn element_count(&self, data: &T) -> usize {
         self.deref().element_count(data)
     }
+
+    fn paint_background(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.deref_mut().paint_background(ctx, data, env, meta, line_index);
+    }
+
+    fn accepts_text(&self) -> bool {
+        self.deref().accepts_text()
+    }
+
+    fn needs_cursor(&self) -> bool {
+        self.deref().needs_cursor()
+    }
+
+    fn move_element(&mut self, from: usize, to: usize) {
+        self.deref_mut().move_element(from, to);
+    }
+
+    fn insert_element(&mut self, index: usize) {
+        self.deref_mut().insert_element(index);
+    }
+
+    fn remove_element(&mut self, index: usize) {
+        self.deref_mut().remove_element(index);
+    }
+
+    fn validate(&self, data: &T) -> Vec<(usize, String)> {
+        self.deref().validate(data)
+    }
+
+    fn changes(&self, baseline: &T, data: &T, elements: &[Option<usize>]) -> Vec<(usize, Rc<dyn Any>, Rc<dyn Any>)> {
+        self.deref().changes(baseline, data, elements)
+    }
+
+    fn value(&self, data: &T, element: usize) -> Option<Rc<dyn Any>> {
+        self.deref().value(data, element)
+    }
+
+    fn set_value(&mut self, data: &mut T, element: usize, value: &dyn Any) -> bool {
+        self.deref_mut().set_value(data, element, value)
+    }
+
+    fn values(&self, data: &T, elements: RangeInclusive<usize>) -> Vec<(usize, Rc<dyn Any>)> {
+        self.deref().values(data, elements)
+    }
+
+    fn set_values(&mut self, data: &mut T, values: &[(usize, Rc<dyn Any>)]) -> usize {
+        self.deref_mut().set_values(data, values)
+    }
+
+    fn same_value(&self, a: &dyn Any, b: &dyn Any) -> bool {
+        self.deref().same_value(a, b)
+    }
+
+    fn texts(&self, data: &T) -> Vec<(usize, String)> {
+        self.deref().texts(data)
+    }
+
+    fn set_texts(&mut self, data: &mut T, texts: &[(usize, String)]) -> Vec<(usize, Result<(), String>)> {
+        self.deref_mut().set_texts(data, texts)
+    }
 }
 
 pub struct WidgetTableLine<
@@ -64,9 +203,188 @@ pub struct WidgetTableLine<
     inner_lens: L2,
     widgets: Vec<WidgetPod<V, W>>,
     generate: Box<dyn Fn() -> WidgetPod<V, W>>,
+    rules: Vec<FormatRule<V>>,
+    /// The environments of the cells changed by rules, computed when the data changes.
+    envs: Vec<Option<Env>>,
+    validators: Vec<Validator<V>>,
+    text: Option<TextFn<V>>,
+    parse: Option<ParseFn<V>>,
+    editable: bool,
     phantom: PhantomData<(S, T, U)>,
 }
 
+/// The environments of the cells changed by rules, empty if the line has no rules.
+pub(crate) fn rule_envs<T: ListIter<U>, U: Data, V: Data>(data: &T, lens: &impl Lens<U, V>, rules: &[FormatRule<V>], env: &Env) -> Vec<Option<Env>> {
+    let mut envs = vec![];
+    if !rules.is_empty() {
+        data.for_each(|data, _|lens.with(data, |data|envs.push(rule_env(rules, data, env))));
+    }
+    envs
+}
+
+pub(crate) fn paint_rule_backgrounds<T: ListIter<U>, U: Data, V: Data>(ctx: &mut PaintCtx, data: &T, lens: &impl Lens<U, V>, rules: &[FormatRule<V>], meta: &TableLayout, line_index: usize) {
+    if rules.is_empty() {
+        return;
+    }
+    data.for_each(|data, index|lens.with(data, |data|{
+        if meta.is_covered(line_index, index) {
+            return;
+        }
+        if let Some(color) = rule_background(rules, data) {
+            ctx.fill(meta.layout_rect(line_index, index), color);
+        }
+    }));
+}
+
+/// The environment of a cell changed by the rules of its line, or the environment of the table.
+pub(crate) fn cell_env<'a>(envs: &'a [Option<Env>], index: usize, env: &'a Env) -> &'a Env {
+    envs.get(index).and_then(Option::as_ref).unwrap_or(env)
+}
+
+/// Checks the value of a cell, the error is the message shown to the user.
+pub(crate) type Validator<V> = Box<dyn Fn(&V) -> Result<(), String>>;
+
+/// Converts a value to the text which is searched.
+pub(crate) type TextFn<V> = Box<dyn Fn(&V) -> String>;
+
+/// Converts the text entered for a cell to a value, the error is the message shown to the user.
+pub(crate) type ParseFn<V> = Box<dyn Fn(&str) -> Result<V, String>>;
+
+pub(crate) fn validate_line<T: ListIter<U>, U: Data, V: Data>(data: &T, lens: &impl Lens<U, V>, validators: &[Validator<V>]) -> Vec<(usize, String)> {
+    let mut invalid = vec![];
+    if validators.is_empty() {
+        return invalid;
+    }
+    data.for_each(|data, index|lens.with(data, |data|{
+        if let Some(Err(message)) = validators.iter().map(|validator|validator(data)).find(Result::is_err) {
+            invalid.push((index, message));
+        }
+    }));
+    invalid
+}
+
+pub(crate) fn line_value<T: ListIter<U>, U: Data, V: Data>(data: &T, lens: &impl Lens<U, V>, element: usize) -> Option<Rc<dyn Any>> {
+    let mut result = None;
+    data.for_each(|data, index|{
+        if index == element {
+            result = Some(lens.with(data, |value|Rc::new(value.clone()) as Rc<dyn Any>));
+        }
+    });
+    result
+}
+
+pub(crate) fn set_line_value<T: ListIter<U>, U: Data, V: Data>(data: &mut T, lens: &impl Lens<U, V>, element: usize, value: &dyn Any) -> bool {
+    let value = match value.downcast_ref::<V>() {
+        Some(value) => value,
+        None => return false,
+    };
+    let mut written = false;
+    data.for_each_mut(|data, index|{
+        if index == element {
+            lens.with_mut(data, |data|*data = value.clone());
+            written = true;
+        }
+    });
+    written
+}
+
+pub(crate) fn line_values<T: ListIter<U>, U: Data, V: Data>(data: &T, lens: &impl Lens<U, V>, elements: RangeInclusive<usize>) -> Vec<(usize, Rc<dyn Any>)> {
+    let mut values = vec![];
+    data.for_each(|data, index|{
+        if elements.contains(&index) {
+            values.push((index, lens.with(data, |value|Rc::new(value.clone()) as Rc<dyn Any>)));
+        }
+    });
+    values
+}
+
+pub(crate) fn set_line_values<T: ListIter<U>, U: Data, V: Data>(data: &mut T, lens: &impl Lens<U, V>, values: &[(usize, Rc<dyn Any>)]) -> usize {
+    let values: HashMap<usize, &V> = values.iter()
+        .filter_map(|(element, value)|Some((*element, value.downcast_ref::<V>()?)))
+        .collect();
+    let mut written = 0;
+    if !values.is_empty() {
+        data.for_each_mut(|data, index|{
+            if let Some(value) = values.get(&index) {
+                lens.with_mut(data, |data|*data = (*value).clone());
+                written += 1;
+            }
+        });
+    }
+    written
+}
+
+/// Returns the text of every value, common types are converted without `text`.
+pub(crate) fn line_texts<T: ListIter<U>, U: Data, V: Data>(data: &T, lens: &impl Lens<U, V>, text: Option<&TextFn<V>>) -> Vec<(usize, String)> {
+    let mut texts = vec![];
+    data.for_each(|data, index|lens.with(data, |value|{
+        let value_text = match text {
+            Some(text) => Some(text(value)),
+            None => value_text(value),
+        };
+        if let Some(value_text) = value_text {
+            texts.push((index, value_text));
+        }
+    }));
+    texts
+}
+
+/// Writes the parsed texts given as (element, text) in one pass, common types are parsed without `parse`.
+pub(crate) fn set_line_texts<T: ListIter<U>, U: Data, V: Data>(data: &mut T, lens: &impl Lens<U, V>, texts: &[(usize, String)], parse: Option<&ParseFn<V>>) -> Vec<(usize, Result<(), String>)> {
+    let mut texts: HashMap<usize, &str> = texts.iter().map(|(element, text)|(*element, text.as_str())).collect();
+    let mut results = vec![];
+    if !texts.is_empty() {
+        data.for_each_mut(|data, index|{
+            let text = match texts.remove(&index) {
+                Some(text) => text,
+                None => return,
+            };
+            lens.with_mut(data, |value|{
+                let parsed = match parse {
+                    Some(parse) => parse(text),
+                    None => match parse_value_text(&*value, text) {
+                        Some(parsed) => parsed.and_then(|parsed|parsed.downcast::<V>()
+                            .map(|parsed|*parsed)
+                            .map_err(|_|"the value can not be entered as text".to_string())),
+                        None => Err("the value can not be entered as text".to_string()),
+                    },
+                };
+                results.push((index, parsed.map(|parsed|*value = parsed)));
+            });
+        });
+    }
+    results.extend(texts.into_iter().map(|(element, _)|(element, Err(format!("element {} does not exist", element)))));
+    results
+}
+
+/// The results of a line which does not accept text, see `TableLine::set_texts`.
+pub(crate) fn rejected_texts(texts: &[(usize, String)]) -> Vec<(usize, Result<(), String>)> {
+    texts.iter().map(|(element, _)|(*element, Err("the line does not accept text".to_string()))).collect()
+}
+
+/// Compares two values of a line by `Data::same`, values of another type are never the same.
+pub(crate) fn same_line_value<V: Data>(a: &dyn Any, b: &dyn Any) -> bool {
+    match (a.downcast_ref::<V>(), b.downcast_ref::<V>()) {
+        (Some(a), Some(b)) => a.same(b),
+        _ => false,
+    }
+}
+
+/// Compares the values of a line with the baseline by `Data::same`.
+pub(crate) fn diff_line<T: ListIter<U>, U: Data, V: Data>(baseline: &T, data: &T, lens: &impl Lens<U, V>, elements: &[Option<usize>]) -> Vec<(usize, Rc<dyn Any>, Rc<dyn Any>)> {
+    let mut old_values = vec![];
+    baseline.for_each(|element, _|old_values.push(lens.with(element, |value|value.clone())));
+
+    let mut changes = vec![];
+    data.for_each(|element, index|lens.with(element, |value|{
+        let old = elements.get(index).copied().flatten().and_then(|index|old_values.get(index));
+        if let Some(old) = old.filter(|old|!old.same(value)) {
+            changes.push((index, Rc::new(old.clone()) as Rc<dyn Any>, Rc::new(value.clone()) as Rc<dyn Any>));
+        }
+    }));
+    changes
+}
+
 impl<
     S: Data,
     T: ListIter<U> + Data,
@@ -83,10 +401,51 @@ impl<
             inner_lens,
             widgets: vec![],
             generate: Box::new(move||WidgetPod::new(generate())),
+            rules: vec![],
+            envs: vec![],
+            validators: vec![],
+            text: None,
+            parse: None,
+            editable: false,
             phantom: Default::default()
         }
     }
 
+    /// Adds a conditional formatting rule for the cells of this line.
+    pub fn with_rule(mut self, rule: FormatRule<V>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Adds a check of the values, invalid cells are marked by the table.
+    pub fn with_validator(mut self, validator: impl Fn(&V) -> Result<(), String> + 'static) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    /// Sets the text of the values which is matched by a search, e.g. the formatted value.
+    pub fn with_text(mut self, text: impl Fn(&V) -> String + 'static) -> Self {
+        self.text = Some(Box::new(text));
+        self
+    }
+
+    /// Sets how text is converted to values, e.g. when replacing text in the cells.
+    pub fn with_parser(mut self, parse: impl Fn(&str) -> Result<V, String> + 'static) -> Self {
+        self.parse = Some(Box::new(parse));
+        self
+    }
+
+    /// Lets the table write text into the cells, e.g. when replacing or pasting text.
+    /// The widgets edit their values regardless of this.
+    pub fn with_editable(mut self, editable: bool) -> Self {
+        self.editable = editable;
+        self
+    }
+
+    pub(crate) fn outer_lens(&self) -> &L1 {
+        &self.outer_lens
+    }
+
     fn update_widget_count(&mut self, data: &S) -> bool {
         let Self {outer_lens, widgets, generate, ..} = self;
         outer_lens.with(data, |data| {
@@ -102,6 +461,11 @@ impl<
 
         })
     }
+
+    fn update_envs(&mut self, data: &S, env: &Env) {
+        let Self {outer_lens, inner_lens, rules, envs, ..} = self;
+        *envs = outer_lens.with(data, |data|rule_envs(data, inner_lens, rules, env));
+    }
 }
 
 impl<
@@ -114,29 +478,35 @@ impl<
     L2: Lens<U, V>,
     W: Widget<V> + 'static,
 > TableLine<S> for WidgetTableLine<S, T, U, V, L1, L2, W> {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut S, env: &Env) {
-        let Self {outer_lens, inner_lens, widgets, ..} = self;
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut S, env: &Env, meta: &TableLayout, line_index: usize) {
+        let Self {outer_lens, inner_lens, widgets, envs, ..} = self;
         outer_lens.with_mut(data, |data|data.for_each_mut(|data, index|inner_lens.with_mut(data, |data|{
-            widgets[index].event(ctx, event, data, env);
+            // The length can change during the event, the widgets are synchronized during update
+            if let Some(widget) = widgets.get_mut(index).filter(|_|!meta.is_covered(line_index, index)) {
+                widget.event(ctx, event, data, cell_env(envs, index, env));
+            }
         })));
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &S, env: &Env) {
         if let LifeCycle::WidgetAdded = event {
             self.update_widget_count(data);
+            self.update_envs(data, env);
         }
 
-        let Self {outer_lens, inner_lens, widgets, ..} = self;
+        let Self {outer_lens, inner_lens, widgets, envs, ..} = self;
         outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
-            widgets[index].lifecycle(ctx, event, data, env);
+            widgets[index].lifecycle(ctx, event, data, cell_env(envs, index, env));
         })));
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, data: &S, env: &Env) {
-        let Self {outer_lens, inner_lens, widgets, ..} = self;
+        self.update_envs(data, env);
+
+        let Self {outer_lens, inner_lens, widgets, envs, ..} = self;
         outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
             if let Some(widget) = widgets.get_mut(index) {
-                widget.update(ctx, data, env);
+                widget.update(ctx, data, cell_env(envs, index, env));
             }
         })));
 
@@ -145,18 +515,23 @@ impl<
         }
     }
 
-    fn paint(&mut self, ctx: &mut PaintCtx, data: &S, env: &Env, _: &TableLayout, _: usize) {
-        let Self {outer_lens, inner_lens, widgets, ..} = self;
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        let Self {outer_lens, inner_lens, widgets, envs, ..} = self;
         outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
-            widgets[index].paint(ctx, data, env);
+            if !meta.is_covered(line_index, index) {
+                widgets[index].paint(ctx, data, cell_env(envs, index, env));
+            }
         })));
     }
 
     fn layout(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &mut TableLayout, line_index: usize) {
-        let Self {outer_lens, inner_lens, widgets, ..} = self;
+        let Self {outer_lens, inner_lens, widgets, envs, ..} = self;
         outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
+            if meta.is_covered(line_index, index) {
+                return;
+            }
             meta.layout(line_index, index, |bc|{
-                widgets[index].layout(ctx, bc, data, env)
+                widgets[index].layout(ctx, bc, data, cell_env(envs, index, env))
             });
 
             //TODO: align to baseline
@@ -164,16 +539,98 @@ impl<
     }
 
     fn arrange(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
-        let Self {outer_lens, inner_lens, widgets, ..} = self;
+        let Self {outer_lens, inner_lens, widgets, envs, ..} = self;
         outer_lens.with(data, |data|data.for_each(|data, index|inner_lens.with(data, |data|{
-            widgets[index].set_origin(ctx, data, env, meta.layout_rect(line_index, index).origin());
+            if meta.is_covered(line_index, index) {
+                return;
+            }
+            widgets[index].set_origin(ctx, data, cell_env(envs, index, env), meta.content_rect(line_index, index).origin());
 
             //TODO: set paint insets
         })));
     }
 
+    fn paint_background(&mut self, ctx: &mut PaintCtx, data: &S, _: &Env, meta: &TableLayout, line_index: usize) {
+        let Self {outer_lens, inner_lens, rules, ..} = self;
+        outer_lens.with(data, |data|paint_rule_backgrounds(ctx, data, inner_lens, rules, meta, line_index));
+    }
+
     fn element_count(&self, data: &S) -> usize {
         self.outer_lens.with(data, |data|data.data_len())
     }
+
+    fn move_element(&mut self, from: usize, to: usize) {
+        move_item(&mut self.widgets, from, to);
+        move_item(&mut self.envs, from, to);
+    }
+
+    fn insert_element(&mut self, index: usize) {
+        if index <= self.widgets.len() {
+            self.widgets.insert(index, (self.generate)());
+        }
+        if index <= self.envs.len() && !self.rules.is_empty() {
+            self.envs.insert(index, None);
+        }
+    }
+
+    fn remove_element(&mut self, index: usize) {
+        if index < self.widgets.len() {
+            self.widgets.remove(index);
+        }
+        if index < self.envs.len() {
+            self.envs.remove(index);
+        }
+    }
+
+    fn validate(&self, data: &S) -> Vec<(usize, String)> {
+        let Self {outer_lens, inner_lens, validators, ..} = self;
+        outer_lens.with(data, |data|validate_line(data, inner_lens, validators))
+    }
+
+    fn changes(&self, baseline: &S, data: &S, elements: &[Option<usize>]) -> Vec<(usize, Rc<dyn Any>, Rc<dyn Any>)> {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with(baseline, |baseline|outer_lens.with(data, |data|diff_line(baseline, data, inner_lens, elements)))
+    }
+
+    fn same_value(&self, a: &dyn Any, b: &dyn Any) -> bool {
+        same_line_value::<V>(a, b)
+    }
+
+    fn value(&self, data: &S, element: usize) -> Option<Rc<dyn Any>> {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with(data, |data|line_value(data, inner_lens, element))
+    }
+
+    fn set_value(&mut self, data: &mut S, element: usize, value: &dyn Any) -> bool {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with_mut(data, |data|set_line_value(data, inner_lens, element, value))
+    }
+
+    fn values(&self, data: &S, elements: RangeInclusive<usize>) -> Vec<(usize, Rc<dyn Any>)> {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with(data, |data|line_values(data, inner_lens, elements))
+    }
+
+    fn set_values(&mut self, data: &mut S, values: &[(usize, Rc<dyn Any>)]) -> usize {
+        let Self {outer_lens, inner_lens, ..} = self;
+        outer_lens.with_mut(data, |data|set_line_values(data, inner_lens, values))
+    }
+
+    fn texts(&self, data: &S) -> Vec<(usize, String)> {
+        let Self {outer_lens, inner_lens, text, ..} = self;
+        outer_lens.with(data, |data|line_texts(data, inner_lens, text.as_ref()))
+    }
+
+    fn set_texts(&mut self, data: &mut S, texts: &[(usize, String)]) -> Vec<(usize, Result<(), String>)> {
+        if !self.editable {
+            return rejected_texts(texts);
+        }
+        let Self {outer_lens, inner_lens, parse, ..} = self;
+        outer_lens.with_mut(data, |data|set_line_texts(data, inner_lens, texts, parse.as_ref()))
+    }
+
+    fn accepts_text(&self) -> bool {
+        self.editable
+    }
 }
 
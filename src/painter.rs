@@ -1,9 +1,10 @@
-use druid::{Env, PaintCtx, Data, RenderContext};
+use std::collections::HashSet;
+use druid::{Color, Env, PaintCtx, Data, Lens, RenderContext, Rect};
 use druid::kurbo::Line;
-use druid::theme::BORDER_LIGHT;
-use druid::widget::Axis;
+use druid::widget::ListIter;
 use crate::table::TableContent;
 use crate::TableLayout;
+use crate::theme;
 
 pub trait TablePainter<T> {
     fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env, content: &mut TableContent<T>, layout: &TableLayout);
@@ -16,31 +17,204 @@ impl<T: Data> TablePainter<T> for DefaultTablePainter {
         content.paint_background(ctx, data, env, layout);
         content.paint_foreground(ctx, data, env, layout);
 
-        let size = ctx.size();
-        let brush = env.get(BORDER_LIGHT);
+        paint_grid(ctx, env, layout);
+    }
+}
+
+/// Paints striped elements and custom backgrounds of lines and elements below the content.
+pub struct BackgroundPainter<T> {
+    stripes: bool,
+    line_backgrounds: Vec<Box<dyn Fn(usize, &Env) -> Option<Color>>>,
+    element_backgrounds: Vec<Box<dyn Fn(&T, &Env, &mut dyn FnMut(usize, Color))>>,
+}
+
+impl<T: Data> BackgroundPainter<T> {
+    pub fn new() -> Self {
+        Self {
+            stripes: false,
+            line_backgrounds: vec![],
+            element_backgrounds: vec![],
+        }
+    }
+
+    /// Paints every second element with `theme::ALTERNATE_ROW_COLOR`.
+    pub fn with_stripes(mut self, stripes: bool) -> Self {
+        self.stripes = stripes;
+        self
+    }
+
+    /// Adds a background by the index of the line.
+    pub fn with_line_background(mut self, background: impl Fn(usize, &Env) -> Option<Color> + 'static) -> Self {
+        self.line_backgrounds.push(Box::new(background));
+        self
+    }
+
+    /// Adds a background by the value of the element, e.g. to color rows by their status.
+    pub fn with_element_background<
+        T2: ListIter<U>,
+        U: Data,
+
+        L: Lens<T, T2> + 'static,
+    >(mut self, lens: L, background: impl Fn(&U, usize, &Env) -> Option<Color> + 'static) -> Self {
+        self.element_backgrounds.push(Box::new(move|data, env, paint|{
+            lens.with(data, |data|data.for_each(|element, index|{
+                if let Some(color) = background(element, index, env) {
+                    paint(index, color);
+                }
+            }));
+        }));
+        self
+    }
 
-        let mut advance_x = 0.5;
-        let mut iter_x = layout.direction_axis(Axis::Vertical).parts().iter();
-        loop {
-            ctx.stroke(Line::new((advance_x, 0.5), (advance_x, size.height + 0.5)), &brush, 1.0);
+    fn paint_backgrounds(&self, ctx: &mut PaintCtx, data: &T, env: &Env, layout: &TableLayout) {
+        let visible = ctx.region().bounding_box();
+        for (rect, color) in self.backgrounds(data, env, layout) {
+            if rect.intersect(visible).area() > 0.0 {
+                ctx.fill(rect, &color);
+            }
+        }
+    }
 
-            if let Some(part) = iter_x.next() {
-                advance_x += part.advance();
-            } else {
-                break;
+    /// The backgrounds in the order in which they are painted.
+    fn backgrounds(&self, data: &T, env: &Env, layout: &TableLayout) -> Vec<(Rect, Color)> {
+        let mut backgrounds = vec![];
+        if self.stripes {
+            let color = theme::alternate_row_color(env);
+            for element in (1..layout.elements().length()).step_by(2) {
+                backgrounds.push((layout.element_rect(element), color.clone()));
             }
         }
+        for background in &self.line_backgrounds {
+            for line in 0..layout.lines().length() {
+                if let Some(color) = background(line, env).filter(|_|!layout.is_line_hidden(line)) {
+                    backgrounds.push((layout.line_rect(line), color));
+                }
+            }
+        }
+        for background in &self.element_backgrounds {
+            background(data, env, &mut |element, color|{
+                if element < layout.elements().length() {
+                    backgrounds.push((layout.element_rect(element), color));
+                }
+            });
+        }
+        backgrounds
+    }
+}
+
+impl<T: Data> Default for BackgroundPainter<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Data> TablePainter<T> for BackgroundPainter<T> {
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env, content: &mut TableContent<T>, layout: &TableLayout) {
+        self.paint_backgrounds(ctx, data, env, layout);
+
+        content.paint_background(ctx, data, env, layout);
+        content.paint_foreground(ctx, data, env, layout);
+
+        paint_grid(ctx, env, layout);
+    }
+}
 
-        let mut advance_y = 0.5;
-        let mut iter_y = layout.direction_axis(Axis::Horizontal).parts().iter();
-        loop {
-            ctx.stroke(Line::new((0.5, advance_y), (size.width + 0.5, advance_y)), &brush, 1.0);
+/// Strokes the grid lines between the cells, the color and width are taken from the table theme.
+pub fn paint_grid(ctx: &mut PaintCtx, env: &Env, layout: &TableLayout) {
+    let size = layout.table_size();
+    let brush = theme::grid_color(env);
+    let width = theme::grid_width(env);
+    let half = width / 2.0;
+
+    ctx.stroke(Line::new((half, half), (half, size.height + half)), &brush, width);
+    ctx.stroke(Line::new((half, half), (size.width + half, half)), &brush, width);
+
+    // Every cell draws its right and bottom border, this way no line crosses a span
+    let visible = ctx.region().bounding_box().inflate(width, width);
+    let axis = layout.line_axis();
+    let (l0, l1) = axis.minor_span(visible);
+    let (e0, e1) = axis.major_span(visible);
+    let lines = layout.lines().visible_parts(l0, l1);
+    let elements = layout.elements().visible_parts(e0, e1);
+
+    // Spans draw their border once, the cells they cover are skipped
+    let mut spanned = HashSet::new();
+    for span in layout.spans() {
+        let outside = span.line >= layout.lines().length() || span.element >= layout.elements().length();
+        if outside || layout.is_line_hidden(span.line) {
+            continue;
+        }
+        let rect = layout.layout_rect(span.line, span.element);
+        if rect.intersect(visible).area() <= 0.0 {
+            continue;
+        }
+        stroke_border(ctx, rect, &brush, width);
+        let (span_lines, span_elements) = layout.cell_extent(span.line, span.element);
+        for line in lines.iter().map(|line|line.0).filter(|line|(span.line..span.line + span_lines).contains(line)) {
+            for element in elements.iter().map(|element|element.0).filter(|element|(span.element..span.element + span_elements).contains(element)) {
+                spanned.insert((line, element));
+            }
+        }
+    }
 
-            if let Some(part) = iter_y.next() {
-                advance_y += part.advance();
-            } else {
-                break;
+    for &(line, line_start, line_end) in &lines {
+        for &(element, element_start, element_end) in &elements {
+            if !spanned.contains(&(line, element)) {
+                let rect = Rect::from_points(axis.pack(element_start, line_start), axis.pack(element_end, line_end));
+                stroke_border(ctx, rect, &brush, width);
             }
         }
     }
-}
\ No newline at end of file
+    for (element, part) in layout.elements().parts().iter().enumerate() {
+        if part.detail_size() > 0.0 {
+            stroke_border(ctx, layout.detail_rect(element), &brush, width);
+        }
+    }
+}
+
+fn stroke_border(ctx: &mut PaintCtx, rect: Rect, brush: &Color, width: f64) {
+    let half = width / 2.0;
+    ctx.stroke(Line::new((rect.x1 + half, rect.y0 - width), (rect.x1 + half, rect.y1 + width)), brush, width);
+    ctx.stroke(Line::new((rect.x0 - width, rect.y1 + half), (rect.x1 + width, rect.y1 + half)), brush, width);
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::im::Vector;
+    use druid::lens::Identity;
+    use druid::widget::Axis;
+    use crate::AxisPart;
+    use super::*;
+
+    #[test]
+    fn backgrounds_are_painted_in_order() {
+        let mut layout = TableLayout::new(Axis::Vertical);
+        layout.lines_mut().set_length(3, AxisPart::new(None).with_fixed(50.0));
+        layout.elements_mut().set_length(4, AxisPart::new(None).with_fixed(20.0));
+        let mut hidden = layout.lines().get(2);
+        hidden.set_hidden(true);
+        layout.lines_mut().set(2, hidden);
+
+        let env = Env::default().adding(theme::ALTERNATE_ROW_COLOR, Color::WHITE);
+        let data: Vector<i32> = vec![3, -1, 4, -5].into();
+        let painter = BackgroundPainter::new()
+            .with_stripes(true)
+            .with_line_background(|_, _|Some(Color::BLACK))
+            .with_element_background(Identity, |value: &i32, _, _|Some(Color::rgb8(255, 0, 0)).filter(|_|*value < 0));
+
+        let backgrounds: Vec<(Rect, u32)> = painter.backgrounds(&data, &env, &layout)
+            .into_iter()
+            .map(|(rect, color)|(rect, color.as_rgba_u32()))
+            .collect();
+        let (white, black, red) = (Color::WHITE.as_rgba_u32(), Color::BLACK.as_rgba_u32(), Color::rgb8(255, 0, 0).as_rgba_u32());
+        assert_eq!(backgrounds, vec![
+            (layout.element_rect(1), white),
+            (layout.element_rect(3), white),
+            // The hidden line gets no background
+            (layout.line_rect(0), black),
+            (layout.line_rect(1), black),
+            (layout.element_rect(1), red),
+            (layout.element_rect(3), red),
+        ]);
+    }
+}
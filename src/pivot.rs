@@ -0,0 +1,274 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::rc::Rc;
+use druid::{Lens, Widget, WidgetPod, Data};
+use druid::im::Vector;
+use druid::lens::Identity;
+use druid::widget::{Axis, Label};
+use crate::{AxisPart, HeaderBuilder, HeaderData, HeaderTable, TableLayout, TableLine, TablePolicy, WidgetTableLine};
+use crate::util::set_len;
+
+/// Pivots a list of records into a table with one element per row key and one line per column key.
+///
+/// The lines are generated from the data, every cell shows the aggregate of all records with the
+/// same row and column key. Each line has its column key as key, which keeps its position and
+/// configuration when the columns change. The cells are computed and can not be edited.
+pub struct PivotPolicy<R, V, L, W> {
+    lens: L,
+    row_key: Box<dyn Fn(&R) -> String>,
+    column_key: Box<dyn Fn(&R) -> String>,
+    aggregate: Box<dyn Fn(&[R]) -> V>,
+    widget: Rc<dyn Fn() -> W>,
+    state: Rc<RefCell<PivotState<V>>>,
+    computed: bool,
+}
+
+pub struct PivotState<V> {
+    rows: Vec<String>,
+    columns: Vec<String>,
+    values: BTreeMap<String, Vector<V>>,
+    /// The column of the line at each position, which changes when lines are moved.
+    line_columns: Vec<String>,
+}
+
+/// Read-only lens to the computed cells of a single column of a pivot table, the column is
+/// identified by its key. Changes made through the lens are discarded.
+pub struct PivotColumnLens<V> {
+    state: Rc<RefCell<PivotState<V>>>,
+    column: String,
+}
+
+impl<R: Data, V: Data, L, W: Widget<V> + 'static> PivotPolicy<R, V, L, W> {
+    pub fn new(
+        lens: L,
+        row_key: impl Fn(&R) -> String + 'static,
+        column_key: impl Fn(&R) -> String + 'static,
+        aggregate: impl Fn(&[R]) -> V + 'static,
+        widget: impl Fn() -> W + 'static,
+    ) -> Self {
+        Self {
+            lens,
+            row_key: Box::new(row_key),
+            column_key: Box::new(column_key),
+            aggregate: Box::new(aggregate),
+            widget: Rc::new(widget),
+            state: Rc::new(RefCell::new(PivotState {
+                rows: vec![],
+                columns: vec![],
+                values: BTreeMap::new(),
+                line_columns: vec![],
+            })),
+            computed: false,
+        }
+    }
+
+    pub fn rows(&self) -> Vec<String> {
+        self.state.borrow().rows.clone()
+    }
+
+    pub fn columns(&self) -> Vec<String> {
+        self.state.borrow().columns.clone()
+    }
+
+    /// Builds the line headers of a `HeaderTable`, showing the column key of the line at their position.
+    pub fn line_headers<S: Data>(&self) -> HeaderBuilder<S> {
+        let state = self.state.clone();
+        Box::new(move|_, _, length, list|{
+            set_len(list, length, ||{
+                let state = state.clone();
+                let label: Box<dyn Widget<HeaderData<S>>> = Box::new(Label::dynamic(move|data: &HeaderData<S>, _|{
+                    state.borrow().line_columns.get(data.index()).cloned().unwrap_or_default()
+                }));
+                WidgetPod::new(label)
+            });
+        })
+    }
+
+    /// Builds the element headers of a `HeaderTable`, showing the row keys.
+    pub fn element_header<S: Data>(&self) -> impl Fn() -> Box<dyn Widget<HeaderData<S>>> + 'static {
+        let state = self.state.clone();
+        move|| -> Box<dyn Widget<HeaderData<S>>> {
+            let state = state.clone();
+            Box::new(Label::dynamic(move|data: &HeaderData<S>, _|{
+                state.borrow().rows.get(data.index()).cloned().unwrap_or_default()
+            }))
+        }
+    }
+
+    fn compute(&self, records: &Vector<R>) -> PivotState<V> {
+        let mut rows = BTreeSet::new();
+        let mut columns = BTreeSet::new();
+        let mut cells: BTreeMap<(String, String), Vec<R>> = BTreeMap::new();
+
+        for record in records.iter() {
+            let row = (self.row_key)(record);
+            let column = (self.column_key)(record);
+            rows.insert(row.clone());
+            columns.insert(column.clone());
+            cells.entry((row, column)).or_default().push(record.clone());
+        }
+
+        let rows = rows.into_iter().collect::<Vec<_>>();
+        let columns = columns.into_iter().collect::<Vec<_>>();
+        let values = columns.iter().map(|column|{
+            let values = rows.iter().map(|row|{
+                let records = cells.get(&(row.clone(), column.clone())).map_or(&[] as &[R], Vec::as_slice);
+                (self.aggregate)(records)
+            }).collect();
+            (column.clone(), values)
+        }).collect();
+
+        PivotState {
+            rows,
+            columns,
+            values,
+            line_columns: vec![],
+        }
+    }
+}
+
+impl<V> PivotState<V> {
+    fn update_line_columns(&mut self, meta: &TableLayout) {
+        self.line_columns = (0..meta.lines().length())
+            .map(|line|meta.lines().key(line).unwrap_or_default().to_string())
+            .collect();
+    }
+}
+
+impl<
+    S: Data,
+    R: Data,
+    V: Data,
+    L: Lens<S, Vector<R>>,
+    W: Widget<V> + 'static,
+> TablePolicy<S> for PivotPolicy<R, V, L, W> {
+    fn update(&mut self, old_data: &S, data: &S, widgets: &mut Vec<Box<dyn TableLine<S>>>, meta: &mut TableLayout) {
+        if self.computed && old_data.same(data) {
+            return;
+        }
+        self.computed = true;
+
+        let state = self.lens.with(data, |records|self.compute(records));
+        let columns = state.columns.clone();
+        *self.state.borrow_mut() = state;
+
+        // Lines are matched with the columns by their key, they keep their position and
+        // configuration and pick up the new values through their lens
+        let existing: HashSet<&str> = columns.iter().map(String::as_str).collect();
+        for line in (0..widgets.len()).rev() {
+            if !meta.lines().key(line).map_or(false, |key|existing.contains(key)) {
+                widgets.remove(line);
+                meta.lines_mut().remove_part(line);
+            }
+        }
+        for column in &columns {
+            if meta.lines().index_of(column).is_some() {
+                continue;
+            }
+            let generate = self.widget.clone();
+            let lens = PivotColumnLens {
+                state: self.state.clone(),
+                column: column.clone(),
+            };
+            widgets.push(Box::new(WidgetTableLine::new(lens, Identity, move||generate())));
+            meta.lines_mut().add_part(AxisPart::new(None));
+            let index = meta.lines().length() - 1;
+            meta.lines_mut().set_key(index, column.clone());
+        }
+        self.state.borrow_mut().update_line_columns(meta);
+    }
+
+    fn lines_changed(&mut self, _: &[Box<dyn TableLine<S>>], meta: &mut TableLayout) {
+        self.state.borrow_mut().update_line_columns(meta);
+    }
+}
+
+impl<S, V: Data> Lens<S, Vector<V>> for PivotColumnLens<V> {
+    fn with<A, F: FnOnce(&Vector<V>) -> A>(&self, _: &S, f: F) -> A {
+        let column = self.state.borrow().values.get(&self.column).cloned().unwrap_or_default();
+        f(&column)
+    }
+
+    fn with_mut<A, F: FnOnce(&mut Vector<V>) -> A>(&self, _: &mut S, f: F) -> A {
+        // The cells are computed from the records, changes are discarded
+        let mut column = self.state.borrow().values.get(&self.column).cloned().unwrap_or_default();
+        f(&mut column)
+    }
+}
+
+impl<
+    S: Data,
+    R: Data,
+    V: Data,
+    L: Lens<S, Vector<R>> + 'static,
+    W: Widget<V> + 'static,
+> HeaderTable<S, PivotPolicy<R, V, L, W>> {
+    pub fn new_pivot(axis: Axis, pivot: PivotPolicy<R, V, L, W>, line_header_width: f64, element_header_width: f64) -> Self {
+        let line_headers = pivot.line_headers();
+        let element_header = pivot.element_header();
+
+        Self::new_dynamic(axis, pivot, line_headers, line_header_width)
+            .with_element_header(element_header, element_header_width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::move_item;
+    use super::*;
+
+    type Record = (u32, u32, f64);
+
+    fn pivot() -> PivotPolicy<Record, f64, Identity, Label<f64>> {
+        PivotPolicy::new(
+            Identity,
+            |record: &Record|format!("row {}", record.0),
+            |record: &Record|format!("col {}", record.1),
+            |records: &[Record]|records.iter().map(|record|record.2).sum(),
+            ||Label::new(""),
+        )
+    }
+
+    fn column_texts(widgets: &[Box<dyn TableLine<Vector<Record>>>], data: &Vector<Record>) -> Vec<Vec<String>> {
+        widgets.iter()
+            .map(|line|line.texts(data).into_iter().map(|(_, text)|text).collect())
+            .collect()
+    }
+
+    #[test]
+    fn cells_aggregate_the_records_of_their_row_and_column() {
+        let records: Vector<Record> = vec![(2, 1, 1.0), (1, 1, 2.0), (1, 2, 3.0), (1, 1, 4.0)].into();
+        let state = pivot().compute(&records);
+
+        assert_eq!(state.rows, vec!["row 1", "row 2"]);
+        assert_eq!(state.columns, vec!["col 1", "col 2"]);
+        assert_eq!(state.values["col 1"], Vector::from(vec![6.0, 1.0]));
+        // Cells without records get the aggregate of no records
+        assert_eq!(state.values["col 2"], Vector::from(vec![3.0, 0.0]));
+    }
+
+    #[test]
+    fn lines_follow_their_column_key() {
+        let mut policy = pivot();
+        let mut widgets: Vec<Box<dyn TableLine<Vector<Record>>>> = vec![];
+        let mut layout = TableLayout::new(Axis::Vertical);
+
+        let data: Vector<Record> = vec![(1, 1, 1.0), (1, 2, 2.0), (1, 3, 3.0)].into();
+        policy.update(&data, &data, &mut widgets, &mut layout);
+        assert_eq!(column_texts(&widgets, &data), vec![vec!["1"], vec!["2"], vec!["3"]]);
+        assert_eq!(layout.lines().key(2), Some("col 3"));
+
+        // The user moves "col 3" to the front
+        move_item(&mut widgets, 2, 0);
+        layout.move_line(2, 0);
+        policy.lines_changed(&widgets, &mut layout);
+        assert_eq!(policy.state.borrow().line_columns, vec!["col 3", "col 1", "col 2"]);
+
+        // "col 1" disappears and "col 4" is added at the end, the other lines keep their position
+        let new_data: Vector<Record> = vec![(1, 2, 2.0), (1, 3, 3.0), (1, 4, 4.0)].into();
+        policy.update(&data, &new_data, &mut widgets, &mut layout);
+        assert_eq!(column_texts(&widgets, &new_data), vec![vec!["3"], vec!["2"], vec!["4"]]);
+        assert_eq!(policy.state.borrow().line_columns, vec!["col 3", "col 2", "col 4"]);
+        assert_eq!(layout.lines().length(), 3);
+    }
+}
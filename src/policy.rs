@@ -3,6 +3,10 @@ use crate::{TableLine, TableLayout};
 
 pub trait TablePolicy<T> {
     fn update(&mut self, old_data: &T, data: &T, widgets: &mut Vec<Box<dyn TableLine<T>>>, meta: &mut TableLayout);
+
+    /// Called after lines were moved, hidden or shown without changing the data,
+    /// e.g. to place spans on the visible lines.
+    fn lines_changed(&mut self, _widgets: &[Box<dyn TableLine<T>>], _meta: &mut TableLayout) {}
 }
 
 pub struct Static;
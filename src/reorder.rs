@@ -0,0 +1,154 @@
+use std::any::Any;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+use druid::{Command, Data, EventCtx, Lens, Point, WidgetId};
+use druid::im::Vector;
+use druid::widget::ListIter;
+use crate::{Table, TablePolicy};
+use crate::commands::{ELEMENT_MOVED, LINE_ORDER_CHANGED, MOVE_ELEMENT, MOVE_LINE, SET_LINE_ORDER};
+
+/// Structural changes of a list, used to move elements of a table inside its data.
+pub trait ListEdit<U>: ListIter<U> {
+    fn remove(&mut self, index: usize) -> U;
+
+    fn insert(&mut self, index: usize, value: U);
+
+    fn move_element(&mut self, from: usize, to: usize) {
+        let value = self.remove(from);
+        self.insert(to, value);
+    }
+}
+
+/// An element dragged out of a table, the payload of `DROP_ELEMENT`.
+pub struct ElementDrag {
+    pub(crate) source: WidgetId,
+    pub(crate) index: usize,
+    pub(crate) value: Rc<dyn Any>,
+    pub(crate) window_pos: Point,
+}
+
+/// Type erased access to the elements of a table.
+pub(crate) trait ElementSource<T> {
+    fn move_element(&self, data: &mut T, from: usize, to: usize);
+
+    fn get(&self, data: &T, index: usize) -> Option<Rc<dyn Any>>;
+
+    fn remove(&self, data: &mut T, index: usize);
+
+    /// Inserts the value if it has the element type of this source.
+    fn insert(&self, data: &mut T, index: usize, value: &dyn Any) -> bool;
+}
+
+pub(crate) struct LensSource<L, T2, U> {
+    lens: L,
+    phantom: PhantomData<(T2, U)>,
+}
+
+impl<U: Data> ListEdit<U> for Vector<U> {
+    fn remove(&mut self, index: usize) -> U {
+        Vector::remove(self, index)
+    }
+
+    fn insert(&mut self, index: usize, value: U) {
+        Vector::insert(self, index, value);
+    }
+}
+
+impl<U: Data> ListEdit<U> for Arc<Vec<U>> {
+    fn remove(&mut self, index: usize) -> U {
+        Arc::make_mut(self).remove(index)
+    }
+
+    fn insert(&mut self, index: usize, value: U) {
+        Arc::make_mut(self).insert(index, value);
+    }
+}
+
+impl ElementDrag {
+    /// The id of the `HeaderTable` the element was dragged from.
+    pub fn source(&self) -> WidgetId {
+        self.source
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn value<U: 'static>(&self) -> Option<&U> {
+        self.value.downcast_ref()
+    }
+
+    pub fn window_pos(&self) -> Point {
+        self.window_pos
+    }
+}
+
+impl<L, T2, U> LensSource<L, T2, U> {
+    pub fn new(lens: L) -> Self {
+        Self {
+            lens,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, T2: ListEdit<U>, U: Data, L: Lens<T, T2>> ElementSource<T> for LensSource<L, T2, U> {
+    fn move_element(&self, data: &mut T, from: usize, to: usize) {
+        self.lens.with_mut(data, |list|list.move_element(from, to));
+    }
+
+    fn get(&self, data: &T, index: usize) -> Option<Rc<dyn Any>> {
+        self.lens.with(data, |list|{
+            let mut value = None;
+            list.for_each(|element, element_index|{
+                if element_index == index {
+                    value = Some(element.clone());
+                }
+            });
+            value.map(|value|Rc::new(value) as Rc<dyn Any>)
+        })
+    }
+
+    fn remove(&self, data: &mut T, index: usize) {
+        self.lens.with_mut(data, |list|{
+            list.remove(index);
+        });
+    }
+
+    fn insert(&self, data: &mut T, index: usize, value: &dyn Any) -> bool {
+        match value.downcast_ref::<U>() {
+            Some(value) => {
+                self.lens.with_mut(data, |list|{
+                    let index = index.min(list.data_len());
+                    list.insert(index, value.clone());
+                });
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: Data, P: TablePolicy<T>> Table<T, P> {
+    /// Handles the commands which move lines and elements, returns false for other commands.
+    pub(crate) fn reorder_command(&mut self, ctx: &mut EventCtx, command: &Command, data: &mut T) -> bool {
+        if let Some((from, to)) = command.get(MOVE_LINE) {
+            self.move_line(*from, *to);
+            ctx.submit_notification(LINE_ORDER_CHANGED.with(self.line_order.clone()));
+        } else if let Some(order) = command.get(SET_LINE_ORDER) {
+            self.set_line_order(order);
+            ctx.submit_notification(LINE_ORDER_CHANGED.with(self.line_order.clone()));
+        } else if let Some((from, to)) = command.get(MOVE_ELEMENT) {
+            if !self.move_element(data, *from, *to) {
+                return true;
+            }
+            // A HeaderTable around this table moves its element headers
+            ctx.submit_notification(ELEMENT_MOVED.with((*from, *to)));
+        } else {
+            return false;
+        }
+        ctx.request_layout();
+        true
+    }
+}
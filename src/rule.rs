@@ -0,0 +1,106 @@
+use druid::{Color, Env, FontDescriptor};
+use druid::theme;
+
+/// Styles the cells of a line whose value matches a predicate.
+///
+/// The text color and font are applied to the cell widget through its `Env`,
+/// the background is painted below the cell. If several rules match, later rules win.
+pub struct FormatRule<V> {
+    predicate: Box<dyn Fn(&V) -> bool>,
+    background: Option<Color>,
+    text_color: Option<Color>,
+    font: Option<FontDescriptor>,
+}
+
+impl<V> FormatRule<V> {
+    pub fn new(predicate: impl Fn(&V) -> bool + 'static) -> Self {
+        Self {
+            predicate: Box::new(predicate),
+            background: None,
+            text_color: None,
+            font: None,
+        }
+    }
+
+    pub fn with_background(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    pub fn with_text_color(mut self, color: Color) -> Self {
+        self.text_color = Some(color);
+        self
+    }
+
+    pub fn with_font(mut self, font: FontDescriptor) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    pub fn matches(&self, value: &V) -> bool {
+        (self.predicate)(value)
+    }
+}
+
+/// Returns the environment of a cell, `None` if no rule changes it.
+pub(crate) fn rule_env<V>(rules: &[FormatRule<V>], value: &V, env: &Env) -> Option<Env> {
+    let mut result: Option<Env> = None;
+    for rule in rules.iter().filter(|rule|rule.text_color.is_some() || rule.font.is_some()) {
+        if !rule.matches(value) {
+            continue;
+        }
+        let env = result.get_or_insert_with(||env.clone());
+        if let Some(color) = &rule.text_color {
+            env.set(theme::TEXT_COLOR, color.clone());
+        }
+        if let Some(font) = &rule.font {
+            env.set(theme::UI_FONT, font.clone());
+        }
+    }
+    result
+}
+
+pub(crate) fn rule_background<'a, V>(rules: &'a [FormatRule<V>], value: &V) -> Option<&'a Color> {
+    rules.iter()
+        .filter(|rule|rule.background.is_some() && rule.matches(value))
+        .filter_map(|rule|rule.background.as_ref())
+        .last()
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::FontFamily;
+    use super::*;
+
+    fn rules() -> Vec<FormatRule<i32>> {
+        vec![
+            FormatRule::new(|value|*value < 0).with_text_color(Color::rgb8(255, 0, 0)),
+            FormatRule::new(|value|*value > 100).with_background(Color::rgb8(255, 255, 0)),
+            FormatRule::new(|value|*value > 1000)
+                .with_background(Color::rgb8(255, 128, 0))
+                .with_font(FontDescriptor::new(FontFamily::MONOSPACE)),
+        ]
+    }
+
+    #[test]
+    fn later_rules_win() {
+        let rules = rules();
+        assert!(rule_background(&rules, &50).is_none());
+        assert_eq!(rule_background(&rules, &500).map(Color::as_rgba_u32), Some(Color::rgb8(255, 255, 0).as_rgba_u32()));
+        assert_eq!(rule_background(&rules, &5000).map(Color::as_rgba_u32), Some(Color::rgb8(255, 128, 0).as_rgba_u32()));
+    }
+
+    #[test]
+    fn env_is_only_changed_by_matching_rules() {
+        let rules = rules();
+        let env = Env::default();
+        assert!(rule_env(&rules, &50, &env).is_none());
+        // A matching background alone does not change the environment
+        assert!(rule_env(&rules, &500, &env).is_none());
+
+        let negative = rule_env(&rules, &-1, &env).unwrap();
+        assert_eq!(negative.get(theme::TEXT_COLOR).as_rgba_u32(), Color::rgb8(255, 0, 0).as_rgba_u32());
+        let large = rule_env(&rules, &5000, &env).unwrap();
+        assert_eq!(large.get(theme::UI_FONT).family, FontFamily::MONOSPACE);
+    }
+}
@@ -0,0 +1,509 @@
+use std::any::Any;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+use druid::{BoxConstraints, Command, Env, Event, EventCtx, KbKey, KeyEvent, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect, Size, UpdateCtx, Vec2, WidgetPod, RenderContext, Data};
+use druid::piet::{FontFamily, Text, TextLayout, TextLayoutBuilder};
+use druid::widget::TextBox;
+use crate::{Table, TableLayout, TableLine, TablePolicy};
+use crate::commands::{FIND, FIND_NEXT, FIND_PREVIOUS, REPLACE, REPLACE_ALL, REPLACED};
+use crate::theme;
+use crate::util::moved_index;
+
+/// What to search for in the text of the cells, see `Table::search`.
+#[derive(Clone)]
+pub struct SearchPattern {
+    kind: PatternKind,
+}
+
+#[derive(Clone)]
+enum PatternKind {
+    Text {
+        text: String,
+        /// The lowercase text for case insensitive searches.
+        folded: Option<String>,
+    },
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+/// The matches of a search, ordered by element and line.
+pub(crate) struct TableSearch {
+    pattern: SearchPattern,
+    matches: Vec<(usize, usize)>,
+    current: Option<usize>,
+}
+
+impl SearchPattern {
+    /// Matches cells which contain the text, ignoring the case.
+    pub fn text(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            kind: PatternKind::Text {
+                folded: Some(text.to_lowercase()),
+                text,
+            },
+        }
+    }
+
+    /// Matches cells which contain the text with the same case.
+    pub fn exact(text: impl Into<String>) -> Self {
+        Self {
+            kind: PatternKind::Text {
+                text: text.into(),
+                folded: None,
+            },
+        }
+    }
+
+    /// Matches cells which contain a match of the regular expression.
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            kind: PatternKind::Regex(regex::Regex::new(pattern)?),
+        })
+    }
+
+    /// An empty text matches nothing.
+    pub fn is_empty(&self) -> bool {
+        match &self.kind {
+            PatternKind::Text {text, ..} => text.is_empty(),
+            #[cfg(feature = "regex")]
+            PatternKind::Regex(regex) => regex.as_str().is_empty(),
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match &self.kind {
+            PatternKind::Text {text: pattern, ..} if pattern.is_empty() => false,
+            PatternKind::Text {folded: Some(folded), ..} => text.to_lowercase().contains(folded.as_str()),
+            PatternKind::Text {text: pattern, folded: None} => text.contains(pattern.as_str()),
+            #[cfg(feature = "regex")]
+            PatternKind::Regex(regex) => !regex.as_str().is_empty() && regex.is_match(text),
+        }
+    }
+
+    /// Replaces all matches inside the text. A regular expression can refer to its groups
+    /// in the replacement, e.g. `$1`.
+    pub fn replace_all(&self, text: &str, replacement: &str) -> String {
+        match &self.kind {
+            PatternKind::Text {text: pattern, ..} if pattern.is_empty() => text.to_string(),
+            PatternKind::Text {text: pattern, folded: Some(folded)} => {
+                let length = pattern.chars().count();
+                let mut result = String::new();
+                let mut rest = text;
+                while let Some(next) = rest.chars().next() {
+                    let end = rest.char_indices().nth(length).map_or(rest.len(), |(index, _)|index);
+                    if rest[..end].to_lowercase() == *folded {
+                        result.push_str(replacement);
+                        rest = &rest[end..];
+                    } else {
+                        result.push(next);
+                        rest = &rest[next.len_utf8()..];
+                    }
+                }
+                result
+            }
+            PatternKind::Text {text: pattern, folded: None} => text.replace(pattern.as_str(), replacement),
+            #[cfg(feature = "regex")]
+            PatternKind::Regex(regex) if regex.as_str().is_empty() => text.to_string(),
+            #[cfg(feature = "regex")]
+            PatternKind::Regex(regex) => regex.replace_all(text, replacement).into_owned(),
+        }
+    }
+}
+
+/// The outcome of replacing text in the cells, see `Table::replace_all`.
+#[derive(Clone, Default)]
+pub struct ReplaceResult {
+    replaced: Vec<(usize, usize)>,
+    failed: Vec<(usize, usize, String)>,
+}
+
+impl ReplaceResult {
+    /// The cells which got a new value as (line, element).
+    pub fn replaced(&self) -> &[(usize, usize)] {
+        &self.replaced
+    }
+
+    /// The cells whose line could not parse the new text as (line, element, message).
+    pub fn failed(&self) -> &[(usize, usize, String)] {
+        &self.failed
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    pub(crate) fn add_replaced(&mut self, line: usize, element: usize) {
+        self.replaced.push((line, element));
+    }
+
+    pub(crate) fn add_failed(&mut self, line: usize, element: usize, message: String) {
+        self.failed.push((line, element, message));
+    }
+}
+
+/// The text of common value types, used by lines without an explicit conversion.
+pub(crate) fn value_text(value: &dyn Any) -> Option<String> {
+    value.downcast_ref::<String>().cloned()
+        .or_else(||value.downcast_ref::<Arc<String>>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<Arc<str>>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<Rc<String>>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<&'static str>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<char>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<bool>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<f64>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<f32>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<i64>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<i32>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<u64>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<u32>().map(|value|value.to_string()))
+        .or_else(||value.downcast_ref::<usize>().map(|value|value.to_string()))
+}
+
+/// Parses the text into a value of the type of `template`, `None` for unknown types.
+pub(crate) fn parse_value_text(template: &dyn Any, text: &str) -> Option<Result<Box<dyn Any>, String>> {
+    fn parse<V: FromStr + 'static>(text: &str, expected: &str) -> Result<Box<dyn Any>, String> {
+        text.trim().parse::<V>()
+            .map(|value|Box::new(value) as Box<dyn Any>)
+            .map_err(|_|format!("\"{}\" is not {}", text.trim(), expected))
+    }
+
+    let value = if template.is::<String>() {
+        Ok(Box::new(text.to_string()) as Box<dyn Any>)
+    } else if template.is::<Arc<String>>() {
+        Ok(Box::new(Arc::new(text.to_string())) as Box<dyn Any>)
+    } else if template.is::<Arc<str>>() {
+        Ok(Box::new(Arc::<str>::from(text)) as Box<dyn Any>)
+    } else if template.is::<Rc<String>>() {
+        Ok(Box::new(Rc::new(text.to_string())) as Box<dyn Any>)
+    } else if template.is::<char>() {
+        parse::<char>(text, "a single character")
+    } else if template.is::<bool>() {
+        parse::<bool>(text, "true or false")
+    } else if template.is::<f64>() {
+        parse::<f64>(text, "a number")
+    } else if template.is::<f32>() {
+        parse::<f32>(text, "a number")
+    } else if template.is::<i64>() {
+        parse::<i64>(text, "a whole number")
+    } else if template.is::<i32>() {
+        parse::<i32>(text, "a whole number")
+    } else if template.is::<u64>() {
+        parse::<u64>(text, "a positive whole number")
+    } else if template.is::<u32>() {
+        parse::<u32>(text, "a positive whole number")
+    } else if template.is::<usize>() {
+        parse::<usize>(text, "a positive whole number")
+    } else {
+        return None;
+    };
+    Some(value)
+}
+
+impl TableSearch {
+    pub fn new(pattern: SearchPattern) -> Self {
+        Self {
+            pattern,
+            matches: vec![],
+            current: None,
+        }
+    }
+
+    pub fn pattern(&self) -> &SearchPattern {
+        &self.pattern
+    }
+
+    pub fn matches(&self) -> &[(usize, usize)] {
+        &self.matches
+    }
+
+    /// The index of the current match inside `matches`.
+    pub fn current_index(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn current_match(&self) -> Option<(usize, usize)> {
+        self.current.map(|index|self.matches[index])
+    }
+
+    /// Matches the text of the data, which also finds cells without a widget.
+    /// The current match stays at the same cell or the next one.
+    pub fn find<T: Data>(&mut self, lines: &[Box<dyn TableLine<T>>], data: &T, layout: &TableLayout) {
+        let current = self.current_match();
+
+        self.matches.clear();
+        if !self.pattern.is_empty() {
+            for (line, table_line) in lines.iter().enumerate() {
+                if layout.is_line_hidden(line) {
+                    continue;
+                }
+                for (element, text) in table_line.texts(data) {
+                    if !layout.is_covered(line, element) && self.pattern.is_match(&text) {
+                        self.matches.push((line, element));
+                    }
+                }
+            }
+        }
+        self.matches.sort_by_key(|(line, element)|(*element, *line));
+
+        self.current = match current {
+            Some(cell) => self.first_from(cell),
+            None => None,
+        };
+    }
+
+    /// Keeps the matches on their element after the element at `from` was moved to `to`,
+    /// the current match stays at the same cell.
+    pub fn move_element(&mut self, from: usize, to: usize) {
+        let current = self.current_match().map(|(line, element)|(line, moved_index(element, from, to)));
+        for (_, element) in &mut self.matches {
+            *element = moved_index(*element, from, to);
+        }
+        self.matches.sort_by_key(|(line, element)|(*element, *line));
+        self.current = current.and_then(|current|self.matches.iter().position(|cell|*cell == current));
+    }
+
+    /// Makes the first match at or after the cell the current one.
+    pub fn select_from(&mut self, (line, element): (usize, usize)) -> Option<(usize, usize)> {
+        self.current = self.first_from((line, element));
+        self.current_match()
+    }
+
+    fn first_from(&self, (line, element): (usize, usize)) -> Option<usize> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let index = self.matches.iter()
+            .position(|(match_line, match_element)|(*match_element, *match_line) >= (element, line))
+            .unwrap_or(0);
+        Some(index)
+    }
+
+    /// Moves to the next or previous match and wraps around at the ends.
+    pub fn step(&mut self, forward: bool) -> Option<(usize, usize)> {
+        let count = self.matches.len();
+        if count == 0 {
+            return None;
+        }
+        self.current = Some(match self.current {
+            Some(index) if forward => (index + 1) % count,
+            Some(index) => (index + count - 1) % count,
+            None if forward => 0,
+            None => count - 1,
+        });
+        self.current_match()
+    }
+
+    pub fn paint(&self, ctx: &mut PaintCtx, env: &Env, layout: &TableLayout) {
+        let lines = layout.lines().length();
+        let elements = layout.elements().length();
+        let match_color = theme::match_color(env);
+        let current = self.current_match();
+        for cell in &self.matches {
+            let (line, element) = *cell;
+            if line >= lines || element >= elements || Some(*cell) == current {
+                continue;
+            }
+            ctx.fill(layout.layout_rect(line, element), &match_color);
+        }
+        if let Some((line, element)) = current.filter(|(line, element)|*line < lines && *element < elements) {
+            ctx.fill(layout.layout_rect(line, element), &theme::current_match_color(env));
+        }
+    }
+}
+
+/// What the search bar asks the table to do.
+pub(crate) enum SearchAction {
+    Search(SearchPattern),
+    Next,
+    Previous,
+    Clear,
+}
+
+impl<T: Data, P: TablePolicy<T>> Table<T, P> {
+    /// Handles the commands to search and replace, returns false for other commands.
+    pub(crate) fn search_command(&mut self, ctx: &mut EventCtx, command: &Command, data: &mut T) -> bool {
+        if let Some(pattern) = command.get(FIND) {
+            self.search(data, pattern.clone());
+        } else if let Some(replacement) = command.get(REPLACE) {
+            let result = self.replace_current(data, replacement);
+            ctx.submit_notification(REPLACED.with(result));
+        } else if let Some((replacement, in_selection)) = command.get(REPLACE_ALL) {
+            let result = self.replace_all(data, replacement, *in_selection);
+            ctx.submit_notification(REPLACED.with(result));
+        } else if command.is(FIND_NEXT) {
+            self.next_match();
+        } else if command.is(FIND_PREVIOUS) {
+            self.previous_match();
+        } else {
+            return false;
+        }
+        ctx.request_paint();
+        true
+    }
+
+    /// F3 and Shift+F3 move to the next and previous match while the table has the focus.
+    pub(crate) fn search_key(&mut self, ctx: &mut EventCtx, key: &KeyEvent) -> bool {
+        if key.key != KbKey::F3 || self.search.is_none() || !ctx.has_focus() {
+            return false;
+        }
+        if key.mods.shift() {
+            self.previous_match();
+        } else {
+            self.next_match();
+        }
+        ctx.request_paint();
+        true
+    }
+}
+
+/// A text input below a `HeaderTable`, see `HeaderTable::with_search_bar`.
+pub(crate) struct SearchBar {
+    text: String,
+    input: WidgetPod<String, TextBox<String>>,
+    rect: Rect,
+}
+
+const SEARCH_BAR_PADDING: f64 = 4.0;
+const SEARCH_INPUT_WIDTH: f64 = 200.0;
+
+impl SearchBar {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            input: WidgetPod::new(TextBox::new().with_placeholder("Find")),
+            rect: Rect::ZERO,
+        }
+    }
+
+    pub fn event(&mut self, ctx: &mut EventCtx, event: &Event, env: &Env) -> Option<SearchAction> {
+        if let Event::KeyDown(key) = event {
+            if (key.mods.ctrl() || key.mods.meta()) && matches!(&key.key, KbKey::Character(c) if c.eq_ignore_ascii_case("f")) {
+                ctx.set_focus(self.input.id());
+                ctx.set_handled();
+                return None;
+            }
+            if self.input.has_focus() {
+                match &key.key {
+                    KbKey::Enter | KbKey::F3 => {
+                        ctx.set_handled();
+                        return Some(if key.mods.shift() {SearchAction::Previous} else {SearchAction::Next});
+                    }
+                    KbKey::Escape => {
+                        self.text.clear();
+                        ctx.request_update();
+                        ctx.set_handled();
+                        return Some(SearchAction::Clear);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let old_text = self.text.clone();
+        self.input.event(ctx, event, &mut self.text, env);
+        if self.text != old_text {
+            ctx.request_update();
+            Some(SearchAction::Search(SearchPattern::text(self.text.clone())))
+        } else {
+            None
+        }
+    }
+
+    pub fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, env: &Env) {
+        self.input.lifecycle(ctx, event, &self.text, env);
+    }
+
+    pub fn update(&mut self, ctx: &mut UpdateCtx, env: &Env) {
+        self.input.update(ctx, &self.text, env);
+    }
+
+    /// Lays out the input inside the given width and returns the height of the bar.
+    pub fn layout(&mut self, ctx: &mut LayoutCtx, width: f64, env: &Env) -> f64 {
+        let input_width = SEARCH_INPUT_WIDTH.min((width - 2.0 * SEARCH_BAR_PADDING).max(0.0));
+        let bc = BoxConstraints::new(Size::new(input_width, 0.0), Size::new(input_width, f64::INFINITY));
+        let size = self.input.layout(ctx, &bc, &self.text, env);
+        size.height + 2.0 * SEARCH_BAR_PADDING
+    }
+
+    /// Places the bar at the given rect, which has the height returned by `layout`.
+    pub fn set_rect(&mut self, ctx: &mut LayoutCtx, rect: Rect, env: &Env) {
+        self.rect = rect;
+        self.input.set_origin(ctx, &self.text, env, rect.origin() + Vec2::new(SEARCH_BAR_PADDING, SEARCH_BAR_PADDING));
+    }
+
+    /// Paints the bar with the position of the current match, e.g. "2 of 5".
+    pub fn paint(&mut self, ctx: &mut PaintCtx, env: &Env, search: Option<&TableSearch>) {
+        let background = theme::header_background(env);
+        if theme::is_visible(&background) {
+            ctx.fill(self.rect, &background);
+        }
+        self.input.paint(ctx, &self.text, env);
+
+        let status = match search.filter(|search|!search.pattern().is_empty()) {
+            Some(search) if search.matches().is_empty() => "No matches".to_string(),
+            Some(search) => match search.current_index() {
+                Some(index) => format!("{} of {}", index + 1, search.matches().len()),
+                None => format!("{} matches", search.matches().len()),
+            },
+            None => return,
+        };
+        let text = ctx.text()
+            .new_text_layout(status)
+            .font(FontFamily::SYSTEM_UI, env.get(druid::theme::TEXT_SIZE_NORMAL))
+            .text_color(env.get(druid::theme::TEXT_COLOR))
+            .build();
+        if let Ok(text) = text {
+            let input = self.input.layout_rect();
+            let origin = Point::new(input.x1 + 2.0 * SEARCH_BAR_PADDING, input.center().y - text.size().height / 2.0);
+            ctx.draw_text(&text, origin);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_text_ignoring_case() {
+        let pattern = SearchPattern::text("ab");
+        assert_eq!(pattern.replace_all("Ab cab AB", "x"), "x cx x");
+        assert_eq!(pattern.replace_all("aab", "b"), "ab");
+        assert_eq!(pattern.replace_all("äab ä", "-"), "ä- ä");
+        assert_eq!(pattern.replace_all("none", "x"), "none");
+    }
+
+    #[test]
+    fn replace_exact_text() {
+        let pattern = SearchPattern::exact("ab");
+        assert_eq!(pattern.replace_all("Ab cab AB", "x"), "Ab cx AB");
+    }
+
+    #[test]
+    fn matches_follow_a_moved_element() {
+        let mut search = TableSearch::new(SearchPattern::text("a"));
+        search.matches = vec![(0, 0), (1, 1), (0, 2)];
+        search.current = Some(1);
+
+        search.move_element(2, 0);
+        assert_eq!(search.matches(), &[(0, 0), (0, 1), (1, 2)]);
+        assert_eq!(search.current_match(), Some((1, 2)));
+    }
+
+    #[test]
+    fn empty_pattern_replaces_nothing() {
+        assert_eq!(SearchPattern::text("").replace_all("abc", "x"), "abc");
+        assert_eq!(SearchPattern::exact("").replace_all("abc", "x"), "abc");
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn replace_regex_with_groups() {
+        let pattern = SearchPattern::regex(r"(\d+)-(\d+)").unwrap();
+        assert_eq!(pattern.replace_all("1-2 and 30-40", "$2-$1"), "2-1 and 40-30");
+        assert_eq!(SearchPattern::regex("").unwrap().replace_all("abc", "x"), "abc");
+    }
+}
@@ -0,0 +1,178 @@
+use druid::{Command, Data};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::{Table, TablePolicy};
+use crate::commands::SET_SORT_FILTER;
+
+/// A snapshot of the user adjustable view of a table, which can be stored and applied again.
+///
+/// Lines are identified by the index in which they were added to the table.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TableViewState {
+    pub line_order: Vec<usize>,
+    pub lines: Vec<LineState>,
+    pub sort_keys: Vec<SortKey>,
+    pub filters: Vec<LineFilter>,
+    /// Only filled by `HeaderTable`, which owns the scroll container of the table.
+    pub scroll: Option<ScrollAnchor>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineState {
+    pub size: f64,
+    pub fixed: bool,
+    pub hidden: bool,
+}
+
+/// A line by which the elements are sorted, see `Table::with_sort_filter`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SortKey {
+    pub line: usize,
+    pub ascending: bool,
+}
+
+/// A text which the values of a line are filtered by, see `Table::with_sort_filter`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineFilter {
+    pub line: usize,
+    pub text: String,
+}
+
+/// The sort keys and filters of a table and the hook which applies them to the data.
+pub(crate) struct SortFilter<T> {
+    hook: Box<dyn Fn(&mut T, &[SortKey], &[LineFilter])>,
+    pub(crate) sort_keys: Vec<SortKey>,
+    pub(crate) filters: Vec<LineFilter>,
+    pending: bool,
+}
+
+impl<T> SortFilter<T> {
+    pub fn new(hook: impl Fn(&mut T, &[SortKey], &[LineFilter]) + 'static) -> Self {
+        Self {
+            hook: Box::new(hook),
+            sort_keys: vec![],
+            filters: vec![],
+            pending: false,
+        }
+    }
+
+    /// The keys and filters are passed to the hook by the next call of `apply`.
+    pub fn set(&mut self, sort_keys: Vec<SortKey>, filters: Vec<LineFilter>) {
+        self.sort_keys = sort_keys;
+        self.filters = filters;
+        self.pending = true;
+    }
+
+    /// Calls the hook if the keys or filters changed since the last call, returns whether it did.
+    pub fn apply(&mut self, data: &mut T) -> bool {
+        if std::mem::take(&mut self.pending) {
+            (self.hook)(data, &self.sort_keys, &self.filters);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<T: Data, P: TablePolicy<T>> Table<T, P> {
+    /// Handles `SET_SORT_FILTER`, returns false for other commands.
+    pub(crate) fn sort_filter_command(&mut self, command: &Command, data: &mut T) -> bool {
+        match command.get(SET_SORT_FILTER) {
+            Some((sort_keys, filters)) => {
+                self.set_sort_filter(sort_keys.clone(), filters.clone());
+                self.apply_sort_filter(data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Passes changed sort keys and filters to the hook, see `Table::with_sort_filter`.
+    pub(crate) fn apply_sort_filter(&mut self, data: &mut T) {
+        if let Some(sort_filter) = &mut self.sort_filter {
+            sort_filter.apply(data);
+        }
+    }
+}
+
+/// The cell at the origin of the view and the offset inside of it, see `TableLayout::as_cell_offset`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ScrollAnchor {
+    pub offset: (f64, f64),
+    pub line: u64,
+    pub element: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use druid::{Data, Lens};
+    use druid::im::Vector;
+    use druid::lens::Identity;
+    use druid::widget::{Axis, Label};
+    use crate::Table;
+    use super::*;
+
+    #[derive(Clone, Data, Lens)]
+    struct Row {
+        a: u32,
+        b: u32,
+        c: u32,
+    }
+
+    fn table() -> Table<Vector<Row>, crate::Static> {
+        Table::new_static(Axis::Vertical)
+            .with_line(Identity, Row::a, ||Label::dynamic(|value: &u32, _|value.to_string()))
+            .with_line(Identity, Row::b, ||Label::dynamic(|value: &u32, _|value.to_string()))
+            .with_line(Identity, Row::c, ||Label::dynamic(|value: &u32, _|value.to_string()))
+    }
+
+    #[test]
+    fn applied_state_is_returned_again() {
+        let mut table = table().with_sort_filter(|_, _, _|());
+        table.set_line_order(&[2, 0, 1]);
+        {
+            let mut layout = table.layout_mut();
+            let mut part = layout.lines().get(0);
+            part.set_fixed(Some(80.0));
+            layout.lines_mut().set(0, part);
+        }
+        table.set_line_visible(2, false);
+        table.set_sort_filter(
+            vec![SortKey { line: 1, ascending: false }],
+            vec![LineFilter { line: 0, text: "4".to_string() }],
+        );
+
+        let state = table.view_state();
+        assert_eq!(state.line_order, vec![2, 0, 1]);
+        assert_eq!(state.lines[2], LineState { size: 80.0, fixed: true, hidden: false });
+        assert!(state.lines[1].hidden);
+
+        let mut restored = table().with_sort_filter(|_, _, _|());
+        restored.apply_view_state(&state);
+        assert_eq!(restored.view_state(), state);
+        assert!(!restored.is_line_visible(2));
+        assert!(restored.layout().lines().get(0).is_fixed());
+    }
+
+    #[test]
+    fn hook_is_called_once_per_change() {
+        let calls = Rc::new(RefCell::new(vec![]));
+        let recorded = calls.clone();
+        let mut sort_filter = SortFilter::new(move |_: &mut (), keys: &[SortKey], filters: &[LineFilter]|{
+            recorded.borrow_mut().push((keys.to_vec(), filters.to_vec()));
+        });
+
+        assert!(!sort_filter.apply(&mut ()));
+        sort_filter.set(vec![SortKey { line: 0, ascending: true }], vec![]);
+        assert!(sort_filter.apply(&mut ()));
+        assert!(!sort_filter.apply(&mut ()));
+        assert_eq!(*calls.borrow(), vec![(vec![SortKey { line: 0, ascending: true }], vec![])]);
+    }
+}
@@ -1,30 +1,99 @@
-use std::cell::{RefCell, RefMut};
-use std::ops::Deref;
+use std::any::Any;
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::{Deref, RangeInclusive};
 use std::rc::Rc;
-use druid::{BoxConstraints, Env, Event, EventCtx, LayoutCtx, Data, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Size, UpdateCtx, Widget};
+use druid::{Application, BoxConstraints, Command, Env, Event, EventCtx, KbKey, KeyEvent, LayoutCtx, Data, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect, Size, UpdateCtx, Widget, RenderContext};
+use druid::kurbo::BezPath;
+use druid::piet::{FontFamily, Text, TextLayout, TextLayoutBuilder};
+use druid::im::Vector;
+use druid::lens::Identity;
 use druid::widget::{Axis, ListIter};
 use crate::{Static, TableLine, TableLayout, TablePolicy, WidgetTableLine, DefaultTableController, DefaultTablePainter};
 use crate::controller::TableController;
-use crate::layout::AxisPart;
+use crate::commands::{PASTE, MOVE_CURSOR, SET_SELECTION, SET_LINE_VISIBLE, TRANSPOSE};
+use crate::layout::{AxisPart, CellRange, CellSpan, TableAxis};
 use crate::painter::TablePainter;
+use crate::detail::{TableDetail, WidgetTableDetail};
+use crate::tree::{TreeNode, TreeTableLine};
+use crate::group::{GroupCell, GroupLens};
+use crate::util::{move_item, moved_index, order_moves};
+use crate::theme;
+use crate::chart::{CellChart, ChartTableLine};
+use crate::format::{CellFormat, FormattedLine};
+use crate::edit::EditTableLine;
+use crate::history::{EditHistory, EditOperation, UndoController};
+use crate::fill::FillController;
+use crate::dirty::{CellChange, ChangeSet, ChangeTracker, DirtyCells};
+use crate::search::{ReplaceResult, SearchPattern, TableSearch};
+use crate::reorder::{ElementSource, ListEdit, LensSource};
+use crate::state::{LineFilter, LineState, SortFilter, SortKey, TableViewState};
 
 pub struct Table<T, P: TablePolicy<T>> {
     pub(crate) layout: Rc<RefCell<TableLayout>>,
     pub(crate) policy: P,
     pub(crate) lines: Vec<Box<dyn TableLine<T>>>,
+    pub(crate) line_order: Vec<usize>,
     pub(crate) controller: Box<dyn TableController<T>>,
     pub(crate) painter: Box<dyn TablePainter<T>>,
+    pub(crate) detail: Option<Box<dyn TableDetail<T>>>,
+    pub(crate) element_source: Option<Box<dyn ElementSource<T>>>,
+    pub(crate) history: Option<Rc<RefCell<EditHistory>>>,
+    pub(crate) change_tracker: Option<ChangeTracker<T>>,
+    /// The cells which differ from the baseline, compared when the data or the baseline changes.
+    dirty: Option<DirtyCells>,
+    fill_handle: bool,
+    pub(crate) search: Option<TableSearch>,
+    /// The invalid elements of every line with their messages, validated when the data changes.
+    invalid: Vec<Vec<(usize, String)>>,
+    /// Widgets were inserted or removed outside of update, e.g. by `insert_element`.
+    children_changed: bool,
+    pub(crate) sort_filter: Option<SortFilter<T>>,
 }
 
 
 pub struct TableContent<'a, T> {
     lines: &'a mut [Box<dyn TableLine<T>>],
+    line_order: &'a [usize],
+    detail: Option<&'a mut Box<dyn TableDetail<T>>>,
+    dirty: Option<&'a DirtyCells>,
+    search: Option<&'a TableSearch>,
+    invalid: &'a [Vec<(usize, String)>],
+    history: Option<&'a RefCell<EditHistory>>,
 }
 
 impl<T: Data> Table<T, Static> {
     pub fn new_static(axis: Axis) -> Self {
         Self::new_dynamic(Static, axis)
     }
+}
+
+impl<T: Data, P: TablePolicy<T>> Table<T, P> {
+    pub fn new_dynamic(policy: P, axis: Axis) -> Self {
+        Self::new(policy, Rc::new(RefCell::new(TableLayout::new(axis))))
+    }
+
+    pub(crate) fn new(policy: P, layout: Rc<RefCell<TableLayout>>) -> Self {
+        Self {
+            layout,
+            policy,
+            lines: vec![],
+            line_order: vec![],
+            controller: Box::new(DefaultTableController),
+            painter: Box::new(DefaultTablePainter),
+            detail: None,
+            element_source: None,
+            history: None,
+            change_tracker: None,
+            dirty: None,
+            fill_handle: false,
+            search: None,
+            invalid: vec![],
+            children_changed: false,
+            sort_filter: None,
+        }
+    }
 
     pub fn with_line<
         T2: ListIter<U> + Data,
@@ -39,32 +108,824 @@ impl<T: Data> Table<T, Static> {
         self.with_custom_line(WidgetTableLine::new(outer_lens, inner_lens, widget))
     }
 
+    /// Adds a line with the given size constraints and padding.
+    pub fn with_line_part<
+        T2: ListIter<U> + Data,
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, T2> + 'static,
+        L2: Lens<U, V> + 'static,
+        W: Widget<V> + 'static,
+        F: Fn() -> W + 'static,
+    >(mut self, outer_lens: L1, inner_lens: L2, widget: F, part: AxisPart) -> Self {
+        self.add_line_part(WidgetTableLine::new(outer_lens, inner_lens, widget), part);
+        self
+    }
+
+    /// Adds a line which paints a chart in every cell, see `CellChart`.
+    pub fn with_chart_line<
+        T2: ListIter<U> + Data,
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, T2> + 'static,
+        L2: Lens<U, V> + 'static,
+        C: CellChart<V> + 'static,
+    >(self, outer_lens: L1, inner_lens: L2, chart: C) -> Self {
+        self.with_custom_line(ChartTableLine::new(outer_lens, inner_lens, chart))
+    }
+
+    /// Adds a line which shows its values as text formatted by `format`.
+    pub fn with_formatted_line<
+        T2: ListIter<U> + Data,
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, T2> + 'static,
+        L2: Lens<U, V> + 'static,
+    >(self, outer_lens: L1, inner_lens: L2, format: impl CellFormat<V> + 'static) -> Self {
+        self.with_custom_line(FormattedLine::new(outer_lens, inner_lens, format))
+    }
+
+    /// Adds a line which shows `display` widgets and swaps in an `editor` widget for the cell being edited.
+    pub fn with_edit_line<
+        T2: ListIter<U> + Data,
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, T2> + 'static,
+        L2: Lens<U, V> + 'static,
+        D: Widget<V> + 'static,
+        E: Widget<V> + 'static,
+    >(self, outer_lens: L1, inner_lens: L2, display: impl Fn() -> D + 'static, editor: impl Fn() -> E + 'static) -> Self {
+        self.with_custom_line(EditTableLine::new(outer_lens, inner_lens, display, editor))
+    }
+
+    pub fn with_tree_line<
+        N: TreeNode + 'static,
+        V: Data,
+
+        L1: Lens<T, Vector<N>> + 'static,
+        L2: Lens<N, V> + Clone + 'static,
+        W: Widget<V> + 'static,
+        F: Fn() -> W + 'static,
+    >(self, lens: L1, inner_lens: L2, widget: F) -> Self {
+        self.with_custom_line(TreeTableLine::new(lens, inner_lens, widget))
+    }
+
+    pub fn with_grouped_line<
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, Vector<U>> + 'static,
+        L2: Lens<U, V> + Clone + 'static,
+        W: Widget<V> + 'static,
+        F: Fn() -> W + 'static,
+    >(self, lens: GroupLens<L1, U>, inner_lens: L2, widget: F) -> Self {
+        let widget = Rc::new(widget);
+        self.with_custom_line(WidgetTableLine::new(lens, Identity, move||{
+            let widget = widget.clone();
+            GroupCell::new(inner_lens.clone(), move||widget())
+        }))
+    }
+
+    /// Adds a grouped line which shows the aggregate of the `GroupPolicy` with the same key in the
+    /// group headers, the key also becomes the key of the line.
+    pub fn with_aggregated_line<
+        U: Data,
+        V: Data,
+
+        L1: Lens<T, Vector<U>> + 'static,
+        L2: Lens<U, V> + Clone + 'static,
+        W: Widget<V> + 'static,
+        F: Fn() -> W + 'static,
+    >(self, lens: GroupLens<L1, U>, inner_lens: L2, widget: F, key: impl Into<String>) -> Self {
+        let key = key.into();
+        let line_key = key.clone();
+        let widget = Rc::new(widget);
+        self.with_custom_line(WidgetTableLine::new(lens, Identity, move||{
+            let widget = widget.clone();
+            GroupCell::new(inner_lens.clone(), move||widget()).with_aggregate(line_key.clone())
+        })).with_line_key(key)
+    }
+
     pub fn with_custom_line<L: TableLine<T> + 'static>(mut self, line: L) -> Self {
         self.add_line(line);
         self
     }
 
+    /// Sets the key of the last added line, see `apply_layout`.
+    pub fn with_line_key(self, key: impl Into<String>) -> Self {
+        let index = self.lines.len().saturating_sub(1);
+        self.layout_mut().lines_mut().set_key(index, key);
+        self
+    }
+
+    pub fn with_painter(mut self, painter: impl TablePainter<T> + 'static) -> Self {
+        self.set_painter(painter);
+        self
+    }
+
+    pub fn set_painter(&mut self, painter: impl TablePainter<T> + 'static) {
+        self.painter = Box::new(painter);
+    }
+
+    /// Replaces the controller, the fill handle and the history keep working around the new one.
+    pub fn with_controller(mut self, controller: impl TableController<T> + 'static) -> Self {
+        self.set_controller(controller);
+        self
+    }
+
+    pub fn set_controller(&mut self, controller: impl TableController<T> + 'static) {
+        self.controller = Box::new(controller);
+        if self.fill_handle {
+            self.wrap_controller(FillController::new);
+        }
+        if let Some(history) = self.history.clone() {
+            self.wrap_controller(|inner|UndoController::new(inner, history));
+        }
+    }
+
+    fn wrap_controller<C: TableController<T> + 'static>(&mut self, wrap: impl FnOnce(Box<dyn TableController<T>>) -> C) {
+        let inner = std::mem::replace(&mut self.controller, Box::new(DefaultTableController));
+        self.controller = Box::new(wrap(inner));
+    }
+
+    /// Records the writes of the table, like edits, fills, replacements and moved elements,
+    /// which can be undone with Ctrl+Z and redone with Ctrl+Shift+Z.
+    pub fn with_history(mut self) -> Self {
+        self.enable_history();
+        self
+    }
+
+    pub fn enable_history(&mut self) {
+        if self.history.is_some() {
+            return;
+        }
+        let history = Rc::new(RefCell::new(EditHistory::new()));
+        self.history = Some(history.clone());
+        self.wrap_controller(|inner|UndoController::new(inner, history));
+    }
+
+    fn record(&self, operations: Vec<EditOperation>) {
+        record_operations(self.history.as_deref(), &self.lines, &self.line_order, operations);
+    }
+
+    fn line_id(&self, line: usize) -> usize {
+        self.line_order.get(line).copied().unwrap_or(line)
+    }
+
+    /// The values of the given elements of a line as (element, value).
+    fn line_values(&self, data: &T, line: usize, elements: &[usize]) -> Vec<(usize, Rc<dyn Any>)> {
+        let range = match (elements.iter().min(), elements.iter().max()) {
+            (Some(first), Some(last)) => *first..=*last,
+            _ => return vec![],
+        };
+        let mut values = self.lines.get(line).map_or(vec![], |table_line|table_line.values(data, range));
+        values.retain(|(element, _)|elements.contains(element));
+        values
+    }
+
+    /// Tables which are edited or navigated with the keyboard take the focus, other tables leave it alone.
+    fn needs_focus(&self) -> bool {
+        self.history.is_some()
+            || self.search.is_some()
+            || self.fill_handle
+            || self.lines.iter().any(|line|line.needs_cursor())
+    }
+
+    pub fn history(&self) -> Option<Rc<RefCell<EditHistory>>> {
+        self.history.clone()
+    }
+
+    /// Shows a handle at the corner of the selection which fills cells when dragged,
+    /// and fills the selection with Ctrl+D and Ctrl+R, see `FillController`.
+    pub fn with_fill_handle(mut self) -> Self {
+        self.enable_fill_handle();
+        self
+    }
+
+    pub fn enable_fill_handle(&mut self) {
+        if self.fill_handle {
+            return;
+        }
+        self.wrap_controller(FillController::new);
+        self.fill_handle = true;
+    }
+
+    /// Compares the data with a baseline and marks changed cells, added and removed elements.
+    /// Elements are identified by `key`, the baseline is the data when the table is added
+    /// or the data passed to `set_baseline`.
+    ///
+    /// Keys have to be unique, of elements with the same key only the first one is compared
+    /// with the baseline.
+    pub fn with_change_tracking<
+        T2: ListIter<U>,
+        U: Data,
+        K: Hash + Eq + 'static,
+
+        L: Lens<T, T2> + 'static,
+    >(mut self, lens: L, key: impl Fn(&U) -> K + 'static) -> Self {
+        self.set_change_tracking(lens, key);
+        self
+    }
+
+    pub fn set_change_tracking<
+        T2: ListIter<U>,
+        U: Data,
+        K: Hash + Eq + 'static,
+
+        L: Lens<T, T2> + 'static,
+    >(&mut self, lens: L, key: impl Fn(&U) -> K + 'static) {
+        self.change_tracker = Some(ChangeTracker::new(lens, key));
+    }
+
+    /// Sets the data to compare with, e.g. after the data was saved.
+    pub fn set_baseline(&mut self, data: &T) {
+        if let Some(tracker) = &mut self.change_tracker {
+            tracker.baseline = Some(data.clone());
+        }
+        self.update_dirty_cells(data);
+    }
+
+    pub fn baseline(&self) -> Option<&T> {
+        self.change_tracker.as_ref().and_then(|tracker|tracker.baseline.as_ref())
+    }
+
+    /// Returns the differences between the data and the baseline, empty without change tracking.
+    pub fn change_set(&self, data: &T) -> ChangeSet {
+        let (elements, baseline) = match self.change_tracker.as_ref().and_then(|tracker|Some((tracker.match_elements(data)?, tracker.baseline.as_ref()?))) {
+            Some(result) => result,
+            None => return ChangeSet::default(),
+        };
+        let cells = self.lines.iter()
+            .enumerate()
+            .flat_map(|(line, table_line)|{
+                let keys = &elements.keys;
+                table_line.changes(baseline, data, &elements.baseline_index)
+                    .into_iter()
+                    .map(move |(element, old, new)|CellChange::new(keys[element].clone(), line, old, new))
+            })
+            .collect();
+        ChangeSet::new(elements, cells)
+    }
+
+    fn update_dirty_cells(&mut self, data: &T) {
+        self.dirty = self.dirty_cells(data);
+    }
+
+    fn dirty_cells(&self, data: &T) -> Option<DirtyCells> {
+        let tracker = self.change_tracker.as_ref()?;
+        let baseline = tracker.baseline.as_ref()?;
+        let elements = tracker.match_elements(data)?;
+        let changed = self.lines.iter()
+            .enumerate()
+            .flat_map(|(line, table_line)|table_line.changes(baseline, data, &elements.baseline_index)
+                .into_iter()
+                .map(move |(element, _, _)|(line, element)))
+            .collect();
+        Some(DirtyCells::new(&elements, changed))
+    }
+
+    /// Finds the cells whose text matches the pattern, see `TableLine::texts`, and moves the cursor
+    /// to the first match from the cursor on. Matches are updated when the data changes.
+    /// Returns the number of matches.
+    pub fn search(&mut self, data: &T, pattern: SearchPattern) -> usize {
+        let mut search = TableSearch::new(pattern);
+        search.find(&self.lines, data, &self.layout.deref().borrow());
+        let cursor = self.layout().cursor().unwrap_or((0, 0));
+        let found = search.select_from(cursor);
+        let count = search.matches().len();
+        self.search = Some(search);
+        self.move_to_match(found);
+        count
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search = None;
+    }
+
+    pub fn search_pattern(&self) -> Option<&SearchPattern> {
+        self.search.as_ref().map(TableSearch::pattern)
+    }
+
+    /// The matching cells as (line, element), ordered by element and line.
+    pub fn search_matches(&self) -> &[(usize, usize)] {
+        self.search.as_ref().map_or(&[][..], |search|search.matches())
+    }
+
+    pub fn current_match(&self) -> Option<(usize, usize)> {
+        self.search.as_ref().and_then(TableSearch::current_match)
+    }
+
+    /// Moves the cursor to the next match, after the last match the first one follows.
+    pub fn next_match(&mut self) -> Option<(usize, usize)> {
+        let found = self.search.as_mut().and_then(|search|search.step(true));
+        self.move_to_match(found)
+    }
+
+    pub fn previous_match(&mut self) -> Option<(usize, usize)> {
+        let found = self.search.as_mut().and_then(|search|search.step(false));
+        self.move_to_match(found)
+    }
+
+    /// Replaces the matching text in the cell of the current match and moves to the next match.
+    /// The text is parsed by the line, see `TableLine::set_texts`.
+    pub fn replace_current(&mut self, data: &mut T, replacement: &str) -> ReplaceResult {
+        let cells: Vec<_> = self.current_match().into_iter().collect();
+        let result = self.replace_cells(data, &cells, replacement);
+        if !result.replaced().is_empty() {
+            self.next_match();
+        }
+        result
+    }
+
+    /// Replaces the matches in all matching cells, or only in those inside the selection.
+    /// Cells whose line does not accept text or can not parse the new text keep their value and are listed in the result.
+    ///
+    /// All cells change at once, which makes the replacement a single step of the edit history.
+    pub fn replace_all(&mut self, data: &mut T, replacement: &str, in_selection: bool) -> ReplaceResult {
+        let selection = self.layout().selection();
+        let cells: Vec<_> = self.search_matches().iter()
+            .copied()
+            .filter(|(line, element)|!in_selection || selection.map_or(false, |selection|selection.contains(*line, *element)))
+            .collect();
+        self.replace_cells(data, &cells, replacement)
+    }
+
+    fn replace_cells(&mut self, data: &mut T, cells: &[(usize, usize)], replacement: &str) -> ReplaceResult {
+        let pattern = match &self.search {
+            Some(search) => search.pattern().clone(),
+            None => return ReplaceResult::default(),
+        };
+        let cells: HashSet<(usize, usize)> = cells.iter().copied().collect();
+        let lines: BTreeSet<usize> = cells.iter().map(|(line, _)|*line).collect();
+
+        let mut writes = vec![];
+        for line in lines {
+            let table_line = match self.lines.get(line) {
+                Some(table_line) => table_line,
+                None => continue,
+            };
+            for (element, text) in table_line.texts(data) {
+                if !cells.contains(&(line, element)) {
+                    continue;
+                }
+                let new_text = pattern.replace_all(&text, replacement);
+                if new_text != text {
+                    writes.push((line, element, new_text));
+                }
+            }
+        }
+        self.set_texts(data, writes)
+    }
+
+    /// Writes texts as (line, element, text) through the parsers of the lines, see `TableLine::set_texts`.
+    /// Every line is written in one pass, all writes are a single step of the edit history.
+    fn set_texts(&mut self, data: &mut T, writes: Vec<(usize, usize, String)>) -> ReplaceResult {
+        let mut lines: BTreeMap<usize, Vec<(usize, String)>> = BTreeMap::new();
+        for (line, element, text) in writes {
+            lines.entry(line).or_default().push((element, text));
+        }
+
+        let mut result = ReplaceResult::default();
+        if let Some(history) = &self.history {
+            history.borrow_mut().begin_group();
+        }
+        for (line, texts) in lines {
+            let before = match self.history {
+                Some(_) => self.line_values(data, line, &texts.iter().map(|(element, _)|*element).collect::<Vec<_>>()),
+                None => vec![],
+            };
+            let written = match self.lines.get_mut(line) {
+                Some(table_line) => table_line.set_texts(data, &texts),
+                None => continue,
+            };
+            if !before.is_empty() {
+                let elements: Vec<usize> = written.iter().filter(|(_, written)|written.is_ok()).map(|(element, _)|*element).collect();
+                let after = self.line_values(data, line, &elements);
+                self.record(value_operations(self.line_id(line), before, after));
+            }
+            for (element, written) in written {
+                match written {
+                    Ok(()) => result.add_replaced(line, element),
+                    Err(message) => result.add_failed(line, element, message),
+                }
+            }
+        }
+        if let Some(history) = &self.history {
+            history.borrow_mut().end_group();
+        }
+        result
+    }
+
+    /// Writes tab separated text into the cells from the cursor on, the rows of the text go to
+    /// the following elements and the columns to the following visible lines. The text is parsed
+    /// by the lines like a replacement, the paste is a single step of the edit history.
+    pub fn paste(&mut self, data: &mut T, text: &str) -> ReplaceResult {
+        let writes = {
+            let layout = self.layout();
+            let (first_line, first_element) = match layout.cursor() {
+                Some(cursor) => cursor,
+                None => return ReplaceResult::default(),
+            };
+            let lines: Vec<usize> = (first_line..layout.lines().length())
+                .filter(|line|!layout.is_line_hidden(*line))
+                .collect();
+            let elements = layout.elements().length();
+            let mut writes = vec![];
+            for (row, element) in text.lines().zip(first_element..elements) {
+                for (column, line) in row.split('\t').zip(&lines) {
+                    writes.push((*line, element, column.to_string()));
+                }
+            }
+            writes
+        };
+        self.set_texts(data, writes)
+    }
+
+    /// Handles `PASTE`, returns false for other commands.
+    fn paste_command(&mut self, ctx: &mut EventCtx, command: &Command, data: &mut T) -> bool {
+        match command.get(PASTE) {
+            Some(text) => {
+                self.paste(data, text);
+                ctx.request_paint();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pastes the clipboard with Ctrl+V, returns false for other keys.
+    fn paste_key(&mut self, ctx: &mut EventCtx, key: &KeyEvent, data: &mut T) -> bool {
+        let paste = matches!(&key.key, KbKey::Character(c) if c.eq_ignore_ascii_case("v"));
+        if !paste || !(key.mods.ctrl() || key.mods.meta()) {
+            return false;
+        }
+        if let Some(text) = Application::global().clipboard().get_string() {
+            self.paste(data, &text);
+            ctx.request_paint();
+        }
+        true
+    }
+
+    fn move_to_match(&mut self, found: Option<(usize, usize)>) -> Option<(usize, usize)> {
+        if found.is_some() {
+            let mut layout = self.layout_mut();
+            layout.set_cursor(found);
+            layout.set_selection_anchor(found);
+        }
+        found
+    }
+
+    pub fn with_span(self, span: CellSpan) -> Self {
+        self.layout_mut().set_span(span);
+        self
+    }
+
     pub(crate) fn add_line<L: TableLine<T> + 'static>(&mut self, line: L) {
+        self.add_line_part(line, AxisPart::new(None));
+    }
+
+    pub(crate) fn add_line_part<L: TableLine<T> + 'static>(&mut self, line: L, part: AxisPart) {
+        self.line_order.push(self.lines.len());
         self.lines.push(Box::new(line));
-        self.layout_mut().lines_mut().add_part(AxisPart::new(None));
+        self.layout_mut().lines_mut().add_part(part);
     }
-}
 
-impl<T: Data, P: TablePolicy<T>> Table<T, P> {
-    pub fn new_dynamic(policy: P, axis: Axis) -> Self {
-        Self::new(policy, Rc::new(RefCell::new(TableLayout::new(axis))))
+    /// Restores an order of the lines returned by `line_order`.
+    pub fn with_line_order(mut self, order: &[usize]) -> Self {
+        self.set_line_order(order);
+        self
     }
 
-    pub(crate) fn new(policy: P, layout: Rc<RefCell<TableLayout>>) -> Self {
-        Self {
-            layout,
-            policy,
-            lines: vec![],
-            controller: Box::new(DefaultTableController),
-            painter: Box::new(DefaultTablePainter),
+    /// The current order of the lines, every line is identified by the index in which it was added.
+    pub fn line_order(&self) -> &[usize] {
+        &self.line_order
+    }
+
+    pub fn set_line_order(&mut self, order: &[usize]) {
+        for (from, to) in order_moves(&self.line_order, order) {
+            self.move_line(from, to);
+        }
+    }
+
+    pub fn move_line(&mut self, from: usize, to: usize) {
+        move_item(&mut self.lines, from, to);
+        move_item(&mut self.line_order, from, to);
+        if self.invalid.len() == self.lines.len() {
+            move_item(&mut self.invalid, from, to);
+        }
+        if let Some(dirty) = &mut self.dirty {
+            dirty.move_line(from, to);
+        }
+        self.layout_mut().move_line(from, to);
+        self.policy.lines_changed(&self.lines, &mut RefCell::borrow_mut(&self.layout));
+    }
+
+    pub fn is_line_visible(&self, index: usize) -> bool {
+        !self.layout.deref().borrow().is_line_hidden(index)
+    }
+
+    /// Returns all cells with an invalid value as (line, element, message), e.g. to block saving.
+    /// The cells are validated when the data changes, before the next paint.
+    pub fn invalid_cells(&self) -> Vec<(usize, usize, String)> {
+        self.invalid.iter()
+            .enumerate()
+            .flat_map(|(line, invalid)|invalid.iter()
+                .map(move |(element, message)|(line, *element, message.clone())))
+            .collect()
+    }
+
+    fn validate(&mut self, data: &T) {
+        self.invalid = self.lines.iter().map(|line|line.validate(data)).collect();
+    }
+
+    /// Hides or shows a line, hidden lines keep their widgets and configuration.
+    pub fn set_line_visible(&mut self, index: usize, visible: bool) {
+        let mut layout = RefCell::borrow_mut(&self.layout);
+        if index < layout.lines().length() {
+            let mut part = layout.lines().get(index);
+            part.set_hidden(!visible);
+            layout.lines_mut().set(index, part);
+        }
+        self.policy.lines_changed(&self.lines, &mut layout);
+    }
+
+    /// Handles the commands which change the layout, the cursor and the selection, returns false
+    /// for other commands.
+    fn layout_command(&mut self, ctx: &mut EventCtx, command: &Command) -> bool {
+        if command.is(TRANSPOSE) {
+            self.layout_mut().transpose();
+            ctx.request_layout();
+        } else if let Some((index, visible)) = command.get(SET_LINE_VISIBLE) {
+            self.set_line_visible(*index, *visible);
+            ctx.request_layout();
+        } else if let Some((lines, elements)) = command.get(MOVE_CURSOR) {
+            let mut layout = self.layout_mut();
+            layout.move_cursor(*lines, *elements);
+            let cursor = layout.cursor();
+            layout.set_selection_anchor(cursor);
+            ctx.request_paint();
+        } else if let Some(selection) = command.get(SET_SELECTION) {
+            self.layout_mut().set_selection(*selection);
+            ctx.request_paint();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Sorts and filters the data by the keys and filters of the view, which the table only keeps.
+    /// `hook` is called at the next event after they were set by `set_sort_filter`,
+    /// `SET_SORT_FILTER` or `apply_view_state`.
+    pub fn with_sort_filter(mut self, hook: impl Fn(&mut T, &[SortKey], &[LineFilter]) + 'static) -> Self {
+        self.sort_filter = Some(SortFilter::new(hook));
+        self
+    }
+
+    /// Does nothing without a hook set by `with_sort_filter`.
+    pub fn set_sort_filter(&mut self, sort_keys: Vec<SortKey>, filters: Vec<LineFilter>) {
+        if let Some(sort_filter) = &mut self.sort_filter {
+            sort_filter.set(sort_keys, filters);
+        }
+    }
+
+    pub fn sort_keys(&self) -> &[SortKey] {
+        self.sort_filter.as_ref().map_or(&[][..], |sort_filter|&sort_filter.sort_keys[..])
+    }
+
+    pub fn filters(&self) -> &[LineFilter] {
+        self.sort_filter.as_ref().map_or(&[][..], |sort_filter|&sort_filter.filters[..])
+    }
+
+    /// Returns the state without a scroll position, which is added by `HeaderTable::view_state`.
+    pub fn view_state(&self) -> TableViewState {
+        let layout = self.layout.deref().borrow();
+        let mut lines = vec![LineState::default(); self.line_order.len()];
+        for (index, id) in self.line_order.iter().enumerate() {
+            let part = layout.lines().get(index);
+            lines[*id] = LineState {
+                size: part.size(),
+                fixed: part.is_fixed(),
+                hidden: part.is_hidden(),
+            };
+        }
+
+        TableViewState {
+            line_order: self.line_order.clone(),
+            lines,
+            sort_keys: self.sort_keys().to_vec(),
+            filters: self.filters().to_vec(),
+            scroll: None,
         }
     }
 
+    /// Applies a state returned by `view_state`, lines which no longer exist are ignored.
+    pub fn apply_view_state(&mut self, state: &TableViewState) {
+        self.set_line_order(&state.line_order);
+        {
+            let mut layout = RefCell::borrow_mut(&self.layout);
+            for (index, id) in self.line_order.iter().enumerate() {
+                if let Some(line) = state.lines.get(*id) {
+                    // Flexible lines keep their minimum and maximum size
+                    let mut part = layout.lines().get(index);
+                    if line.fixed {
+                        part.set_fixed(Some(line.size));
+                    } else if part.is_fixed() {
+                        part.set_fixed(None);
+                    }
+                    part.set_hidden(line.hidden);
+                    layout.lines_mut().set(index, part);
+                }
+            }
+            self.policy.lines_changed(&self.lines, &mut layout);
+        }
+        self.set_sort_filter(state.sort_keys.clone(), state.filters.clone());
+    }
+
+    /// Returns the order of the line ids which matches the order of the keys in `layout`.
+    /// Lines without a matching key stay behind the matched ones.
+    pub(crate) fn keyed_line_order(&self, layout: &TableLayout) -> Vec<usize> {
+        let own = self.layout.deref().borrow();
+        let mut order: Vec<usize> = (0..layout.lines().length())
+            .filter_map(|index|layout.lines().key(index))
+            .filter_map(|key|own.lines().index_of(key))
+            .map(|index|self.line_order[index])
+            .collect();
+        order.extend(self.line_order.iter().filter(|id|!order.contains(*id)).copied().collect::<Vec<_>>());
+        order
+    }
+
+    /// Applies a stored layout, lines are matched by their key and reordered like in `layout`.
+    pub fn apply_layout(&mut self, layout: &TableLayout) {
+        let order = self.keyed_line_order(layout);
+        self.set_line_order(&order);
+        self.layout_mut().apply(layout);
+    }
+
+    pub fn with_view_state(mut self, state: &TableViewState) -> Self {
+        self.apply_view_state(state);
+        self
+    }
+
+    fn sync_line_order(&mut self) {
+        // Lines generated by the policy are numbered from scratch
+        if self.line_order.len() != self.lines.len() {
+            self.line_order = (0..self.lines.len()).collect();
+        }
+    }
+
+    /// Sets the list of elements, this enables moving elements inside the data.
+    /// The lens has to point to the same list as the lines of the table.
+    pub fn with_element_source<
+        T2: ListEdit<U> + 'static,
+        U: Data,
+
+        L: Lens<T, T2> + 'static,
+    >(mut self, lens: L) -> Self {
+        self.set_element_source(lens);
+        self
+    }
+
+    pub(crate) fn set_element_source<
+        T2: ListEdit<U> + 'static,
+        U: Data,
+
+        L: Lens<T, T2> + 'static,
+    >(&mut self, lens: L) {
+        self.element_source = Some(Box::new(LensSource::new(lens)));
+    }
+
+    pub fn has_element_source(&self) -> bool {
+        self.element_source.is_some()
+    }
+
+    /// Moves an element inside the data, the widgets and the size of the element follow.
+    /// Returns false if the table has no element source.
+    pub fn move_element(&mut self, data: &mut T, from: usize, to: usize) -> bool {
+        let moved = self.apply_move_element(data, from, to);
+        if moved {
+            self.record(vec![EditOperation::MoveElement {from, to}]);
+        }
+        moved
+    }
+
+    /// Moves an element without recording it in the edit history.
+    pub(crate) fn apply_move_element(&mut self, data: &mut T, from: usize, to: usize) -> bool {
+        let elements = self.layout.deref().borrow().elements().length();
+        let source = match &self.element_source {
+            Some(source) if from < elements && to < elements => source,
+            _ => return false,
+        };
+        source.move_element(data, from, to);
+
+        for line in &mut self.lines {
+            line.move_element(from, to);
+        }
+        if let Some(detail) = &mut self.detail {
+            detail.move_element(from, to);
+        }
+        for invalid in &mut self.invalid {
+            for (element, _) in invalid.iter_mut() {
+                *element = moved_index(*element, from, to);
+            }
+        }
+        if let Some(dirty) = &mut self.dirty {
+            dirty.move_element(from, to);
+        }
+        if let Some(search) = &mut self.search {
+            search.move_element(from, to);
+        }
+        self.layout_mut().move_element(from, to);
+        true
+    }
+
+    pub(crate) fn element(&self, data: &T, index: usize) -> Option<Rc<dyn Any>> {
+        self.element_source.as_ref()?.get(data, index)
+    }
+
+    pub(crate) fn insert_element(&mut self, data: &mut T, index: usize, value: &dyn Any) -> bool {
+        let index = index.min(self.layout.deref().borrow().elements().length());
+        let inserted = self.apply_insert_element(data, index, value);
+        if let Some(value) = self.element(data, index).filter(|_|inserted) {
+            self.record(vec![EditOperation::InsertElement {index, value}]);
+        }
+        inserted
+    }
+
+    /// Inserts an element without recording it in the edit history.
+    pub(crate) fn apply_insert_element(&mut self, data: &mut T, index: usize, value: &dyn Any) -> bool {
+        let elements = self.layout.deref().borrow().elements().length();
+        let index = index.min(elements);
+        let inserted = self.element_source.as_ref().map_or(false, |source|source.insert(data, index, value));
+        if inserted {
+            for line in &mut self.lines {
+                line.insert_element(index);
+            }
+            if let Some(detail) = &mut self.detail {
+                detail.insert_element(index);
+            }
+            self.layout_mut().elements_mut().insert_part(index, AxisPart::new(None));
+            self.children_changed = true;
+        }
+        inserted
+    }
+
+    pub(crate) fn remove_element(&mut self, data: &mut T, index: usize) {
+        let value = self.element(data, index);
+        if self.apply_remove_element(data, index) {
+            if let Some(value) = value {
+                self.record(vec![EditOperation::RemoveElement {index, value}]);
+            }
+        }
+    }
+
+    /// Removes an element without recording it in the edit history, returns false if it does not exist.
+    pub(crate) fn apply_remove_element(&mut self, data: &mut T, index: usize) -> bool {
+        let elements = self.layout.deref().borrow().elements().length();
+        let removed = match self.element_source.as_ref().filter(|_|index < elements) {
+            Some(source) => {
+                source.remove(data, index);
+                true
+            }
+            None => false,
+        };
+        if removed {
+            for line in &mut self.lines {
+                line.remove_element(index);
+            }
+            if let Some(detail) = &mut self.detail {
+                detail.remove_element(index);
+            }
+            self.layout_mut().elements_mut().remove_part(index);
+            self.children_changed = true;
+        }
+        removed
+    }
+
+    pub fn with_detail<
+        T2: ListIter<U> + Data,
+        U: Data,
+
+        L: Lens<T, T2> + 'static,
+        W: Widget<U> + 'static,
+        F: Fn() -> W + 'static,
+    >(mut self, lens: L, widget: F) -> Self {
+        self.set_detail(WidgetTableDetail::new(lens, widget));
+        self
+    }
+
+    pub fn with_custom_detail<D: TableDetail<T> + 'static>(mut self, detail: D) -> Self {
+        self.set_detail(detail);
+        self
+    }
+
+    pub fn set_detail<D: TableDetail<T> + 'static>(&mut self, detail: D) {
+        self.detail = Some(Box::new(detail));
+    }
+
     pub fn policy(&self) -> &P {
         &self.policy
     }
@@ -73,6 +934,10 @@ impl<T: Data, P: TablePolicy<T>> Table<T, P> {
         &mut self.policy
     }
 
+    pub fn layout(&self) -> Ref<TableLayout> {
+        self.layout.deref().borrow()
+    }
+
     pub fn layout_mut(&self) -> RefMut<TableLayout> {
         self.layout.deref().borrow_mut()
     }
@@ -81,16 +946,131 @@ impl<T: Data, P: TablePolicy<T>> Table<T, P> {
 
 impl<T: Data, P: TablePolicy<T>> Widget<T> for Table<T, P> {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
-        let layout = self.layout.deref().borrow();
-        let mut content = TableContent {lines: &mut self.lines};
+        self.apply_sort_filter(data);
+        if let Event::Command(command) = event {
+            // The features handle their own commands, see `reorder`, `search`, `history` and `state`
+            let handled = self.sort_filter_command(command, data)
+                || self.history_command(ctx, command, data)
+                || self.reorder_command(ctx, command, data)
+                || self.search_command(ctx, command, data)
+                || self.paste_command(ctx, command, data)
+                || self.layout_command(ctx, command);
+            if handled {
+                ctx.set_handled();
+                return;
+            }
+        }
+
+        if let Event::KeyDown(key) = event {
+            if self.search_key(ctx, key) {
+                ctx.set_handled();
+                return;
+            }
+        }
+
+        if let Event::MouseDown(mouse) = event {
+            if self.needs_focus() {
+                ctx.request_focus();
+            }
+            // The cursor is set before the lines get the event, so they can react to clicks on the active cell
+            let captured = self.controller.captures_mouse(mouse.pos, &self.layout());
+            let mut layout = self.layout_mut();
+            let cell = layout.cell_at(mouse.pos);
+            if cell.is_some() && !captured {
+                if !mouse.mods.shift() {
+                    layout.set_selection_anchor(cell);
+                } else if layout.selection_anchor().is_none() {
+                    let cursor = layout.cursor();
+                    layout.set_selection_anchor(cursor);
+                }
+                layout.set_cursor(cell);
+                ctx.request_paint();
+            }
+        }
+
+        {
+            let layout = self.layout.deref().borrow();
+            let mut content = TableContent {lines: &mut self.lines, line_order: &self.line_order, detail: self.detail.as_mut(), dirty: None, search: None, invalid: &self.invalid, history: self.history.as_deref()};
+
+            self.controller.event(ctx, event, data, env, &mut content, &layout);
+        }
+
+        if let Event::KeyDown(key) = event {
+            // Ctrl+V while an editor has the focus pastes into the editor
+            if !ctx.is_handled() && ctx.is_focused() && self.paste_key(ctx, key, data) {
+                ctx.set_handled();
+            }
+        }
+
+        if let Event::KeyDown(key) = event {
+            if !ctx.is_handled() && ctx.is_focused() {
+                let (lines, elements) = match (&key.key, self.layout().axis_direction(TableAxis::LineAxis)) {
+                    (KbKey::Tab, _) if key.mods.shift() => (-1, 0),
+                    (KbKey::Tab, _) => (1, 0),
+                    (KbKey::ArrowLeft, Axis::Vertical) | (KbKey::ArrowUp, Axis::Horizontal) => (-1, 0),
+                    (KbKey::ArrowRight, Axis::Vertical) | (KbKey::ArrowDown, Axis::Horizontal) => (1, 0),
+                    (KbKey::ArrowUp, Axis::Vertical) | (KbKey::ArrowLeft, Axis::Horizontal) => (0, -1),
+                    (KbKey::ArrowDown, Axis::Vertical) | (KbKey::ArrowRight, Axis::Horizontal) => (0, 1),
+                    _ => (0, 0),
+                };
+                if (lines, elements) != (0, 0) {
+                    let mut layout = self.layout_mut();
+                    // Shift extends the selection, Tab always moves a single cell
+                    let extend = key.mods.shift() && key.key != KbKey::Tab;
+                    if extend && layout.selection_anchor().is_none() {
+                        let cursor = layout.cursor();
+                        layout.set_selection_anchor(cursor);
+                    }
+                    layout.move_cursor(lines, elements);
+                    if !extend {
+                        let cursor = layout.cursor();
+                        layout.set_selection_anchor(cursor);
+                    }
+                    ctx.request_paint();
+                    ctx.set_handled();
+                }
+            }
+        }
 
-        self.controller.event(ctx, event, data, env, &mut content, &layout);
+        if let Event::MouseMove(mouse) = event {
+            let mut layout = self.layout_mut();
+            let hovered = layout.cell_at(mouse.pos);
+            if hovered != layout.hovered() {
+                layout.set_hovered(hovered);
+                ctx.request_paint();
+            }
+        }
+
+        // Lines can change their length without changing the data (e.g. by collapsing a group).
+        // The widgets are synchronized during the next update.
+        let elements = self.lines.first().map_or(0, |line|line.element_count(data));
+        if elements != self.layout.deref().borrow().elements().length() {
+            ctx.request_update();
+        }
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::HotChanged(false) = event {
+            self.layout_mut().set_hovered(None);
+            ctx.request_paint();
+        }
+        if let LifeCycle::WidgetAdded = event {
+            if self.needs_focus() {
+                ctx.register_for_focus();
+            }
+            if let Some(tracker) = self.change_tracker.as_mut().filter(|tracker|tracker.baseline.is_none()) {
+                tracker.baseline = Some(data.clone());
+            }
+            self.policy.update(data, data, &mut self.lines, &mut RefCell::borrow_mut(&self.layout));
+            self.sync_line_order();
+        }
+
         for line in &mut self.lines {
             line.lifecycle(ctx, event, data, env);
         }
+        if let Some(detail) = &mut self.detail {
+            detail.lifecycle(ctx, event, data, env);
+        }
         self.controller.lifecycle(ctx, event, data, env);
 
         if let LifeCycle::WidgetAdded = event {
@@ -103,11 +1083,14 @@ impl<T: Data, P: TablePolicy<T>> Widget<T> for Table<T, P> {
             }
 
             self.layout_mut().elements_mut().set_length(elements, AxisPart::new(None));
+            self.validate(data);
+            self.update_dirty_cells(data);
         }
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
         self.policy.update(old_data, data, &mut self.lines, &mut RefCell::borrow_mut(&self.layout));
+        self.sync_line_order();
         let elements = self.lines.first().map_or(0, |line|line.element_count(data));
 
         for line in &mut self.lines {
@@ -118,20 +1101,51 @@ impl<T: Data, P: TablePolicy<T>> Widget<T> for Table<T, P> {
             }
         }
 
+        if let Some(detail) = &mut self.detail {
+            detail.update(ctx, data, env);
+        }
+
         self.layout_mut().elements_mut().set_length(elements, AxisPart::new(None));
+        if std::mem::take(&mut self.children_changed) {
+            ctx.children_changed();
+        }
+
+        // Lines generated by the policy are checked from scratch
+        if !old_data.same(data) || self.invalid.len() != self.lines.len() {
+            self.validate(data);
+            self.update_dirty_cells(data);
+            ctx.request_paint();
+        }
+
+        if let Some(search) = self.search.as_mut().filter(|_|!old_data.same(data)) {
+            search.find(&self.lines, data, &self.layout.deref().borrow());
+            ctx.request_paint();
+        }
 
         self.controller.update(ctx, old_data, data, env);
     }
 
     fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
         let mut layout = RefCell::borrow_mut(&self.layout);
+        layout.set_grid_width(theme::grid_width(env));
+        layout.set_cell_padding(theme::cell_padding(env));
         layout.prepare_layout(bc.max());
 
         for (index, line) in self.lines.iter_mut().enumerate() {
-            line.layout(ctx, data, env, &mut *layout, index);
+            if !layout.is_line_hidden(index) {
+                line.layout(ctx, data, env, &mut *layout, index);
+            }
+        }
+        if let Some(detail) = &mut self.detail {
+            detail.layout(ctx, data, env, &mut *layout);
         }
         for (index, line) in self.lines.iter_mut().enumerate() {
-            line.arrange(ctx, data, env, &layout, index);
+            if !layout.is_line_hidden(index) {
+                line.arrange(ctx, data, env, &layout, index);
+            }
+        }
+        if let Some(detail) = &mut self.detail {
+            detail.arrange(ctx, data, env, &layout);
         }
 
         layout.table_size()
@@ -139,26 +1153,307 @@ impl<T: Data, P: TablePolicy<T>> Widget<T> for Table<T, P> {
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
         let layout = self.layout.deref().borrow();
-        let mut content = TableContent {lines: &mut self.lines};
+        let mut content = TableContent {lines: &mut self.lines, line_order: &self.line_order, detail: self.detail.as_mut(), dirty: self.dirty.as_ref(), search: self.search.as_ref(), invalid: &self.invalid, history: None};
 
         self.painter.paint(ctx, data, env, &mut content, &layout);
+        self.controller.paint(ctx, env, &layout);
+
+        if let Some((line, element)) = layout.hovered().filter(|(line, _)|!layout.is_line_hidden(*line)) {
+            let message = self.invalid.get(line)
+                .and_then(|invalid|invalid.iter().find(|(index, _)|*index == element));
+            if let Some((_, message)) = message {
+                let rect = layout.layout_rect(line, element);
+                let origin = Point::new(rect.x0, rect.y1 + theme::tooltip_padding(env));
+                let message = message.clone();
+                let env = env.clone();
+                ctx.paint_with_z_index(1, move|ctx|paint_tooltip(ctx, &env, &message, origin));
+            }
+        }
+    }
+}
+
+fn paint_tooltip(ctx: &mut PaintCtx, env: &Env, message: &str, origin: Point) {
+    let text = ctx.text()
+        .new_text_layout(message.to_string())
+        .font(FontFamily::SYSTEM_UI, env.get(druid::theme::TEXT_SIZE_NORMAL))
+        .text_color(env.get(druid::theme::TEXT_COLOR))
+        .build();
+    if let Ok(text) = text {
+        let rect = Rect::from_origin_size(origin, text.size()).inset(theme::tooltip_padding(env));
+        ctx.fill(rect, &env.get(druid::theme::BACKGROUND_LIGHT));
+        ctx.stroke(rect, &theme::error_color(env), theme::grid_width(env));
+        ctx.draw_text(&text, origin);
     }
 }
 
 impl<'a, T: Data> TableContent<'a, T> {
+    /// The id of the line at the given position, which is the index in which it was added.
+    pub fn line_id(&self, line: usize) -> usize {
+        self.line_order.get(line).copied().unwrap_or(line)
+    }
+
     pub fn paint_background(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env, layout: &TableLayout) {
         for (index, line) in self.lines.iter_mut().enumerate() {
-            line.paint(ctx, data, env, layout, index);
+            if !layout.is_line_hidden(index) {
+                line.paint_background(ctx, data, env, layout, index);
+            }
+        }
+        for (index, line) in self.lines.iter_mut().enumerate() {
+            if !layout.is_line_hidden(index) {
+                line.paint(ctx, data, env, layout, index);
+            }
+        }
+        if let Some(detail) = &mut self.detail {
+            detail.paint(ctx, data, env, layout);
         }
     }
 
-    pub fn paint_foreground(&mut self, _: &mut PaintCtx, _: &T, _: &Env, _: &TableLayout) {
-        //TODO: paint hovered cells
+    pub fn paint_foreground(&mut self, ctx: &mut PaintCtx, _data: &T, env: &Env, layout: &TableLayout) {
+        if let Some(dirty) = self.dirty {
+            dirty.paint(ctx, env, layout);
+        }
+        if let Some(search) = self.search {
+            search.paint(ctx, env, layout);
+        }
+
+        let error_color = theme::error_color(env);
+        let marker_size = theme::error_marker_size(env);
+        for (index, invalid) in self.invalid.iter().enumerate() {
+            if index >= layout.lines().length() || layout.is_line_hidden(index) {
+                continue;
+            }
+            for &(element, _) in invalid {
+                if element >= layout.elements().length() || layout.is_covered(index, element) {
+                    continue;
+                }
+                let rect = layout.layout_rect(index, element);
+                let mut marker = BezPath::new();
+                marker.move_to((rect.x1 - marker_size, rect.y0));
+                marker.line_to((rect.x1, rect.y0));
+                marker.line_to((rect.x1, rect.y0 + marker_size));
+                marker.close_path();
+                ctx.fill(marker, &error_color);
+            }
+        }
+
+        let hover = theme::hover_color(env);
+        if let Some((line, element)) = layout.hovered().filter(|_|theme::is_visible(&hover)) {
+            if line < layout.lines().length() && element < layout.elements().length() {
+                ctx.fill(layout.layout_rect(line, element), &hover);
+            }
+        }
+
+        let lines = layout.lines().length();
+        let elements = layout.elements().length();
+        let in_table = |range: &CellRange|range.lines.1 < lines && range.elements.1 < elements;
+        if let Some(selection) = layout.selection().filter(|selection|!selection.is_single_cell() && in_table(selection)) {
+            ctx.fill(layout.range_rect(selection), &theme::selection_color(env));
+        }
+        if let Some((line, element)) = layout.cursor().filter(|_|ctx.has_focus()) {
+            if line < lines && element < elements {
+                let width = theme::focus_ring_width(env);
+                ctx.stroke(layout.layout_rect(line, element).inset(-width / 2.0), &theme::focus_ring_color(env), width);
+            }
+        }
     }
 
-    pub fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
-        for line in self.lines.iter_mut() {
-            line.event(ctx, event, data, env);
+    /// Returns the value of a cell, `None` if the line does not support values.
+    pub fn value(&self, data: &T, line: usize, element: usize) -> Option<Rc<dyn Any>> {
+        self.lines.get(line)?.value(data, element)
+    }
+
+    /// Writes the value of a cell, returns false if the line does not accept it.
+    /// The write is recorded in the edit history of the table.
+    pub fn set_value(&mut self, data: &mut T, line: usize, element: usize, value: &dyn Any) -> bool {
+        let before = self.value(data, line, element).filter(|_|self.history.is_some());
+        let written = self.lines.get_mut(line).map_or(false, |table_line|table_line.set_value(data, element, value));
+        if let (Some(before), Some(after)) = (before, self.value(data, line, element).filter(|_|written)) {
+            self.record(vec![EditOperation::SetValue {line: self.line_id(line), element, before, after}]);
         }
+        written
     }
-}
\ No newline at end of file
+
+    /// Returns the values of a range of elements of a line as (element, value).
+    pub fn values(&self, data: &T, line: usize, elements: RangeInclusive<usize>) -> Vec<(usize, Rc<dyn Any>)> {
+        self.lines.get(line).map_or(vec![], |table_line|table_line.values(data, elements))
+    }
+
+    /// Writes values as (element, value) into a line, returns the count of written values.
+    /// The write is recorded in the edit history of the table.
+    pub fn set_values(&mut self, data: &mut T, line: usize, values: &[(usize, Rc<dyn Any>)]) -> usize {
+        let first = values.iter().map(|(element, _)|*element).min().unwrap_or(0);
+        let last = values.iter().map(|(element, _)|*element).max().unwrap_or(0);
+        let before = match self.history {
+            Some(_) => self.values(data, line, first..=last),
+            None => vec![],
+        };
+        let written = self.lines.get_mut(line).map_or(0, |table_line|table_line.set_values(data, values));
+        if written > 0 && !before.is_empty() {
+            let after = self.values(data, line, first..=last);
+            self.record(value_operations(self.line_id(line), before, after));
+        }
+        written
+    }
+
+    /// Starts a group of writes, which are undone as one step until the matching `end_group`.
+    pub fn begin_group(&self) {
+        if let Some(history) = self.history {
+            history.borrow_mut().begin_group();
+        }
+    }
+
+    pub fn end_group(&self) {
+        if let Some(history) = self.history {
+            history.borrow_mut().end_group();
+        }
+    }
+
+    fn record(&self, operations: Vec<EditOperation>) {
+        record_operations(self.history, self.lines, self.line_order, operations);
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Passes the event to the lines and the detail, the values they change are recorded in the
+    /// edit history. The data is only copied for events which can write, e.g. not for mouse moves.
+    pub fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env, layout: &TableLayout) {
+        let before = self.history.filter(|_|can_write(event)).map(|_|data.clone());
+        for (index, line) in self.lines.iter_mut().enumerate() {
+            if !layout.is_line_hidden(index) {
+                line.event(ctx, event, data, env, layout, index);
+            }
+        }
+        if let Some(detail) = &mut self.detail {
+            detail.event(ctx, event, data, env, layout);
+        }
+        if let Some(before) = before.filter(|before|!before.same(data)) {
+            let operations = self.changed_values(&before, data);
+            self.record(operations);
+        }
+    }
+
+    /// The values which differ from `before`, lines whose length changed are skipped.
+    fn changed_values(&self, before: &T, data: &T) -> Vec<EditOperation> {
+        let mut operations = vec![];
+        for (index, line) in self.lines.iter().enumerate() {
+            let elements = line.element_count(before);
+            if elements != line.element_count(data) {
+                continue;
+            }
+            let elements: Vec<Option<usize>> = (0..elements).map(Some).collect();
+            for (element, before, after) in line.changes(before, data, &elements) {
+                operations.push(EditOperation::SetValue {line: self.line_id(index), element, before, after});
+            }
+        }
+        operations
+    }
+}
+
+/// Returns false for events which widgets do not write on, e.g. mouse moves without a button.
+fn can_write(event: &Event) -> bool {
+    match event {
+        Event::MouseMove(mouse) => !mouse.buttons.is_empty(),
+        Event::AnimFrame(_) | Event::Timer(_) | Event::WindowSize(_) | Event::WindowConnected | Event::Internal(_) => false,
+        _ => true,
+    }
+}
+
+/// Records operations in the edit history, values which were written back are dropped.
+fn record_operations<T: Data>(history: Option<&RefCell<EditHistory>>, lines: &[Box<dyn TableLine<T>>], line_order: &[usize], operations: Vec<EditOperation>) {
+    if let Some(history) = history {
+        history.borrow_mut().record(operations, |operation|match operation {
+            EditOperation::SetValue {line, before, after, ..} => line_order.iter()
+                .position(|id|id == line)
+                .map_or(false, |index|lines[index].same_value(before.as_ref(), after.as_ref())),
+            _ => false,
+        });
+    }
+}
+
+/// The writes of a line from the values before to the values after, both given as (element, value).
+fn value_operations(line: usize, before: Vec<(usize, Rc<dyn Any>)>, after: Vec<(usize, Rc<dyn Any>)>) -> Vec<EditOperation> {
+    let mut before: HashMap<usize, Rc<dyn Any>> = before.into_iter().collect();
+    after.into_iter()
+        .filter_map(|(element, after)|Some(EditOperation::SetValue {line, element, before: before.remove(&element)?, after}))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::widget::Label;
+    use super::*;
+
+    #[derive(Clone, Data, Lens)]
+    struct Row {
+        name: String,
+        age: i32,
+    }
+
+    fn row(name: &str, age: i32) -> Row {
+        Row { name: name.to_string(), age }
+    }
+
+    #[test]
+    fn invalid_cells_follow_their_line() {
+        let data: Vector<Row> = vec![row("", 30), row("Ada", -4)].into();
+        let mut table = Table::new_static(Axis::Vertical)
+            .with_custom_line(WidgetTableLine::new(Identity, Row::name, ||Label::dynamic(|name: &String, _|name.clone()))
+                .with_validator(|name: &String|if name.is_empty() {Err("name is missing".to_string())} else {Ok(())}))
+            .with_custom_line(WidgetTableLine::new(Identity, Row::age, ||Label::dynamic(|age: &i32, _|age.to_string()))
+                .with_validator(|age: &i32|if *age < 0 {Err("age is negative".to_string())} else {Ok(())})
+                .with_validator(|age: &i32|if *age > 150 {Err("age is too large".to_string())} else {Ok(())}));
+
+        table.validate(&data);
+        assert_eq!(table.invalid_cells(), vec![
+            (0, 0, "name is missing".to_string()),
+            (1, 1, "age is negative".to_string()),
+        ]);
+
+        table.move_line(1, 0);
+        assert_eq!(table.invalid_cells(), vec![
+            (0, 1, "age is negative".to_string()),
+            (1, 0, "name is missing".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn undo_writes_back_through_the_moved_line() {
+        let mut data: Vector<Row> = vec![row("Ada", 36), row("Bob", 40)].into();
+        let mut table = Table::new_static(Axis::Vertical)
+            .with_custom_line(WidgetTableLine::new(Identity, Row::name, ||Label::dynamic(|name: &String, _|name.clone())))
+            .with_custom_line(WidgetTableLine::new(Identity, Row::age, ||Label::dynamic(|age: &i32, _|age.to_string())))
+            .with_history();
+
+        table.move_line(1, 0);
+        {
+            let mut content = TableContent {lines: &mut table.lines, line_order: &table.line_order, detail: None, dirty: None, search: None, invalid: &table.invalid, history: table.history.as_deref()};
+            assert!(content.set_value(&mut data, 0, 1, &41));
+        }
+        table.move_line(1, 0);
+
+        assert!(table.undo(&mut data));
+        assert_eq!(data[1].age, 40);
+        assert_eq!(data[1].name, "Bob");
+        assert!(table.redo(&mut data));
+        assert_eq!(data[1].age, 41);
+    }
+
+    #[test]
+    fn values_written_back_leave_no_step() {
+        let mut data: Vector<Row> = vec![row("Ada", 36)].into();
+        let mut table = Table::new_static(Axis::Vertical)
+            .with_custom_line(WidgetTableLine::new(Identity, Row::age, ||Label::dynamic(|age: &i32, _|age.to_string())))
+            .with_history();
+        let history = table.history().unwrap();
+
+        history.borrow_mut().set_editing(true);
+        let mut content = TableContent {lines: &mut table.lines, line_order: &table.line_order, detail: None, dirty: None, search: None, invalid: &table.invalid, history: table.history.as_deref()};
+        content.set_value(&mut data, 0, 0, &37);
+        content.set_value(&mut data, 0, 0, &36);
+        history.borrow_mut().set_editing(false);
+
+        assert!(!history.borrow().can_undo());
+    }
+}
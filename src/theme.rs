@@ -0,0 +1,130 @@
+//! Keys to style tables, unset keys fall back to the druid theme.
+use druid::{Color, Env, Key};
+use druid::theme;
+
+pub const GRID_COLOR: Key<Color> = Key::new("druid-table.grid-color");
+/// Width of the grid lines, which is also the space between two cells.
+pub const GRID_WIDTH: Key<f64> = Key::new("druid-table.grid-width");
+pub const HEADER_BACKGROUND: Key<Color> = Key::new("druid-table.header-background");
+pub const SELECTION_COLOR: Key<Color> = Key::new("druid-table.selection-color");
+/// Overlay of the cell under the mouse, no cell is highlighted when unset.
+pub const HOVER_COLOR: Key<Color> = Key::new("druid-table.hover-color");
+/// Background of every second element.
+pub const ALTERNATE_ROW_COLOR: Key<Color> = Key::new("druid-table.alternate-row-color");
+/// Space between the border of a cell and its widget.
+pub const CELL_PADDING: Key<f64> = Key::new("druid-table.cell-padding");
+pub const FOCUS_RING_COLOR: Key<Color> = Key::new("druid-table.focus-ring-color");
+pub const FOCUS_RING_WIDTH: Key<f64> = Key::new("druid-table.focus-ring-width");
+/// Color of the marker of invalid cells.
+pub const ERROR_COLOR: Key<Color> = Key::new("druid-table.error-color");
+/// Size of the triangle in the corner of invalid cells.
+pub const ERROR_MARKER_SIZE: Key<f64> = Key::new("druid-table.error-marker-size");
+/// Space between the message of an invalid cell and the border of its tooltip.
+pub const TOOLTIP_PADDING: Key<f64> = Key::new("druid-table.tooltip-padding");
+/// Overlay of cells which differ from the baseline, see `Table::with_change_tracking`.
+pub const CHANGED_COLOR: Key<Color> = Key::new("druid-table.changed-color");
+pub const ADDED_COLOR: Key<Color> = Key::new("druid-table.added-color");
+/// Color of the marker where elements were removed.
+pub const REMOVED_COLOR: Key<Color> = Key::new("druid-table.removed-color");
+/// Space between the border of a cell and a chart painted into it, see `ChartTableLine`.
+pub const CHART_INSET: Key<f64> = Key::new("druid-table.chart-inset");
+/// Background of cells which match the search, see `Table::search`.
+pub const MATCH_COLOR: Key<Color> = Key::new("druid-table.match-color");
+pub const CURRENT_MATCH_COLOR: Key<Color> = Key::new("druid-table.current-match-color");
+/// Width and height of the square which is dragged to fill cells, see `Table::with_fill_handle`.
+pub const FILL_HANDLE_SIZE: Key<f64> = Key::new("druid-table.fill-handle-size");
+
+pub fn grid_color(env: &Env) -> Color {
+    env.try_get(GRID_COLOR).unwrap_or_else(|_|env.get(theme::BORDER_LIGHT))
+}
+
+pub fn grid_width(env: &Env) -> f64 {
+    env.try_get(GRID_WIDTH).unwrap_or(1.0)
+}
+
+pub fn header_background(env: &Env) -> Color {
+    env.try_get(HEADER_BACKGROUND).unwrap_or(Color::TRANSPARENT)
+}
+
+pub fn selection_color(env: &Env) -> Color {
+    env.try_get(SELECTION_COLOR).unwrap_or_else(|_|env.get(theme::PRIMARY_DARK).with_alpha(0.3))
+}
+
+pub fn hover_color(env: &Env) -> Color {
+    env.try_get(HOVER_COLOR).unwrap_or(Color::TRANSPARENT)
+}
+
+pub fn alternate_row_color(env: &Env) -> Color {
+    env.try_get(ALTERNATE_ROW_COLOR).unwrap_or_else(|_|env.get(theme::TEXT_COLOR).with_alpha(0.04))
+}
+
+pub fn cell_padding(env: &Env) -> f64 {
+    env.try_get(CELL_PADDING).unwrap_or(0.0)
+}
+
+pub fn focus_ring_color(env: &Env) -> Color {
+    env.try_get(FOCUS_RING_COLOR).unwrap_or_else(|_|env.get(theme::PRIMARY_LIGHT))
+}
+
+pub fn focus_ring_width(env: &Env) -> f64 {
+    env.try_get(FOCUS_RING_WIDTH).unwrap_or(2.0)
+}
+
+pub fn error_color(env: &Env) -> Color {
+    env.try_get(ERROR_COLOR).unwrap_or_else(|_|Color::rgb8(0xE0, 0x30, 0x30))
+}
+
+pub fn error_marker_size(env: &Env) -> f64 {
+    env.try_get(ERROR_MARKER_SIZE).unwrap_or(6.0)
+}
+
+pub fn tooltip_padding(env: &Env) -> f64 {
+    env.try_get(TOOLTIP_PADDING).unwrap_or(4.0)
+}
+
+pub fn changed_color(env: &Env) -> Color {
+    env.try_get(CHANGED_COLOR).unwrap_or_else(|_|Color::rgba8(0xE0, 0xB0, 0x20, 0x40))
+}
+
+pub fn added_color(env: &Env) -> Color {
+    env.try_get(ADDED_COLOR).unwrap_or_else(|_|Color::rgba8(0x30, 0xB0, 0x40, 0x30))
+}
+
+pub fn removed_color(env: &Env) -> Color {
+    env.try_get(REMOVED_COLOR).unwrap_or_else(|_|error_color(env))
+}
+
+pub fn chart_inset(env: &Env) -> f64 {
+    env.try_get(CHART_INSET).unwrap_or(2.0)
+}
+
+pub fn match_color(env: &Env) -> Color {
+    env.try_get(MATCH_COLOR).unwrap_or_else(|_|Color::rgba8(0xF0, 0xD0, 0x30, 0x50))
+}
+
+pub fn current_match_color(env: &Env) -> Color {
+    env.try_get(CURRENT_MATCH_COLOR).unwrap_or_else(|_|Color::rgba8(0xF0, 0x90, 0x20, 0x90))
+}
+
+pub fn fill_handle_size(env: &Env) -> f64 {
+    env.try_get(FILL_HANDLE_SIZE).unwrap_or(7.0)
+}
+
+/// Returns false for colors which would not change anything when painted.
+pub(crate) fn is_visible(color: &Color) -> bool {
+    color.as_rgba8().3 > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hover_is_only_painted_when_set() {
+        let env = Env::default();
+        assert!(!is_visible(&hover_color(&env)));
+
+        let env = env.adding(HOVER_COLOR, Color::BLACK.with_alpha(0.08));
+        assert!(is_visible(&hover_color(&env)));
+    }
+}
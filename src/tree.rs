@@ -0,0 +1,477 @@
+use std::any::Any;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use druid::{BoxConstraints, Env, Event, EventCtx, LayoutCtx, Lens, LifeCycle, LifeCycleCtx, PaintCtx, Point, Rect, Size, UpdateCtx, Widget, WidgetPod, Data, RenderContext};
+use druid::im::Vector;
+use druid::lens::Identity;
+use druid::theme;
+use druid::widget::ListIter;
+use crate::{TableLayout, TableLine, WidgetTableLine};
+use crate::line::{diff_line, line_texts, line_value, line_values, rejected_texts, same_line_value, set_line_texts, set_line_value, set_line_values};
+use crate::util::disclosure_triangle;
+
+pub trait TreeNode: Data {
+    fn children(&self) -> &Vector<Self>;
+
+    fn children_mut(&mut self) -> &mut Vector<Self>;
+
+    fn is_expanded(&self) -> bool;
+
+    fn set_expanded(&mut self, expanded: bool);
+}
+
+/// A visible node of a tree together with its depth.
+#[derive(Clone, Data, Lens)]
+pub struct TreeRow<N> {
+    #[lens(name = "node_lens")]
+    node: N,
+    #[lens(ignore)]
+    depth: usize,
+}
+
+/// The visible nodes of a tree in depth first order, descendants of collapsed nodes are skipped.
+#[derive(Clone, Data)]
+pub struct TreeList<N: TreeNode> {
+    roots: Vector<N>,
+}
+
+/// Lens adapter which exposes a `Vector` of root nodes as a flat `TreeList`.
+pub struct TreeLens<L> {
+    lens: L,
+}
+
+pub struct TreeCell<N, V, L, W> {
+    lens: L,
+    child: WidgetPod<V, W>,
+    indent: f64,
+    phantom: std::marker::PhantomData<N>,
+}
+
+pub struct TreeTableLine<
+    S: Data,
+    N: TreeNode,
+    V: Data,
+
+    L1: Lens<S, Vector<N>>,
+    L2: Lens<N, V>,
+    W: Widget<V>,
+> {
+    inner: WidgetTableLine<S, TreeList<N>, TreeRow<N>, TreeRow<N>, TreeLens<L1>, Identity, TreeCell<N, V, L2, W>>,
+    /// The values of the nodes, which are searched, filled and compared instead of the rows.
+    value_lens: RowValueLens<L2>,
+}
+
+/// Lens from a row to the value of its node.
+struct RowValueLens<L> {
+    lens: L,
+}
+
+fn visit<N: TreeNode>(nodes: &Vector<N>, depth: usize, index: &mut usize, cb: &mut dyn FnMut(&TreeRow<N>, usize)) {
+    for node in nodes.iter() {
+        let row = TreeRow {node: node.clone(), depth};
+        cb(&row, *index);
+        *index += 1;
+        if node.is_expanded() {
+            visit(node.children(), depth + 1, index, cb);
+        }
+    }
+}
+
+fn visit_mut<N: TreeNode>(nodes: &mut Vector<N>, depth: usize, index: &mut usize, cb: &mut dyn FnMut(&mut TreeRow<N>, usize)) {
+    for node in nodes.iter_mut() {
+        // Expanding or collapsing a node takes effect after the iteration, otherwise the indices
+        // would no longer match the widgets of the lines.
+        let expanded = node.is_expanded();
+
+        let mut row = TreeRow {node: node.clone(), depth};
+        cb(&mut row, *index);
+        *index += 1;
+        if !row.node.same(node) {
+            *node = row.node;
+        }
+
+        if expanded {
+            visit_mut(node.children_mut(), depth + 1, index, cb);
+        }
+    }
+}
+
+fn visible_count<N: TreeNode>(nodes: &Vector<N>) -> usize {
+    nodes.iter()
+        .map(|node|1 + if node.is_expanded() {visible_count(node.children())} else {0})
+        .sum()
+}
+
+impl<N> TreeRow<N> {
+    pub fn node(&self) -> &N {
+        &self.node
+    }
+
+    pub fn node_mut(&mut self) -> &mut N {
+        &mut self.node
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+impl<N: TreeNode> TreeRow<N> {
+    pub fn has_children(&self) -> bool {
+        !self.node.children().is_empty()
+    }
+}
+
+impl<N: TreeNode> TreeList<N> {
+    pub fn new(roots: Vector<N>) -> Self {
+        Self {
+            roots,
+        }
+    }
+
+    pub fn roots(&self) -> &Vector<N> {
+        &self.roots
+    }
+}
+
+impl<N: TreeNode> ListIter<TreeRow<N>> for TreeList<N> {
+    fn for_each(&self, mut cb: impl FnMut(&TreeRow<N>, usize)) {
+        visit(&self.roots, 0, &mut 0, &mut cb);
+    }
+
+    fn for_each_mut(&mut self, mut cb: impl FnMut(&mut TreeRow<N>, usize)) {
+        visit_mut(&mut self.roots, 0, &mut 0, &mut cb);
+    }
+
+    fn data_len(&self) -> usize {
+        visible_count(&self.roots)
+    }
+}
+
+impl<N, V, L: Lens<N, V>> Lens<TreeRow<N>, V> for RowValueLens<L> {
+    fn with<R, F: FnOnce(&V) -> R>(&self, data: &TreeRow<N>, f: F) -> R {
+        self.lens.with(&data.node, f)
+    }
+
+    fn with_mut<R, F: FnOnce(&mut V) -> R>(&self, data: &mut TreeRow<N>, f: F) -> R {
+        self.lens.with_mut(&mut data.node, f)
+    }
+}
+
+impl<L> TreeLens<L> {
+    pub fn new(lens: L) -> Self {
+        Self {
+            lens,
+        }
+    }
+}
+
+impl<S, N: TreeNode, L: Lens<S, Vector<N>>> Lens<S, TreeList<N>> for TreeLens<L> {
+    fn with<V, F: FnOnce(&TreeList<N>) -> V>(&self, data: &S, f: F) -> V {
+        self.lens.with(data, |roots|f(&TreeList::new(roots.clone())))
+    }
+
+    fn with_mut<V, F: FnOnce(&mut TreeList<N>) -> V>(&self, data: &mut S, f: F) -> V {
+        self.lens.with_mut(data, |roots|{
+            let mut list = TreeList::new(roots.clone());
+            let value = f(&mut list);
+            if !list.roots.same(roots) {
+                *roots = list.roots;
+            }
+            value
+        })
+    }
+}
+
+impl<N: TreeNode, V: Data, L: Lens<N, V>, W: Widget<V>> TreeCell<N, V, L, W> {
+    pub fn new(lens: L, child: W, indent: f64) -> Self {
+        Self {
+            lens,
+            child: WidgetPod::new(child),
+            indent,
+            phantom: Default::default(),
+        }
+    }
+
+    fn disclosure_rect(&self, data: &TreeRow<N>, height: f64) -> Rect {
+        Rect::from_origin_size(
+            Point::new(data.depth as f64 * self.indent, 0.0),
+            Size::new(self.indent, height.min(self.indent * 1.5)),
+        )
+    }
+}
+
+impl<N: TreeNode, V: Data, L: Lens<N, V>, W: Widget<V>> Widget<TreeRow<N>> for TreeCell<N, V, L, W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut TreeRow<N>, env: &Env) {
+        if let Event::MouseDown(mouse) = event {
+            if data.has_children() && self.disclosure_rect(data, ctx.size().height).contains(mouse.pos) {
+                let expanded = data.node.is_expanded();
+                data.node.set_expanded(!expanded);
+                ctx.set_handled();
+                return;
+            }
+        }
+
+        let Self {lens, child, ..} = self;
+        lens.with_mut(&mut data.node, |data|child.event(ctx, event, data, env));
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &TreeRow<N>, env: &Env) {
+        let Self {lens, child, ..} = self;
+        lens.with(&data.node, |data|child.lifecycle(ctx, event, data, env));
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &TreeRow<N>, data: &TreeRow<N>, env: &Env) {
+        if old_data.depth != data.depth {
+            ctx.request_layout();
+        }
+        if old_data.node.is_expanded() != data.node.is_expanded() || old_data.has_children() != data.has_children() {
+            ctx.request_paint();
+        }
+
+        let Self {lens, child, ..} = self;
+        lens.with(&data.node, |data|child.update(ctx, data, env));
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &TreeRow<N>, env: &Env) -> Size {
+        let offset = (data.depth + 1) as f64 * self.indent;
+
+        let Self {lens, child, ..} = self;
+        let child_size = lens.with(&data.node, |data|{
+            let size = child.layout(ctx, &bc.shrink(Size::new(offset, 0.0)), data, env);
+            child.set_origin(ctx, data, env, Point::new(offset, 0.0));
+            size
+        });
+
+        bc.constrain(Size::new(child_size.width + offset, child_size.height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &TreeRow<N>, env: &Env) {
+        if data.has_children() {
+            let rect = self.disclosure_rect(data, ctx.size().height);
+            let path = disclosure_triangle(rect.center(), self.indent / 4.0, data.node.is_expanded());
+            ctx.fill(path, &env.get(theme::TEXT_COLOR));
+        }
+
+        let Self {lens, child, ..} = self;
+        lens.with(&data.node, |data|child.paint(ctx, data, env));
+    }
+}
+
+impl<
+    S: Data,
+    N: TreeNode + 'static,
+    V: Data,
+
+    L1: Lens<S, Vector<N>>,
+    L2: Lens<N, V> + Clone + 'static,
+    W: Widget<V> + 'static,
+> TreeTableLine<S, N, V, L1, L2, W> {
+    pub fn new(lens: L1, inner_lens: L2, widget: impl Fn() -> W + 'static) -> Self {
+        Self::with_indent(lens, inner_lens, widget, 16.0)
+    }
+
+    pub fn with_indent(lens: L1, inner_lens: L2, widget: impl Fn() -> W + 'static, indent: f64) -> Self {
+        let value_lens = RowValueLens {lens: inner_lens.clone()};
+        Self {
+            inner: WidgetTableLine::new(TreeLens::new(lens), Identity, move||{
+                TreeCell::new(inner_lens.clone(), widget(), indent)
+            }),
+            value_lens,
+        }
+    }
+
+    /// Lets the table write text into the values of the nodes, e.g. when replacing or pasting text.
+    pub fn with_editable(mut self, editable: bool) -> Self {
+        self.inner = self.inner.with_editable(editable);
+        self
+    }
+}
+
+impl<
+    S: Data,
+    N: TreeNode + 'static,
+    V: Data,
+
+    L1: Lens<S, Vector<N>>,
+    L2: Lens<N, V> + 'static,
+    W: Widget<V> + 'static,
+> TableLine<S> for TreeTableLine<S, N, V, L1, L2, W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut S, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.inner.event(ctx, event, data, env, meta, line_index);
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &S, env: &Env) {
+        self.inner.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, data: &S, env: &Env) {
+        self.inner.update(ctx, data, env);
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.inner.paint(ctx, data, env, meta, line_index);
+    }
+
+    fn paint_background(&mut self, ctx: &mut PaintCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.inner.paint_background(ctx, data, env, meta, line_index);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &mut TableLayout, line_index: usize) {
+        self.inner.layout(ctx, data, env, meta, line_index);
+    }
+
+    fn arrange(&mut self, ctx: &mut LayoutCtx, data: &S, env: &Env, meta: &TableLayout, line_index: usize) {
+        self.inner.arrange(ctx, data, env, meta, line_index);
+    }
+
+    fn element_count(&self, data: &S) -> usize {
+        self.inner.element_count(data)
+    }
+
+    fn move_element(&mut self, from: usize, to: usize) {
+        self.inner.move_element(from, to);
+    }
+
+    fn insert_element(&mut self, index: usize) {
+        self.inner.insert_element(index);
+    }
+
+    fn remove_element(&mut self, index: usize) {
+        self.inner.remove_element(index);
+    }
+
+    fn accepts_text(&self) -> bool {
+        self.inner.accepts_text()
+    }
+
+    fn needs_cursor(&self) -> bool {
+        self.inner.needs_cursor()
+    }
+
+    fn validate(&self, data: &S) -> Vec<(usize, String)> {
+        self.inner.validate(data)
+    }
+
+    fn changes(&self, baseline: &S, data: &S, elements: &[Option<usize>]) -> Vec<(usize, Rc<dyn Any>, Rc<dyn Any>)> {
+        let (outer_lens, value_lens) = (self.inner.outer_lens(), &self.value_lens);
+        outer_lens.with(baseline, |baseline|outer_lens.with(data, |data|diff_line(baseline, data, value_lens, elements)))
+    }
+
+    fn same_value(&self, a: &dyn Any, b: &dyn Any) -> bool {
+        same_line_value::<V>(a, b)
+    }
+
+    fn value(&self, data: &S, element: usize) -> Option<Rc<dyn Any>> {
+        self.inner.outer_lens().with(data, |data|line_value(data, &self.value_lens, element))
+    }
+
+    fn set_value(&mut self, data: &mut S, element: usize, value: &dyn Any) -> bool {
+        self.inner.outer_lens().with_mut(data, |data|set_line_value(data, &self.value_lens, element, value))
+    }
+
+    fn values(&self, data: &S, elements: RangeInclusive<usize>) -> Vec<(usize, Rc<dyn Any>)> {
+        self.inner.outer_lens().with(data, |data|line_values(data, &self.value_lens, elements))
+    }
+
+    fn set_values(&mut self, data: &mut S, values: &[(usize, Rc<dyn Any>)]) -> usize {
+        self.inner.outer_lens().with_mut(data, |data|set_line_values(data, &self.value_lens, values))
+    }
+
+    fn texts(&self, data: &S) -> Vec<(usize, String)> {
+        self.inner.outer_lens().with(data, |data|line_texts(data, &self.value_lens, None))
+    }
+
+    fn set_texts(&mut self, data: &mut S, texts: &[(usize, String)]) -> Vec<(usize, Result<(), String>)> {
+        if !self.inner.accepts_text() {
+            return rejected_texts(texts);
+        }
+        self.inner.outer_lens().with_mut(data, |data|set_line_texts(data, &self.value_lens, texts, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use druid::widget::{Axis, Label};
+    use crate::{AxisPart, SearchPattern};
+    use crate::search::TableSearch;
+    use super::*;
+
+    #[derive(Clone, Data, Lens)]
+    struct Node {
+        name: Arc<String>,
+        expanded: bool,
+        children: Vector<Node>,
+    }
+
+    impl Node {
+        fn new(name: &str, expanded: bool, children: Vec<Node>) -> Self {
+            Self {
+                name: Arc::new(name.to_string()),
+                expanded,
+                children: children.into(),
+            }
+        }
+    }
+
+    impl TreeNode for Node {
+        fn children(&self) -> &Vector<Self> {
+            &self.children
+        }
+
+        fn children_mut(&mut self) -> &mut Vector<Self> {
+            &mut self.children
+        }
+
+        fn is_expanded(&self) -> bool {
+            self.expanded
+        }
+
+        fn set_expanded(&mut self, expanded: bool) {
+            self.expanded = expanded;
+        }
+    }
+
+    #[test]
+    fn search_finds_visible_nodes() {
+        let data: Vector<Node> = vec![
+            Node::new("apple", true, vec![Node::new("banana", false, vec![]), Node::new("cherry", false, vec![])]),
+            Node::new("grape", false, vec![Node::new("blueberry", false, vec![])]),
+        ].into();
+        let line: Box<dyn TableLine<Vector<Node>>> = Box::new(
+            TreeTableLine::new(Identity, Node::name, ||Label::dynamic(|name: &Arc<String>, _: &Env|name.to_string()))
+        );
+
+        let mut layout = TableLayout::new(Axis::Vertical);
+        layout.lines_mut().set_length(1, AxisPart::new(None));
+        layout.elements_mut().set_length(line.element_count(&data), AxisPart::new(None));
+
+        let lines = vec![line];
+        let mut search = TableSearch::new(SearchPattern::text("B"));
+        search.find(&lines, &data, &layout);
+        // The collapsed "blueberry" is not a row of the table
+        assert_eq!(search.matches(), &[(0, 1)]);
+
+        let mut search = TableSearch::new(SearchPattern::text("e"));
+        search.find(&lines, &data, &layout);
+        assert_eq!(search.matches(), &[(0, 0), (0, 2), (0, 3)]);
+    }
+
+    #[test]
+    fn values_are_written_into_the_nodes() {
+        let mut data: Vector<Node> = vec![
+            Node::new("apple", true, vec![Node::new("banana", false, vec![])]),
+        ].into();
+        let mut line = TreeTableLine::new(Identity, Node::name, ||Label::new("")).with_editable(true);
+
+        let value = line.value(&data, 0).unwrap();
+        assert_eq!(line.set_values(&mut data, &[(1, value)]), 1);
+        assert_eq!(data[0].children[0].name.as_str(), "apple");
+
+        let results = line.set_texts(&mut data, &[(0, "pear".to_string())]);
+        assert_eq!(results, vec![(0, Ok(()))]);
+        assert_eq!(data[0].name.as_str(), "pear");
+        assert!(data[0].expanded);
+    }
+}
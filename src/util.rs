@@ -1,3 +1,5 @@
+use druid::Point;
+use druid::kurbo::BezPath;
 
 pub fn set_len<T, F: Fn() -> T>(list: &mut Vec<T>, new_len: usize, f: F) {
     if list.len() > new_len {
@@ -7,4 +9,102 @@ pub fn set_len<T, F: Fn() -> T>(list: &mut Vec<T>, new_len: usize, f: F) {
             list.push(f());
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn disclosure_triangle(center: Point, radius: f64, expanded: bool) -> BezPath {
+    let mut path = BezPath::new();
+    if expanded {
+        path.move_to((center.x - radius, center.y - radius / 2.0));
+        path.line_to((center.x + radius, center.y - radius / 2.0));
+        path.line_to((center.x, center.y + radius));
+    } else {
+        path.move_to((center.x - radius / 2.0, center.y - radius));
+        path.line_to((center.x + radius, center.y));
+        path.line_to((center.x - radius / 2.0, center.y + radius));
+    }
+    path.close_path();
+    path
+}
+
+/// Returns the moves (remove at `from`, insert at `to`) which reorder `current` into `order`.
+/// Ids which are not part of `current` are ignored.
+pub fn order_moves(current: &[usize], order: &[usize]) -> Vec<(usize, usize)> {
+    let mut current = current.to_vec();
+    let mut moves = vec![];
+    let mut target = 0;
+    for id in order {
+        if let Some(from) = current[target..].iter().position(|other|other == id).map(|index|index + target) {
+            if from != target {
+                let id = current.remove(from);
+                current.insert(target, id);
+                moves.push((from, target));
+            }
+            target += 1;
+        }
+    }
+    moves
+}
+
+/// Moves the item at `from` to `to`, does nothing if one of the indices is out of bounds.
+pub fn move_item<T>(list: &mut Vec<T>, from: usize, to: usize) {
+    if from < list.len() && to < list.len() {
+        let item = list.remove(from);
+        list.insert(to, item);
+    }
+}
+
+/// The index of an item after the item at `from` was moved to `to` by `move_item`.
+pub fn moved_index(index: usize, from: usize, to: usize) -> usize {
+    if index == from {
+        to
+    } else if from < index && index <= to {
+        index - 1
+    } else if to <= index && index < from {
+        index + 1
+    } else {
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_item_ignores_out_of_bounds() {
+        let mut list = vec![0, 1, 2, 3];
+        move_item(&mut list, 0, 2);
+        assert_eq!(list, vec![1, 2, 0, 3]);
+        move_item(&mut list, 3, 0);
+        assert_eq!(list, vec![3, 1, 2, 0]);
+        move_item(&mut list, 4, 0);
+        move_item(&mut list, 0, 4);
+        assert_eq!(list, vec![3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn moved_index_follows_move_item() {
+        for &(from, to) in &[(0, 3), (3, 0), (1, 2), (2, 2)] {
+            let mut list = vec![0, 1, 2, 3];
+            move_item(&mut list, from, to);
+            for index in 0..4 {
+                assert_eq!(list[moved_index(index, from, to)], index);
+            }
+        }
+    }
+
+    #[test]
+    fn order_moves_reorder_current() {
+        let current = vec![0, 1, 2, 3];
+        let order = vec![2, 0, 3, 1];
+        let mut list = current.clone();
+        for (from, to) in order_moves(&current, &order) {
+            move_item(&mut list, from, to);
+        }
+        assert_eq!(list, order);
+
+        // Unknown ids are ignored, missing ids keep their relative order at the end
+        assert_eq!(order_moves(&current, &[7, 3]), vec![(3, 0)]);
+        assert!(order_moves(&current, &current).is_empty());
+    }
+}